@@ -1,3 +1,76 @@
-fn main() {
-    println!("editor app");
+//! `editor-app <world dir> [--key value ...]`: opens a world directory, or creates it
+//! with the given settings, and edits it.
+
+use std::process::ExitCode;
+
+use bevy::prelude::*;
+
+use editor::{EditorCamera, EditorPlugin};
+use engine::terrain::{
+    config::WorldConfig,
+    generator::ChunkLoading,
+    plugins::{default_asset_root, VoxelTerrainPlugin},
+    storage::WorldSave,
+};
+
+const USAGE: &str = "usage: editor-app <world dir> [--seed <n>] [--view-radius <n>] [--generator flat] ...";
+
+/// The world directory, then the config: defaults, `VOXEL_*` variables, `--key value` arguments
+fn open_world() -> Result<WorldSave, String> {
+    let mut args = std::env::args().skip(1);
+    let dir = args.next().filter(|dir| !dir.starts_with("--")).ok_or(USAGE)?;
+    let mut config = WorldConfig::default();
+    config.apply_env()?;
+    config.apply_args(args)?;
+    WorldSave::open(dir, &config)
+}
+
+fn main() -> ExitCode {
+    let world = match open_world() {
+        Ok(world) => world,
+        Err(message) => {
+            eprintln!("{message}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let generator = match world.generator(&default_asset_root()) {
+        Ok(generator) => generator,
+        Err(e) => {
+            eprintln!("cannot read {}: {e}", world.store().root().display());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    App::new()
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: format!("Editor - {}", world.store().root().display()),
+                ..default()
+            }),
+            ..default()
+        }))
+        .add_plugins(
+            VoxelTerrainPlugin::new(world.config().clone())
+                .with_generator(generator)
+                .with_loading(ChunkLoading::Manual),
+        )
+        .add_plugins(EditorPlugin::new(world))
+        .add_systems(Startup, setup)
+        .run();
+    ExitCode::SUCCESS
+}
+
+fn setup(mut commands: Commands) {
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 10_000.0,
+            ..default()
+        },
+        Transform::from_xyz(0.0, 100.0, 0.0).looking_at(Vec3::new(0.3, 0.0, 0.5), Vec3::Y),
+    ));
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 90.0, 40.0).looking_at(Vec3::new(0.0, 60.0, 0.0), Vec3::Y),
+        EditorCamera::default(),
+    ));
 }
@@ -1,68 +1,26 @@
-#[derive(Component)]
-struct FreeCam {
-    sensitivity: f32,
-    speed: f32,
-}
+use std::path::Path;
 
 use bevy::{
     input::mouse::{MouseMotion, MouseWheel},
-    pbr::wireframe::WireframePlugin,
     prelude::*,
     render::{
-        settings::{Backends, RenderCreation, WgpuFeatures, WgpuSettings},
+        settings::{Backends, RenderCreation, WgpuSettings},
         RenderPlugin,
     },
 };
-use bevy_egui::{egui, EguiContexts, EguiPlugin, EguiPrimaryContextPass};
 
 use engine::debug::*;
+use audio::weather::WeatherAudioPlugin;
+use audio::{GameAudioPlugin, SoundListener};
+use engine::environment::{EnvironmentPlugin, WeatherPlugin};
 
-// Import TerrainPlugin from your engine or define it if missing
-use engine::terrain::plugins::{TerrainPlugin, TerrainTaskPlugin};
-
-fn draw_grid(mut gizmos: Gizmos) {
-    let cell_count = UVec2::new(20, 20);
-    let spacing = Vec2::splat(1.0);
-
-    // XZ Plane (Floor)
-    gizmos.grid(
-        Quat::IDENTITY,
-        cell_count,
-        spacing,
-        Color::srgb(0.0, 1.0, 0.0),
-    );
-
-    // YZ Plane (Side Wall)
-    gizmos.grid(
-        Quat::from_rotation_z(std::f32::consts::FRAC_PI_2),
-        cell_count,
-        spacing,
-        Color::srgb(1.0, 0.0, 0.0),
-    );
-
-    // XY Plane (Back Wall)
-    gizmos.grid(
-        Quat::from_rotation_x(std::f32::consts::FRAC_PI_2),
-        cell_count,
-        spacing,
-        Color::srgb(0.0, 0.0, 1.0),
-    );
-}
-
-fn tweak_camera(mut query: Query<&mut Projection, With<Camera>>) {
-    for mut projection in &mut query {
-        if let Projection::Perspective(ref mut p) = *projection {
-            p.near = 1.0;
-            p.far = 0.2;
-        }
-    }
-}
+use engine::terrain::plugins::{default_asset_root, VoxelTerrainPlugin};
+use engine::terrain::storage::{ArchiveGenerator, WorldSave};
 
 #[derive(Component)]
 struct PanOrbitCamera {
     pub focus: Vec3,
     pub radius: f32,
-    pub upside_down: bool,
 }
 
 fn pan_orbit_camera(
@@ -103,7 +61,7 @@ fn pan_orbit_camera(
             let pitch = Quat::from_rotation_x(-delta_y);
 
             transform.rotation = yaw * transform.rotation;
-            transform.rotation = transform.rotation * pitch;
+            transform.rotation *= pitch;
         }
 
         // --- Pan ---
@@ -120,61 +78,89 @@ fn pan_orbit_camera(
     }
 }
 
-fn ui_example_system(mut contexts: EguiContexts) -> Result {
-    egui::Window::new("Hello").show(contexts.ctx_mut()?, |ui| {
-        ui.label("world");
-    });
-    Ok(())
+fn exit_with(message: String) -> ! {
+    eprintln!("{message}");
+    std::process::exit(2);
+}
+
+/// Removes `--name <value>` from the arguments
+fn take_arg(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let at = args.iter().position(|arg| arg == name)?;
+    if at + 1 == args.len() {
+        exit_with(format!("missing value for `{name}`"));
+    }
+    args.drain(at..at + 2).nth(1)
+}
+
+/// Defaults, or the settings of the level given with `--level <file.vxl>`, then `VOXEL_*`
+/// variables, then `--key value` arguments. A level keeps its own generator. With
+/// `--world <dir>` the world directory is opened, or created with these settings.
+fn terrain_plugin() -> VoxelTerrainPlugin {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let level = take_arg(&mut args, "--level")
+        .map(|path| ArchiveGenerator::open(Path::new(&path), &default_asset_root()).unwrap_or_else(|e| exit_with(e)));
+    let world = take_arg(&mut args, "--world");
+    if level.is_some() && world.is_some() {
+        exit_with("`--level` and `--world` cannot be used together".into());
+    }
+
+    let mut config = level.as_ref().map(|level| level.config().clone()).unwrap_or_default();
+    if let Err(e) = config.apply_env().and_then(|_| config.apply_args(args)) {
+        exit_with(e);
+    }
+    if let Some(dir) = world {
+        let world = WorldSave::open(dir, &config).unwrap_or_else(|e| exit_with(e));
+        let generator = world
+            .generator(&default_asset_root())
+            .unwrap_or_else(|e| exit_with(format!("cannot read {}: {e}", world.store().root().display())));
+        return VoxelTerrainPlugin::new(world.config().clone()).with_generator(generator);
+    }
+    let plugin = VoxelTerrainPlugin::new(config);
+    match level {
+        Some(level) => plugin.with_generator(level),
+        None => plugin,
+    }
 }
 
 fn main() {
     App::new()
-        .add_plugins(DefaultPlugins.set(RenderPlugin {
-            render_creation: RenderCreation::Automatic(WgpuSettings {
-                backends: Some(Backends::PRIMARY | Backends::GL),
-                features: WgpuFeatures::POLYGON_MODE_LINE,
-                ..default()
-            }),
-            ..default()
-        }))
-        .add_plugins(WireframePlugin::default())
-        .add_plugins(TerrainPlugin)
-        .add_plugins(TerrainTaskPlugin)
+        .add_plugins(
+            DefaultPlugins
+                .set(RenderPlugin {
+                    render_creation: RenderCreation::Automatic(WgpuSettings {
+                        backends: Some(Backends::PRIMARY | Backends::GL),
+                        ..default()
+                    }),
+                    ..default()
+                })
+                .set(AssetPlugin {
+                    watch_for_changes_override: Some(cfg!(feature = "dev")),
+                    ..default()
+                }),
+        )
+        .add_plugins(terrain_plugin())
+        .add_plugins((TerrainDebugPlugin, TerrainTuningPlugin))
+        .add_plugins(EnvironmentPlugin::default())
+        .add_plugins(WeatherPlugin::default().with_change_interval(180.0))
+        // The sandbox ships no sound files, the null backend only records what would play
+        .add_plugins((GameAudioPlugin::null(), WeatherAudioPlugin::default()))
         .add_systems(Startup, setup)
-        .add_systems(Update, (tweak_camera, pan_orbit_camera))
-        // Note: You can add Egui back here if needed
-        //.add_systems(Startup, (setup, spawn_test_chunk, spawn_test_chunk_greedy))
-        //.add_systems(Update, (draw_grid, tweak_camera, pan_orbit_camera))
+        .add_systems(Update, pan_orbit_camera)
         .run();
 }
 
 fn setup(mut commands: Commands) {
-    // Light
-    commands.spawn((
-        PointLight {
-            shadows_enabled: true,
-            intensity: 10_000_000.0,
-            ..default()
-        },
-        Transform::from_xyz(4.0, 20.0, 4.0),
-    ));
-
     // Camera
     let translation = Vec3::new(-10.0, 20.0, 40.0);
     let focus = Vec3::new(16., 0., 16.);
 
     commands.spawn((
         Camera3d::default(),
+        SoundListener,
         Transform::from_translation(translation).looking_at(focus, Vec3::Y),
-        // ADD THIS: The system won't run without this component!
         PanOrbitCamera {
             focus,
             radius: translation.distance(focus),
-            upside_down: false,
-        },
-        FreeCam {
-            sensitivity: 0.003,
-            speed: 20.0,
         },
     ));
 }
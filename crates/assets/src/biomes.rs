@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+
+use bevy::asset::Asset;
+use bevy::reflect::TypePath;
+use serde::{Deserialize, Serialize};
+
+use crate::noise_graph::NoiseGraph;
+
+/// Biome table source, `*.biomes.ron`. Climate noises are separate `*.noise.ron` files,
+/// relative to the table, so editing one of them reloads the table too:
+///
+/// ```ron
+/// (
+///     climate: { "temperature": "temperature.noise.ron", "humidity": "humidity.noise.ron" },
+///     biomes: [
+///         (name: "Desert", surface: 4, subsurface: 4, temperature: (0.4, 1.0), humidity: (-1.0, 0.0)),
+///         (name: "Plains", surface: 2, subsurface: 3),
+///     ],
+/// )
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BiomeTableSource {
+    #[serde(default)]
+    pub climate: BTreeMap<String, String>,
+    pub biomes: Vec<Biome>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Biome {
+    pub name: String,
+    /// Block id of the top layer
+    pub surface: u8,
+    /// Block id of the few layers below the surface
+    pub subsurface: u8,
+    /// Climate ranges this biome covers, inclusive
+    #[serde(default = "full_range")]
+    pub temperature: (f64, f64),
+    #[serde(default = "full_range")]
+    pub humidity: (f64, f64),
+    #[serde(default = "default_height_scale")]
+    pub height_scale: f32,
+}
+
+fn full_range() -> (f64, f64) {
+    (f64::NEG_INFINITY, f64::INFINITY)
+}
+
+fn default_height_scale() -> f32 {
+    1.0
+}
+
+/// Loaded biome table, with its climate noises resolved
+#[derive(Asset, TypePath, Debug, Clone, PartialEq)]
+pub struct BiomeTable {
+    pub climate: BTreeMap<String, NoiseGraph>,
+    pub biomes: Vec<Biome>,
+}
+
+impl BiomeTable {
+    /// Reads a table and its climate noises from disk, noise paths relative to the table
+    pub fn load(path: &std::path::Path) -> Result<Self, String> {
+        let read = |path: &std::path::Path| {
+            std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))
+        };
+        let source: BiomeTableSource =
+            ron::from_str(&read(path)?).map_err(|e| format!("invalid biome table {}: {e}", path.display()))?;
+        let dir = path.parent().unwrap_or(std::path::Path::new(""));
+        let mut climate = BTreeMap::new();
+        for (name, noise) in source.climate {
+            let noise = dir.join(noise);
+            let graph: NoiseGraph =
+                ron::from_str(&read(&noise)?).map_err(|e| format!("invalid noise graph {}: {e}", noise.display()))?;
+            climate.insert(name, graph);
+        }
+        Ok(Self { climate, biomes: source.biomes })
+    }
+
+    /// First biome whose ranges contain the climate, the last one is the fallback
+    pub fn select(&self, temperature: f64, humidity: f64) -> Option<&Biome> {
+        self.biomes
+            .iter()
+            .find(|b| {
+                (b.temperature.0..=b.temperature.1).contains(&temperature)
+                    && (b.humidity.0..=b.humidity.1).contains(&humidity)
+            })
+            .or(self.biomes.last())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_first_matching_biome() {
+        let source: BiomeTableSource = ron::from_str(
+            r#"(
+                climate: { "temperature": "t.noise.ron" },
+                biomes: [
+                    (name: "Desert", surface: 4, subsurface: 4, temperature: (0.4, 1.0), humidity: (-1.0, 0.0)),
+                    (name: "Plains", surface: 2, subsurface: 3),
+                ],
+            )"#,
+        )
+        .unwrap();
+        assert_eq!(source.climate["temperature"], "t.noise.ron");
+
+        let table = BiomeTable {
+            climate: BTreeMap::new(),
+            biomes: source.biomes,
+        };
+        assert_eq!(table.select(0.5, -0.5).unwrap().name, "Desert");
+        assert_eq!(table.select(0.5, 0.5).unwrap().name, "Plains");
+        assert_eq!(table.select(-2.0, 0.0).unwrap().height_scale, 1.0);
+    }
+
+    #[test]
+    fn loads_noises_next_to_the_table() {
+        let dir = std::env::temp_dir().join(format!("biome-table-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("climate")).unwrap();
+        std::fs::write(dir.join("climate/t.noise.ron"), "(root: Constant(0.5))").unwrap();
+        std::fs::write(
+            dir.join("world.biomes.ron"),
+            r#"(climate: { "temperature": "climate/t.noise.ron" }, biomes: [(name: "Plains", surface: 2, subsurface: 3)])"#,
+        )
+        .unwrap();
+
+        let table = BiomeTable::load(&dir.join("world.biomes.ron")).unwrap();
+        assert_eq!(table.climate["temperature"].build(0).sample(1.0, 2.0), 0.5);
+        std::fs::remove_file(dir.join("climate/t.noise.ron")).unwrap();
+        assert!(BiomeTable::load(&dir.join("world.biomes.ron")).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+
+use bevy::asset::Asset;
+use bevy::reflect::TypePath;
+use serde::{Deserialize, Serialize};
+
+/// Face order used by `CompiledBlock::faces`: +X, -X, +Y, -Y, +Z, -Z
+pub const FACE_NAMES: [&str; 6] = ["east", "west", "top", "bottom", "south", "north"];
+
+/// Source block definition, one `*.block.ron` file per block:
+///
+/// ```ron
+/// (
+///     id: 2,
+///     name: "Grass",
+///     solid: true,
+///     sound: Some("grass"),
+///     textures: { "top": "grass_top.png", "bottom": "dirt.png", "side": "grass_side.png" },
+/// )
+/// ```
+///
+/// Texture keys are a face name from `FACE_NAMES`, `side` (the four horizontal faces)
+/// or `all`. The most specific key wins. Paths are relative to the content directory.
+/// `sound` names the group of step, break and place sounds, see the `audio` crate.
+/// `smooth: true` blocks are drawn as smooth terrain by meshers that mix both shapes.
+#[derive(Asset, TypePath, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockDefinition {
+    pub id: u8,
+    pub name: String,
+    #[serde(default = "default_solid")]
+    pub solid: bool,
+    #[serde(default)]
+    pub smooth: bool,
+    #[serde(default)]
+    pub sound: Option<String>,
+    #[serde(default)]
+    pub textures: BTreeMap<String, String>,
+}
+
+fn default_solid() -> bool {
+    true
+}
+
+impl BlockDefinition {
+    /// Texture path per face, in `FACE_NAMES` order. `None` when the block has no textures.
+    pub fn face_textures(&self) -> Result<Option<[String; 6]>, String> {
+        if self.textures.is_empty() {
+            return Ok(None);
+        }
+        for key in self.textures.keys() {
+            if key != "all" && key != "side" && !FACE_NAMES.contains(&key.as_str()) {
+                return Err(format!("block `{}`: unknown face `{key}`", self.name));
+            }
+        }
+
+        let mut faces: [String; 6] = Default::default();
+        for (face, name) in FACE_NAMES.iter().enumerate() {
+            let horizontal = face != 2 && face != 3;
+            let texture = self
+                .textures
+                .get(*name)
+                .or_else(|| horizontal.then(|| self.textures.get("side")).flatten())
+                .or_else(|| self.textures.get("all"))
+                .ok_or_else(|| format!("block `{}`: no texture for face `{name}`", self.name))?;
+            faces[face] = texture.clone();
+        }
+        Ok(Some(faces))
+    }
+}
+
+pub const MANIFEST_VERSION: u32 = 1;
+pub const MANIFEST_FILE: &str = "blocks.manifest.ron";
+
+/// Output of the asset compiler, loaded by the engine's block registry
+#[derive(Asset, TypePath, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockManifest {
+    pub version: u32,
+    /// Hash of every input, the compiler skips the build when it did not change
+    pub source_hash: String,
+    /// Texture array container, next to the manifest
+    pub texture_array: String,
+    pub tile_size: u32,
+    pub mip_levels: u32,
+    pub layers: u32,
+    pub blocks: Vec<CompiledBlock>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompiledBlock {
+    pub id: u8,
+    pub name: String,
+    pub solid: bool,
+    #[serde(default)]
+    pub smooth: bool,
+    /// Sound group, missing from manifests compiled before it existed
+    #[serde(default)]
+    pub sound: Option<String>,
+    /// Texture array layer per face, in `FACE_NAMES` order
+    pub faces: Option<[u32; 6]>,
+}
+
+impl BlockManifest {
+    pub fn from_ron(text: &str) -> Result<Self, String> {
+        let manifest: Self = ron::from_str(text).map_err(|e| format!("invalid block manifest: {e}"))?;
+        if manifest.version != MANIFEST_VERSION {
+            return Err(format!("unsupported block manifest version {}", manifest.version));
+        }
+        Ok(manifest)
+    }
+
+    pub fn to_ron(&self) -> String {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .expect("manifest is always serializable")
+    }
+
+    pub fn load(path: &std::path::Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        Self::from_ron(&text)
+    }
+}
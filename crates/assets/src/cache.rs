@@ -0,0 +1,93 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Compiled artifacts on disk, keyed by a blake3 hash of everything they were built from.
+/// The same input always maps to the same file, so stale entries are never read back.
+#[derive(Debug, Clone)]
+pub struct ArtifactCache {
+    root: PathBuf,
+    extension: &'static str,
+}
+
+impl ArtifactCache {
+    pub fn new(root: impl Into<PathBuf>, extension: &'static str) -> Self {
+        Self {
+            root: root.into(),
+            extension,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Hash of the inputs, each part is length-prefixed so `["ab", "c"]` and `["a", "bc"]` differ
+    pub fn key(parts: &[&[u8]]) -> String {
+        let mut hasher = blake3::Hasher::new();
+        for part in parts {
+            hasher.update(&(part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        }
+        hasher.finalize().to_hex().to_string()
+    }
+
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.root.join(format!("{key}.{}", self.extension))
+    }
+
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        fs::read(self.path(key)).ok()
+    }
+
+    /// Writes through a temporary file, readers never see a partial artifact
+    pub fn put(&self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        fs::create_dir_all(&self.root)?;
+        let path = self.path(key);
+        let tmp = path.with_extension(format!("{}.tmp", self.extension));
+        fs::write(&tmp, bytes)?;
+        fs::rename(tmp, path)
+    }
+
+    /// Cached artifact for `key`, built and stored on a miss. The flag is true on a hit.
+    pub fn get_or_build<E>(
+        &self,
+        key: &str,
+        build: impl FnOnce() -> Result<Vec<u8>, E>,
+    ) -> Result<(Vec<u8>, bool), E>
+    where
+        E: From<io::Error>,
+    {
+        if let Some(bytes) = self.get(key) {
+            return Ok((bytes, true));
+        }
+        let bytes = build()?;
+        self.put(key, &bytes)?;
+        Ok((bytes, false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_once_per_key() {
+        let dir = std::env::temp_dir().join(format!("artifact-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let cache = ArtifactCache::new(&dir, "bin");
+
+        let key = ArtifactCache::key(&[b"ab", b"c"]);
+        assert_ne!(key, ArtifactCache::key(&[b"a", b"bc"]));
+
+        let (bytes, hit) = cache.get_or_build::<io::Error>(&key, || Ok(vec![1, 2, 3])).unwrap();
+        assert_eq!((bytes, hit), (vec![1, 2, 3], false));
+        let (bytes, hit) = cache
+            .get_or_build::<io::Error>(&key, || panic!("should come from the cache"))
+            .unwrap();
+        assert_eq!((bytes, hit), (vec![1, 2, 3], true));
+        assert!(cache.path(&key).ends_with(format!("{key}.bin")));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,344 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::blocks::{BlockDefinition, BlockManifest, CompiledBlock, MANIFEST_FILE, MANIFEST_VERSION};
+use crate::cache::ArtifactCache;
+use crate::texture_array::{mip_chain, mip_chain_len, mip_count, TextureArray};
+
+pub const TEXTURE_ARRAY_FILE: &str = "blocks.vxta";
+/// Processed mip chains by source content hash
+const CACHE_DIR: &str = "cache";
+/// Part of the source hash, bump when the compiled output changes for the same inputs
+const COMPILER_VERSION: u32 = 3;
+
+#[derive(Debug)]
+pub enum CompileError {
+    Io(PathBuf, io::Error),
+    Definition(PathBuf, String),
+    Texture(PathBuf, String),
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::Io(path, e) => write!(f, "{}: {e}", path.display()),
+            CompileError::Definition(path, e) => write!(f, "{}: {e}", path.display()),
+            CompileError::Texture(path, e) => write!(f, "{}: {e}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CompileReport {
+    pub blocks: usize,
+    pub layers: usize,
+    /// Textures decoded and mipmapped in this run
+    pub textures_built: usize,
+    /// Textures whose mip chain came from the cache
+    pub textures_cached: usize,
+    /// Nothing changed since the last build, outputs were left alone
+    pub up_to_date: bool,
+}
+
+fn io_err(path: &Path) -> impl FnOnce(io::Error) -> CompileError + '_ {
+    move |e| CompileError::Io(path.to_path_buf(), e)
+}
+
+/// Decodes a PNG to RGBA8, returns the pixels and the side length
+fn decode_png(path: &Path, bytes: &[u8]) -> Result<(Vec<u8>, u32), CompileError> {
+    let texture_err = |e: String| CompileError::Texture(path.to_path_buf(), e);
+
+    let mut decoder = png::Decoder::new(io::Cursor::new(bytes));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| texture_err(e.to_string()))?;
+    let mut buf = vec![0; reader.output_buffer_size().ok_or_else(|| texture_err("image too large".into()))?];
+    let info = reader.next_frame(&mut buf).map_err(|e| texture_err(e.to_string()))?;
+    buf.truncate(info.buffer_size());
+
+    if info.width != info.height || !info.width.is_power_of_two() {
+        return Err(texture_err(format!(
+            "textures must be square with a power-of-two size, got {}x{}",
+            info.width, info.height
+        )));
+    }
+
+    let pixels = (info.width * info.height) as usize;
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => buf,
+        png::ColorType::Rgb => buf.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => buf.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => buf.iter().flat_map(|g| [*g, *g, *g, 255]).collect(),
+        png::ColorType::Indexed => return Err(texture_err("palette was not expanded".into())),
+    };
+    debug_assert_eq!(rgba.len(), pixels * 4);
+    Ok((rgba, info.width))
+}
+
+/// Compiles every `*.block.ron` definition of `source` and the PNGs they reference
+/// into `out/blocks.manifest.ron` and `out/blocks.vxta`.
+///
+/// Mip chains are cached in `out/cache` by source content hash, so only new or changed
+/// textures are decoded again. When no input changed, nothing is written.
+pub fn compile(source: &Path, out: &Path) -> Result<CompileReport, CompileError> {
+    // 1. Definitions, sorted by file name for a stable output
+    let mut definition_files: Vec<PathBuf> = fs::read_dir(source)
+        .map_err(io_err(source))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.to_str().is_some_and(|s| s.ends_with(".block.ron")))
+        .collect();
+    definition_files.sort();
+
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&COMPILER_VERSION.to_le_bytes());
+
+    let mut definitions = Vec::new();
+    for path in &definition_files {
+        let text = fs::read_to_string(path).map_err(io_err(path))?;
+        hasher.update(path.file_name().unwrap().as_encoded_bytes());
+        hasher.update(text.as_bytes());
+        let definition: BlockDefinition =
+            ron::from_str(&text).map_err(|e| CompileError::Definition(path.clone(), e.to_string()))?;
+        let faces = definition
+            .face_textures()
+            .map_err(|e| CompileError::Definition(path.clone(), e))?;
+        definitions.push((path.clone(), definition, faces));
+    }
+
+    let mut ids = BTreeMap::new();
+    for (path, definition, _) in &definitions {
+        if let Some(other) = ids.insert(definition.id, &definition.name) {
+            return Err(CompileError::Definition(
+                path.clone(),
+                format!("id {} is used by both `{other}` and `{}`", definition.id, definition.name),
+            ));
+        }
+        if definition.id == 0 && definition.solid {
+            return Err(CompileError::Definition(path.clone(), "id 0 is air and can't be solid".into()));
+        }
+    }
+
+    // 2. One layer per distinct texture, in path order
+    let textures: BTreeSet<&str> = definitions
+        .iter()
+        .filter_map(|(_, _, faces)| faces.as_ref())
+        .flatten()
+        .map(String::as_str)
+        .collect();
+
+    let mut sources = Vec::with_capacity(textures.len());
+    for name in &textures {
+        let path = source.join(name);
+        let bytes = fs::read(&path).map_err(io_err(&path))?;
+        let hash = blake3::hash(&bytes);
+        hasher.update(name.as_bytes());
+        hasher.update(hash.as_bytes());
+        sources.push((path, bytes, hash));
+    }
+    let source_hash = hasher.finalize().to_hex().to_string();
+
+    let manifest_path = out.join(MANIFEST_FILE);
+    let array_path = out.join(TEXTURE_ARRAY_FILE);
+    if array_path.exists() {
+        if let Ok(previous) = BlockManifest::load(&manifest_path) {
+            if previous.source_hash == source_hash {
+                return Ok(CompileReport {
+                    blocks: previous.blocks.len(),
+                    layers: previous.layers as usize,
+                    up_to_date: true,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    // 3. Mip chains, from the cache when the content was seen before
+    let cache = ArtifactCache::new(out.join(CACHE_DIR), "mips");
+
+    let mut report = CompileReport::default();
+    let mut size = None;
+    let mut layers = Vec::with_capacity(sources.len());
+    for (path, bytes, hash) in &sources {
+        let key = hash.to_hex();
+        // A truncated or corrupt entry is built again and overwritten
+        let (chain, texture_size) = match cache.get(&key).as_deref().and_then(cached_chain) {
+            Some((chain, texture_size)) => {
+                report.textures_cached += 1;
+                (chain.to_vec(), texture_size)
+            }
+            None => {
+                let (rgba, texture_size) = decode_png(path, bytes)?;
+                let chain = mip_chain(&rgba, texture_size);
+                let mut cached = texture_size.to_le_bytes().to_vec();
+                cached.extend_from_slice(&chain);
+                cache.put(&key, &cached).map_err(io_err(&cache.path(&key)))?;
+                report.textures_built += 1;
+                (chain, texture_size)
+            }
+        };
+
+        match size {
+            None => size = Some(texture_size),
+            Some(expected) if expected != texture_size => {
+                return Err(CompileError::Texture(
+                    path.clone(),
+                    format!("is {texture_size}x{texture_size}, other textures are {expected}x{expected}"),
+                ));
+            }
+            _ => {}
+        }
+        layers.push(chain);
+    }
+
+    // 4. Manifest, faces point at layers
+    let layer_of: BTreeMap<&str, u32> = textures.iter().enumerate().map(|(i, t)| (*t, i as u32)).collect();
+    let mut blocks: Vec<CompiledBlock> = definitions
+        .iter()
+        .map(|(_, definition, faces)| CompiledBlock {
+            id: definition.id,
+            name: definition.name.clone(),
+            solid: definition.solid,
+            smooth: definition.smooth,
+            sound: definition.sound.clone(),
+            faces: faces.as_ref().map(|faces| faces.each_ref().map(|f| layer_of[f.as_str()])),
+        })
+        .collect();
+    blocks.sort_by_key(|b| b.id);
+
+    let size = size.unwrap_or(1);
+    let array = TextureArray {
+        size,
+        mip_levels: mip_count(size),
+        layers,
+    };
+    let manifest = BlockManifest {
+        version: MANIFEST_VERSION,
+        source_hash,
+        texture_array: TEXTURE_ARRAY_FILE.to_string(),
+        tile_size: size,
+        mip_levels: array.mip_levels,
+        layers: array.layers.len() as u32,
+        blocks,
+    };
+
+    // Texture array first, the manifest is what marks the build as complete
+    let mut file = BufWriter::new(fs::File::create(&array_path).map_err(io_err(&array_path))?);
+    array.write(&mut file).and_then(|_| file.flush()).map_err(io_err(&array_path))?;
+    fs::write(&manifest_path, manifest.to_ron()).map_err(io_err(&manifest_path))?;
+
+    report.blocks = manifest.blocks.len();
+    report.layers = manifest.layers as usize;
+    Ok(report)
+}
+
+/// Mip chain and texture size of a cache entry, None when it does not hold a full chain
+fn cached_chain(cached: &[u8]) -> Option<(&[u8], u32)> {
+    let (size, chain) = cached.split_first_chunk::<4>()?;
+    let size = u32::from_le_bytes(*size);
+    // Rejects sizes whose chain length would overflow
+    (size as usize).checked_mul(size as usize * 4)?;
+    let complete = size.is_power_of_two() && chain.len() == mip_chain_len(size);
+    complete.then_some((chain, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_png(path: &Path, size: u32, rgb: [u8; 3]) {
+        let file = fs::File::create(path).unwrap();
+        let mut encoder = png::Encoder::new(BufWriter::new(file), size, size);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let pixels: Vec<u8> = (0..size * size).flat_map(|_| rgb).collect();
+        encoder.write_header().unwrap().write_image_data(&pixels).unwrap();
+    }
+
+    fn content_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("asset-compiler-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("src")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn compiles_and_caches() {
+        let dir = content_dir("cache");
+        let (src, out) = (dir.join("src"), dir.join("out"));
+        write_png(&src.join("stone.png"), 16, [120, 120, 120]);
+        write_png(&src.join("grass_top.png"), 16, [60, 160, 60]);
+        write_png(&src.join("dirt.png"), 16, [120, 80, 40]);
+        fs::write(src.join("stone.block.ron"), r#"(id: 1, name: "Stone", sound: Some("stone"), textures: { "all": "stone.png" })"#).unwrap();
+        fs::write(
+            src.join("grass.block.ron"),
+            r#"(id: 2, name: "Grass", textures: { "top": "grass_top.png", "all": "dirt.png" })"#,
+        )
+        .unwrap();
+
+        let report = compile(&src, &out).unwrap();
+        assert_eq!((report.blocks, report.layers, report.textures_built), (2, 3, 3));
+
+        let manifest = BlockManifest::load(&out.join(MANIFEST_FILE)).unwrap();
+        // Layers are in path order: dirt, grass_top, stone
+        assert_eq!(manifest.blocks[0].faces, Some([2; 6]));
+        assert_eq!(manifest.blocks[1].faces, Some([0, 0, 1, 0, 0, 0]));
+        assert_eq!(manifest.blocks[0].sound.as_deref(), Some("stone"));
+        assert_eq!(manifest.blocks[1].sound, None);
+        let array = TextureArray::read(&mut fs::File::open(out.join(TEXTURE_ARRAY_FILE)).unwrap()).unwrap();
+        assert_eq!((array.size, array.mip_levels, array.layers.len()), (16, 5, 3));
+
+        assert!(compile(&src, &out).unwrap().up_to_date);
+
+        // Only the changed texture is decoded again
+        write_png(&src.join("stone.png"), 16, [90, 90, 90]);
+        let report = compile(&src, &out).unwrap();
+        assert_eq!((report.textures_built, report.textures_cached), (1, 2));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rebuilds_corrupt_cache_entries() {
+        let dir = content_dir("corrupt");
+        let (src, out) = (dir.join("src"), dir.join("out"));
+        write_png(&src.join("stone.png"), 16, [120, 120, 120]);
+        fs::write(src.join("stone.block.ron"), r#"(id: 1, name: "Stone", textures: { "all": "stone.png" })"#).unwrap();
+        compile(&src, &out).unwrap();
+
+        // Truncate the cached mip chain, then change the definition so the build runs again
+        let entry = fs::read_dir(out.join(CACHE_DIR)).unwrap().next().unwrap().unwrap().path();
+        let bytes = fs::read(&entry).unwrap();
+        fs::write(&entry, &bytes[..bytes.len() / 2]).unwrap();
+        fs::write(src.join("stone.block.ron"), r#"(id: 1, name: "Rock", textures: { "all": "stone.png" })"#).unwrap();
+
+        let report = compile(&src, &out).unwrap();
+        assert_eq!((report.textures_built, report.textures_cached), (1, 0));
+        assert_eq!(fs::read(&entry).unwrap(), bytes);
+        assert!(compile(&src, &out).unwrap().up_to_date);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_bad_inputs() {
+        let dir = content_dir("invalid");
+        let (src, out) = (dir.join("src"), dir.join("out"));
+        write_png(&src.join("a.png"), 16, [0; 3]);
+        write_png(&src.join("b.png"), 8, [0; 3]);
+        fs::write(src.join("a.block.ron"), r#"(id: 1, name: "A", textures: { "all": "a.png" })"#).unwrap();
+        fs::write(src.join("b.block.ron"), r#"(id: 2, name: "B", textures: { "all": "b.png" })"#).unwrap();
+        assert!(matches!(compile(&src, &out), Err(CompileError::Texture(..))));
+
+        fs::write(src.join("b.block.ron"), r#"(id: 1, name: "B", textures: { "all": "a.png" })"#).unwrap();
+        assert!(matches!(compile(&src, &out), Err(CompileError::Definition(..))));
+
+        fs::write(src.join("b.block.ron"), r#"(id: 2, name: "B", textures: { "side": "a.png" })"#).unwrap();
+        assert!(matches!(compile(&src, &out), Err(CompileError::Definition(..))));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
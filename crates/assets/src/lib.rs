@@ -1,5 +1,12 @@
 //! Asset pipeline: load/compile/cache
 
-pub fn load_asset(path: &str) {
-    println!("loading asset: {}", path);
-}
+pub mod biomes;
+pub mod blocks;
+pub mod cache;
+pub mod compiler;
+pub mod loaders;
+pub mod noise_graph;
+pub mod structure;
+pub mod texture_array;
+
+pub use loaders::AssetsPlugin;
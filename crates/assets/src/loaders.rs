@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::marker::PhantomData;
+
+use bevy::app::{App, Plugin};
+use bevy::asset::io::Reader;
+use bevy::asset::{Asset, AssetApp, AssetLoader, LoadContext};
+use bevy::reflect::TypePath;
+use serde::de::DeserializeOwned;
+
+use crate::biomes::{BiomeTable, BiomeTableSource};
+use crate::blocks::{BlockDefinition, BlockManifest};
+use crate::noise_graph::NoiseGraph;
+use crate::structure::StructureTemplate;
+
+#[derive(Debug)]
+pub enum AssetLoadError {
+    Io(std::io::Error),
+    Parse(String),
+    Dependency(String),
+}
+
+impl fmt::Display for AssetLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetLoadError::Io(e) => write!(f, "{e}"),
+            AssetLoadError::Parse(e) => write!(f, "{e}"),
+            AssetLoadError::Dependency(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for AssetLoadError {}
+
+/// Assets stored as a single RON file
+pub trait RonAsset: Asset + DeserializeOwned {
+    const EXTENSIONS: &'static [&'static str];
+
+    fn from_text(text: &str) -> Result<Self, String> {
+        ron::from_str(text).map_err(|e| e.to_string())
+    }
+}
+
+impl RonAsset for BlockDefinition {
+    const EXTENSIONS: &'static [&'static str] = &["block.ron"];
+
+    fn from_text(text: &str) -> Result<Self, String> {
+        let definition: Self = ron::from_str(text).map_err(|e| e.to_string())?;
+        definition.face_textures()?;
+        Ok(definition)
+    }
+}
+
+impl RonAsset for BlockManifest {
+    const EXTENSIONS: &'static [&'static str] = &["manifest.ron"];
+
+    fn from_text(text: &str) -> Result<Self, String> {
+        Self::from_ron(text)
+    }
+}
+
+impl RonAsset for NoiseGraph {
+    const EXTENSIONS: &'static [&'static str] = &["noise.ron"];
+}
+
+impl RonAsset for StructureTemplate {
+    const EXTENSIONS: &'static [&'static str] = &["structure.ron"];
+
+    fn from_text(text: &str) -> Result<Self, String> {
+        Self::from_ron(text)
+    }
+}
+
+async fn read_text(reader: &mut dyn Reader) -> Result<String, AssetLoadError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).await.map_err(AssetLoadError::Io)?;
+    String::from_utf8(bytes).map_err(|e| AssetLoadError::Parse(e.to_string()))
+}
+
+#[derive(TypePath)]
+pub struct RonLoader<A: RonAsset>(PhantomData<fn() -> A>);
+
+impl<A: RonAsset> Default for RonLoader<A> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<A: RonAsset> AssetLoader for RonLoader<A> {
+    type Asset = A;
+    type Settings = ();
+    type Error = AssetLoadError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<A, AssetLoadError> {
+        let text = read_text(reader).await?;
+        A::from_text(&text).map_err(|e| AssetLoadError::Parse(format!("{}: {e}", load_context.path())))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        A::EXTENSIONS
+    }
+}
+
+/// Loads a biome table and the noise graphs it references. The graphs are loader
+/// dependencies, so a change to one of them reloads the table.
+#[derive(TypePath, Default)]
+pub struct BiomeTableLoader;
+
+impl AssetLoader for BiomeTableLoader {
+    type Asset = BiomeTable;
+    type Settings = ();
+    type Error = AssetLoadError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<BiomeTable, AssetLoadError> {
+        let text = read_text(reader).await?;
+        let source: BiomeTableSource = ron::from_str(&text)
+            .map_err(|e| AssetLoadError::Parse(format!("{}: {e}", load_context.path())))?;
+
+        let mut climate = BTreeMap::new();
+        for (name, path) in source.climate {
+            // Relative to the table's folder, not to the table file
+            let path = load_context
+                .path()
+                .resolve_embed(&path)
+                .map_err(|e| AssetLoadError::Dependency(e.to_string()))?;
+            let graph = load_context
+                .loader()
+                .immediate()
+                .load::<NoiseGraph>(path)
+                .await
+                .map_err(|e| AssetLoadError::Dependency(e.to_string()))?;
+            climate.insert(name, graph.take());
+        }
+
+        Ok(BiomeTable {
+            climate,
+            biomes: source.biomes,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["biomes.ron"]
+    }
+}
+
+/// Registers the engine data asset types and their loaders
+pub struct AssetsPlugin;
+
+impl Plugin for AssetsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<BlockDefinition>()
+            .init_asset::<BlockManifest>()
+            .init_asset::<NoiseGraph>()
+            .init_asset::<BiomeTable>()
+            .init_asset::<StructureTemplate>()
+            .init_asset_loader::<RonLoader<BlockDefinition>>()
+            .init_asset_loader::<RonLoader<BlockManifest>>()
+            .init_asset_loader::<RonLoader<NoiseGraph>>()
+            .init_asset_loader::<RonLoader<StructureTemplate>>()
+            .init_asset_loader::<BiomeTableLoader>();
+    }
+}
@@ -0,0 +1,101 @@
+use bevy::asset::Asset;
+use bevy::reflect::TypePath;
+use serde::{Deserialize, Serialize};
+
+/// A box of voxels that can be pasted into the world, `*.structure.ron`:
+///
+/// ```ron
+/// (
+///     size: (1, 3, 1),
+///     anchor: (0, 0, 0),
+///     palette: [None, Some(5), Some(6)],
+///     voxels: [1, 1, 2],
+///     markers: [(name: "chest", position: (0, 1, 0))],
+/// )
+/// ```
+///
+/// `voxels` holds palette indices in x, then z, then y order. A `None` palette entry
+/// leaves the world voxel untouched, so a tree does not carve air around its leaves.
+#[derive(Asset, TypePath, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructureTemplate {
+    /// Extent in voxels, x, y, z
+    pub size: (u32, u32, u32),
+    /// Template position that lands on the placement origin
+    #[serde(default)]
+    pub anchor: (i32, i32, i32),
+    /// Block id per palette index
+    pub palette: Vec<Option<u8>>,
+    pub voxels: Vec<u8>,
+    #[serde(default)]
+    pub markers: Vec<StructureMarker>,
+}
+
+/// Named point inside a template, for entities the game spawns after placement
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructureMarker {
+    pub name: String,
+    pub position: (i32, i32, i32),
+}
+
+impl StructureTemplate {
+    pub fn index(&self, x: u32, y: u32, z: u32) -> usize {
+        let (sx, _, sz) = self.size;
+        (x + z * sx + y * sx * sz) as usize
+    }
+
+    /// Block id at a template position, `None` when the template leaves it alone
+    pub fn get(&self, x: u32, y: u32, z: u32) -> Option<u8> {
+        self.palette[self.voxels[self.index(x, y, z)] as usize]
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let (sx, sy, sz) = self.size;
+        let expected = sx as usize * sy as usize * sz as usize;
+        if self.voxels.len() != expected {
+            return Err(format!("structure has {} voxels, its size needs {expected}", self.voxels.len()));
+        }
+        if let Some(index) = self.voxels.iter().find(|i| **i as usize >= self.palette.len()) {
+            return Err(format!("palette index {index} out of range ({} entries)", self.palette.len()));
+        }
+        Ok(())
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, String> {
+        let template: Self = ron::from_str(text).map_err(|e| format!("invalid structure: {e}"))?;
+        template.validate()?;
+        Ok(template)
+    }
+
+    pub fn to_ron(&self) -> String {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .expect("structure is always serializable")
+    }
+
+    pub fn load(path: &std::path::Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        Self::from_ron(&text)
+    }
+
+    pub fn save(&self, path: &std::path::Path) -> Result<(), String> {
+        std::fs::write(path, self.to_ron()).map_err(|e| format!("cannot write {}: {e}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_validates() {
+        let template = StructureTemplate::from_ron(
+            r#"(size: (1, 3, 1), palette: [None, Some(5), Some(6)], voxels: [1, 1, 2],
+                markers: [(name: "chest", position: (0, 1, 0))])"#,
+        )
+        .unwrap();
+        assert_eq!((template.get(0, 0, 0), template.get(0, 2, 0)), (Some(5), Some(6)));
+        assert_eq!(StructureTemplate::from_ron(&template.to_ron()).unwrap(), template);
+
+        assert!(StructureTemplate::from_ron("(size: (2, 1, 1), palette: [None], voxels: [0])").is_err());
+        assert!(StructureTemplate::from_ron("(size: (1, 1, 1), palette: [None], voxels: [1])").is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use std::io::{self, Read, Write};
+
+pub const TEXTURE_ARRAY_VERSION: u16 = 1;
+const MAGIC: [u8; 4] = *b"VXTA";
+
+/// Square RGBA8 layers of the same size, each with a full mip chain.
+///
+/// On disk: `"VXTA", version u16, 0u16, size u32, mip_levels u32, layers u32`, then the
+/// layers one after the other, each being its mips from largest to smallest
+/// (layer-major, the order `wgpu::util::TextureDataOrder::LayerMajor` expects).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextureArray {
+    pub size: u32,
+    pub mip_levels: u32,
+    /// Mip chain of every layer, see `mip_chain`
+    pub layers: Vec<Vec<u8>>,
+}
+
+/// Number of mips down to 1x1
+pub fn mip_count(size: u32) -> u32 {
+    size.max(1).ilog2() + 1
+}
+
+/// Byte length of a full RGBA8 mip chain
+pub fn mip_chain_len(size: u32) -> usize {
+    (0..mip_count(size)).map(|m| ((size >> m) as usize).pow(2) * 4).sum()
+}
+
+/// Full mip chain of a square power-of-two RGBA8 image, 2x2 box filter
+pub fn mip_chain(rgba: &[u8], size: u32) -> Vec<u8> {
+    assert!(size.is_power_of_two(), "texture size must be a power of two");
+    assert_eq!(rgba.len(), (size * size * 4) as usize);
+
+    let mut out = Vec::with_capacity(mip_chain_len(size));
+    out.extend_from_slice(rgba);
+
+    let mut previous = rgba.to_vec();
+    let mut previous_size = size as usize;
+    while previous_size > 1 {
+        let next_size = previous_size / 2;
+        let mut next = vec![0u8; next_size * next_size * 4];
+        for y in 0..next_size {
+            for x in 0..next_size {
+                for c in 0..4 {
+                    let texel = |dx: usize, dy: usize| {
+                        previous[((y * 2 + dy) * previous_size + x * 2 + dx) * 4 + c] as u32
+                    };
+                    let sum = texel(0, 0) + texel(1, 0) + texel(0, 1) + texel(1, 1);
+                    next[(y * next_size + x) * 4 + c] = ((sum + 2) / 4) as u8;
+                }
+            }
+        }
+        out.extend_from_slice(&next);
+        previous = next;
+        previous_size = next_size;
+    }
+    out
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+impl TextureArray {
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(&MAGIC)?;
+        out.write_all(&TEXTURE_ARRAY_VERSION.to_le_bytes())?;
+        out.write_all(&0u16.to_le_bytes())?;
+        out.write_all(&self.size.to_le_bytes())?;
+        out.write_all(&self.mip_levels.to_le_bytes())?;
+        out.write_all(&(self.layers.len() as u32).to_le_bytes())?;
+        for layer in &self.layers {
+            out.write_all(layer)?;
+        }
+        Ok(())
+    }
+
+    pub fn read(input: &mut impl Read) -> io::Result<Self> {
+        let mut header = [0u8; 20];
+        input.read_exact(&mut header).map_err(|_| invalid("not a texture array"))?;
+        if header[..4] != MAGIC {
+            return Err(invalid("not a texture array"));
+        }
+        let version = u16::from_le_bytes([header[4], header[5]]);
+        if version != TEXTURE_ARRAY_VERSION {
+            return Err(invalid(format!("unsupported texture array version {version}")));
+        }
+        let word = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap());
+        let (size, mip_levels, count) = (word(8), word(12), word(16));
+        if !size.is_power_of_two() || mip_levels != mip_count(size) {
+            return Err(invalid(format!("bad texture array size {size} with {mip_levels} mips")));
+        }
+
+        let mut layers = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let mut layer = vec![0u8; mip_chain_len(size)];
+            input.read_exact(&mut layer)?;
+            layers.push(layer);
+        }
+        Ok(Self { size, mip_levels, layers })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mip_chain_averages_down_to_one_texel() {
+        // 2x2 checker of black and white
+        let rgba = [0, 0, 0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 0, 0, 0, 255];
+        let chain = mip_chain(&rgba, 2);
+        assert_eq!(chain.len(), mip_chain_len(2));
+        assert_eq!(&chain[16..], &[128, 128, 128, 255]);
+        assert_eq!(mip_count(16), 5);
+    }
+
+    #[test]
+    fn container_round_trip() {
+        let array = TextureArray {
+            size: 4,
+            mip_levels: mip_count(4),
+            layers: vec![mip_chain(&[200; 64], 4), mip_chain(&[10; 64], 4)],
+        };
+        let mut bytes = Vec::new();
+        array.write(&mut bytes).unwrap();
+        assert_eq!(TextureArray::read(&mut bytes.as_slice()).unwrap(), array);
+        assert!(TextureArray::read(&mut &bytes[..bytes.len() - 1]).is_err());
+    }
+}
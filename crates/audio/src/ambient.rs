@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::world::TerrainProbe;
+use crate::SoundListener;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AmbientZone {
+    Surface,
+    Underground,
+}
+
+/// Looping tracks per zone, paths relative to the asset folder
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct AmbientSettings {
+    /// On the surface, when `ListenerBiome` has no track of its own
+    pub surface: String,
+    /// Surface tracks by biome name
+    pub biomes: HashMap<String, String>,
+    pub underground: String,
+    /// Solid ground this many blocks above the listener, without sky light, is underground
+    pub cave_depth: i32,
+    /// Seconds of crossfade between tracks
+    pub fade: f32,
+    pub volume: f32,
+}
+
+impl Default for AmbientSettings {
+    fn default() -> Self {
+        Self {
+            surface: "sounds/ambient/surface.ogg".into(),
+            biomes: HashMap::new(),
+            underground: "sounds/ambient/cave.ogg".into(),
+            cave_depth: 4,
+            fade: 3.0,
+            volume: 0.5,
+        }
+    }
+}
+
+impl AmbientSettings {
+    pub fn track(&self, zone: AmbientZone, biome: Option<&str>) -> &str {
+        match zone {
+            AmbientZone::Underground => &self.underground,
+            AmbientZone::Surface => biome.and_then(|biome| self.biomes.get(biome)).unwrap_or(&self.surface),
+        }
+    }
+}
+
+/// Biome around the listener, picking the surface track. `AmbientAudio::biome` keeps it up to
+/// date from the world's biome table; without one, games with their own lookup set it.
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub struct ListenerBiome(pub Option<String>);
+
+/// Tracks the backend should be playing, and their volumes.
+/// The current track fades in while the others fade out.
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub struct AmbientMix {
+    pub zone: Option<AmbientZone>,
+    pub tracks: Vec<(String, f32)>,
+}
+
+impl AmbientMix {
+    /// Moves every volume by `step` towards its target, tracks reaching 0 are dropped
+    pub fn fade_to(&mut self, track: &str, volume: f32, step: f32) {
+        if !self.tracks.iter().any(|(path, _)| path == track) {
+            self.tracks.push((track.to_string(), 0.0));
+        }
+        for (path, current) in &mut self.tracks {
+            let target = if path == track { volume } else { 0.0 };
+            *current = if *current < target { (*current + step).min(target) } else { (*current - step).max(target) };
+        }
+        self.tracks.retain(|(path, current)| path == track || *current > 0.0);
+    }
+
+    pub fn volume(&self, track: &str) -> f32 {
+        self.tracks.iter().find(|(path, _)| path == track).map_or(0.0, |(_, volume)| *volume)
+    }
+}
+
+/// Underground when the column has ground `cave_depth` blocks above the listener and no sky
+/// light reaches it. Unlit chunks go by the ground alone.
+pub fn listener_zone(eye: IVec3, sky_light: Option<u8>, surface: Option<i32>, cave_depth: i32) -> AmbientZone {
+    let covered = surface.is_some_and(|top| top >= eye.y + cave_depth);
+    if covered && sky_light.is_none_or(|light| light == 0) {
+        AmbientZone::Underground
+    } else {
+        AmbientZone::Surface
+    }
+}
+
+pub struct AmbientAudio;
+
+impl AmbientAudio {
+    /// Looks up the biome under the listener, left alone when the terrain has no biomes
+    pub fn biome(
+        terrain: TerrainProbe,
+        listeners: Query<&GlobalTransform, With<SoundListener>>,
+        mut biome: ResMut<ListenerBiome>,
+    ) {
+        let Some(listener) = listeners.iter().next() else {
+            return;
+        };
+        let eye = listener.translation().floor().as_ivec3();
+        if let Some(name) = terrain.biome(eye.x, eye.z) {
+            if biome.0.as_deref() != Some(name) {
+                biome.0 = Some(name.to_string());
+            }
+        }
+    }
+
+    pub fn mix(
+        time: Res<Time>,
+        settings: Res<AmbientSettings>,
+        biome: Res<ListenerBiome>,
+        terrain: TerrainProbe,
+        listeners: Query<&GlobalTransform, With<SoundListener>>,
+        mut mix: ResMut<AmbientMix>,
+    ) {
+        let Some(listener) = listeners.iter().next() else {
+            return;
+        };
+        let eye = listener.translation().floor().as_ivec3();
+        let zone = listener_zone(eye, terrain.sky_light(eye), terrain.top_solid(eye.x, eye.z), settings.cave_depth);
+        let step = if settings.fade > 0.0 { time.delta_secs() / settings.fade } else { 1.0 };
+        mix.zone = Some(zone);
+        mix.fade_to(settings.track(zone, biome.0.as_deref()), settings.volume, step * settings.volume);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn underground_needs_cover_and_darkness() {
+        let eye = IVec3::new(0, 30, 0);
+        assert_eq!(listener_zone(eye, Some(0), Some(60), 4), AmbientZone::Underground);
+        assert_eq!(listener_zone(eye, None, Some(60), 4), AmbientZone::Underground);
+        // Under a tree or an overhang
+        assert_eq!(listener_zone(eye, Some(0), Some(32), 4), AmbientZone::Surface);
+        // A shaft to the sky
+        assert_eq!(listener_zone(eye, Some(15), Some(60), 4), AmbientZone::Surface);
+        assert_eq!(listener_zone(eye, None, None, 4), AmbientZone::Surface);
+    }
+
+    #[test]
+    fn crossfades_between_tracks() {
+        let mut settings = AmbientSettings::default();
+        settings.biomes.insert("Desert".into(), "desert.ogg".into());
+        assert_eq!(settings.track(AmbientZone::Surface, Some("Desert")), "desert.ogg");
+        assert_eq!(settings.track(AmbientZone::Surface, Some("Tundra")), settings.surface);
+        assert_eq!(settings.track(AmbientZone::Underground, Some("Desert")), settings.underground);
+
+        let mut mix = AmbientMix::default();
+        for _ in 0..4 {
+            mix.fade_to("a", 1.0, 0.25);
+        }
+        assert_eq!(mix.volume("a"), 1.0);
+
+        mix.fade_to("b", 1.0, 0.25);
+        assert_eq!((mix.volume("a"), mix.volume("b")), (0.75, 0.25));
+        for _ in 0..3 {
+            mix.fade_to("b", 1.0, 0.25);
+        }
+        assert_eq!(mix.tracks, vec![("b".to_string(), 1.0)]);
+    }
+}
@@ -0,0 +1,125 @@
+use bevy::audio::{PlaybackMode, Volume};
+use bevy::prelude::*;
+
+use crate::ambient::AmbientMix;
+use crate::{AudioSystems, PlaySound, SoundEmitter, SoundListener};
+
+/// Space between the ears of a `SpatialListener`, in blocks
+const EAR_GAP: f32 = 0.3;
+
+/// Plays through Bevy audio: spatial emitters on their entities, one-shots on short-lived
+/// entities despawned once done, and an entity per ambient track
+pub struct BevyAudioBackend;
+
+impl Plugin for BevyAudioBackend {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (BevyAudio::listeners, BevyAudio::emitters, BevyAudio::play, BevyAudio::ambient).in_set(AudioSystems::Play),
+        );
+    }
+}
+
+/// An entity playing one of the `AmbientMix` tracks
+#[derive(Component)]
+struct AmbientTrack(String);
+
+pub struct BevyAudio;
+
+impl BevyAudio {
+    pub fn listeners(mut commands: Commands, listeners: Query<Entity, (Added<SoundListener>, Without<SpatialListener>)>) {
+        for entity in &listeners {
+            commands.entity(entity).insert(SpatialListener::new(EAR_GAP));
+        }
+    }
+
+    pub fn emitters(
+        mut commands: Commands,
+        assets: Res<AssetServer>,
+        emitters: Query<(Entity, &SoundEmitter), Changed<SoundEmitter>>,
+    ) {
+        for (entity, emitter) in &emitters {
+            let mode = if emitter.looping { PlaybackMode::Loop } else { PlaybackMode::Remove };
+            commands.entity(entity).insert((
+                AudioPlayer::new(assets.load(&emitter.path)),
+                PlaybackSettings { mode, ..PlaybackSettings::ONCE }
+                    .with_volume(Volume::Linear(emitter.volume))
+                    .with_spatial(true),
+            ));
+        }
+    }
+
+    pub fn play(mut commands: Commands, assets: Res<AssetServer>, mut sounds: MessageReader<PlaySound>) {
+        for sound in sounds.read() {
+            let settings = PlaybackSettings::DESPAWN.with_volume(Volume::Linear(sound.volume));
+            let player = AudioPlayer::new(assets.load(&sound.path));
+            match sound.position {
+                Some(position) => {
+                    commands.spawn((player, settings.with_spatial(true), Transform::from_translation(position)));
+                }
+                None => {
+                    commands.spawn((player, settings));
+                }
+            }
+        }
+    }
+
+    /// Starts new tracks, follows the volumes and stops the faded out ones
+    fn ambient(
+        mut commands: Commands,
+        assets: Res<AssetServer>,
+        mix: Res<AmbientMix>,
+        mut tracks: Query<(Entity, &AmbientTrack, Option<&mut AudioSink>)>,
+    ) {
+        for (entity, track, sink) in &mut tracks {
+            match mix.tracks.iter().find(|(path, _)| *path == track.0) {
+                Some((_, volume)) => {
+                    if let Some(mut sink) = sink {
+                        sink.set_volume(Volume::Linear(*volume));
+                    }
+                }
+                None => commands.entity(entity).despawn(),
+            }
+        }
+        for (path, volume) in &mix.tracks {
+            if !tracks.iter().any(|(_, track, _)| track.0 == *path) {
+                commands.spawn((
+                    AmbientTrack(path.clone()),
+                    AudioPlayer::new(assets.load(path)),
+                    PlaybackSettings::LOOP.with_volume(Volume::Linear(*volume)),
+                ));
+            }
+        }
+    }
+}
+
+/// Plays nothing. What would have been played is kept in `NullAudio`.
+pub struct NullAudioBackend;
+
+impl Plugin for NullAudioBackend {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NullAudio>().add_systems(Update, NullAudio::record.in_set(AudioSystems::Play));
+    }
+}
+
+/// Everything the null backend was asked to play, since startup
+#[derive(Resource, Default, Debug)]
+pub struct NullAudio {
+    pub played: Vec<PlaySound>,
+    pub emitters: Vec<(Entity, SoundEmitter)>,
+    /// Ambient tracks as of the last frame
+    pub ambient: Vec<(String, f32)>,
+}
+
+impl NullAudio {
+    fn record(
+        mut null: ResMut<NullAudio>,
+        mix: Res<AmbientMix>,
+        mut sounds: MessageReader<PlaySound>,
+        emitters: Query<(Entity, &SoundEmitter), Changed<SoundEmitter>>,
+    ) {
+        null.played.extend(sounds.read().cloned());
+        null.emitters.extend(emitters.iter().map(|(entity, emitter)| (entity, emitter.clone())));
+        null.ambient.clone_from(&mix.tracks);
+    }
+}
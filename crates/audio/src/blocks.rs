@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use engine::terrain::ecs::resources::voxel::VoxelRegistry;
+use engine::terrain::types::Voxel;
+
+use crate::world::TerrainProbe;
+use crate::PlaySound;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SoundAction {
+    Step,
+    Break,
+    Place,
+}
+
+impl SoundAction {
+    pub const ALL: [SoundAction; 3] = [SoundAction::Step, SoundAction::Break, SoundAction::Place];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SoundAction::Step => "step",
+            SoundAction::Break => "break",
+            SoundAction::Place => "place",
+        }
+    }
+
+    fn volume(self) -> f32 {
+        match self {
+            SoundAction::Step => 0.4,
+            SoundAction::Break => 0.8,
+            SoundAction::Place => 0.7,
+        }
+    }
+}
+
+/// Variants per action for one material, one is picked at random for every sound
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SoundGroup {
+    pub sounds: HashMap<SoundAction, Vec<String>>,
+}
+
+impl SoundGroup {
+    /// `sounds/blocks/<name>/<action><n>.ogg`, `n` from 1 to `variants`
+    pub fn conventional(name: &str, variants: u32) -> Self {
+        let sounds = SoundAction::ALL
+            .into_iter()
+            .map(|action| {
+                let paths = (1..=variants)
+                    .map(|n| format!("sounds/blocks/{name}/{}{n}.ogg", action.name()))
+                    .collect();
+                (action, paths)
+            })
+            .collect();
+        Self { sounds }
+    }
+}
+
+/// Sound groups by name, blocks refer to one with `VoxelDefinition::sound_group`
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct SoundRegistry {
+    pub groups: HashMap<String, SoundGroup>,
+}
+
+impl Default for SoundRegistry {
+    /// The common materials, four variants each
+    fn default() -> Self {
+        let groups = ["stone", "dirt", "grass", "gravel", "sand", "wood", "snow", "glass"]
+            .into_iter()
+            .map(|name| (name.to_string(), SoundGroup::conventional(name, 4)))
+            .collect();
+        Self { groups }
+    }
+}
+
+impl SoundRegistry {
+    /// Variant `seed` modulo the variant count, `None` for unknown groups and missing actions
+    pub fn pick(&self, group: &str, action: SoundAction, seed: u32) -> Option<&str> {
+        let variants = self.groups.get(group)?.sounds.get(&action)?;
+        if variants.is_empty() {
+            return None;
+        }
+        Some(&variants[seed as usize % variants.len()])
+    }
+
+    /// Sound for an action on a block, through its sound group
+    pub fn for_voxel(&self, registry: &VoxelRegistry, voxel: Voxel, action: SoundAction, seed: u32) -> Option<&str> {
+        let group = registry.definitions.get(&voxel)?.sound_group.as_deref()?;
+        self.pick(group, action, seed)
+    }
+}
+
+/// A block was stepped on, broken or placed. Gameplay code sends break and place,
+/// `Footsteps` sends steps.
+#[derive(Message, Clone, Copy, Debug, PartialEq)]
+pub struct BlockSound {
+    pub voxel: Voxel,
+    pub action: SoundAction,
+    /// Center of the block
+    pub position: Vec3,
+}
+
+/// Step sounds for an entity walking on the terrain, from the block under its feet
+#[derive(Component, Clone, Debug)]
+pub struct Footsteps {
+    /// Blocks walked per step
+    pub stride: f32,
+    /// Height of the entity's transform above its feet
+    pub eye_height: f32,
+    travelled: f32,
+    last: Option<Vec3>,
+}
+
+impl Default for Footsteps {
+    fn default() -> Self {
+        Self::new(1.8, 1.6)
+    }
+}
+
+impl Footsteps {
+    pub fn new(stride: f32, eye_height: f32) -> Self {
+        Self { stride, eye_height, travelled: 0.0, last: None }
+    }
+}
+
+/// Spreads consecutive sounds over the variants
+fn variant_seed(counter: &mut u32) -> u32 {
+    *counter = counter.wrapping_add(1);
+    counter.wrapping_mul(2_654_435_761) >> 16
+}
+
+pub struct BlockAudio;
+
+impl BlockAudio {
+    /// Steps on solid ground, horizontal movement only so falling and jumping are silent
+    pub fn footsteps(
+        registry: Option<Res<VoxelRegistry>>,
+        terrain: TerrainProbe,
+        mut walkers: Query<(&GlobalTransform, &mut Footsteps)>,
+        mut sounds: MessageWriter<BlockSound>,
+    ) {
+        let Some(registry) = registry else {
+            return;
+        };
+        for (transform, mut footsteps) in &mut walkers {
+            let position = transform.translation();
+            let last = footsteps.last.replace(position).unwrap_or(position);
+            let ground = (position - Vec3::Y * (footsteps.eye_height + 0.1)).floor().as_ivec3();
+            let Some(voxel) = terrain.voxel(ground).filter(|voxel| registry.get(voxel).is_solid) else {
+                continue;
+            };
+            footsteps.travelled += (position - last).xz().length();
+            if footsteps.travelled < footsteps.stride {
+                continue;
+            }
+            footsteps.travelled %= footsteps.stride;
+            sounds.write(BlockSound { voxel, action: SoundAction::Step, position: ground.as_vec3() + 0.5 });
+        }
+    }
+
+    /// Block sounds to playable ones, through the block's sound group
+    pub fn resolve(
+        registry: Option<Res<VoxelRegistry>>,
+        sounds: Res<SoundRegistry>,
+        mut counter: Local<u32>,
+        mut blocks: MessageReader<BlockSound>,
+        mut play: MessageWriter<PlaySound>,
+    ) {
+        let Some(registry) = registry else {
+            blocks.clear();
+            return;
+        };
+        for block in blocks.read() {
+            let Some(path) = sounds.for_voxel(&registry, block.voxel, block.action, variant_seed(&mut counter)) else {
+                continue;
+            };
+            play.write(PlaySound {
+                path: path.to_string(),
+                position: Some(block.position),
+                volume: block.action.volume(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_from_the_block_group() {
+        let sounds = SoundRegistry::default();
+        let registry = VoxelRegistry::default();
+        assert_eq!(
+            sounds.for_voxel(&registry, Voxel::STONE, SoundAction::Break, 5),
+            Some("sounds/blocks/stone/break2.ogg"),
+        );
+        // Air has no group, unregistered blocks resolve to nothing either
+        assert_eq!(sounds.for_voxel(&registry, Voxel::AIR, SoundAction::Step, 0), None);
+        assert_eq!(sounds.for_voxel(&registry, Voxel(99), SoundAction::Step, 0), None);
+        assert_eq!(sounds.pick("lava", SoundAction::Step, 0), None);
+    }
+
+    #[test]
+    fn variants_are_spread() {
+        let mut counter = 0;
+        let picks: std::collections::HashSet<u32> = (0..16).map(|_| variant_seed(&mut counter) % 4).collect();
+        assert_eq!(picks.len(), 4);
+    }
+}
@@ -1,5 +1,108 @@
-//! Audio subsystem
+//! Audio subsystem: one-shot and spatial sounds, block material sounds and ambient
+//! soundscapes, played through Bevy audio or a silent backend for tests and servers.
 
-pub fn play_dummy() {
-    println!("playing audio (dummy)");
+pub mod ambient;
+pub mod backend;
+pub mod blocks;
+pub mod weather;
+pub mod world;
+
+use bevy::prelude::*;
+
+use ambient::{AmbientAudio, AmbientMix, AmbientSettings, ListenerBiome};
+use backend::{BevyAudioBackend, NullAudioBackend};
+use blocks::{BlockAudio, BlockSound, SoundRegistry};
+
+/// A sound to play once. Positioned sounds are spatial, heard from the `SoundListener`.
+#[derive(Message, Clone, Debug, PartialEq)]
+pub struct PlaySound {
+    /// Relative to the asset folder
+    pub path: String,
+    pub position: Option<Vec3>,
+    /// Linear, 1 is the file's own volume
+    pub volume: f32,
+}
+
+/// Spatial sound attached to an entity, following its transform
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct SoundEmitter {
+    pub path: String,
+    pub volume: f32,
+    /// Loops until the component is removed, otherwise plays once
+    pub looping: bool,
+}
+
+/// Where sounds are heard from, usually the camera. The first one found is used.
+#[derive(Component, Default)]
+pub struct SoundListener;
+
+/// What the sounds are played through
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AudioBackend {
+    /// Bevy audio, needs `bevy::audio::AudioPlugin` and the asset server
+    #[default]
+    Bevy,
+    /// Plays nothing, records what would be played in `backend::NullAudio`
+    Null,
+}
+
+/// Systems gathering what to play run before the backend plays it
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AudioSystems {
+    Gather,
+    Play,
+}
+
+/// Block material sounds, footsteps and ambient soundscapes around the `SoundListener`
+#[derive(Default)]
+pub struct GameAudioPlugin {
+    backend: AudioBackend,
+    sounds: SoundRegistry,
+    ambient: AmbientSettings,
+}
+
+impl GameAudioPlugin {
+    /// Silent, for headless apps and tests
+    pub fn null() -> Self {
+        Self::default().with_backend(AudioBackend::Null)
+    }
+
+    pub fn with_backend(mut self, backend: AudioBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn with_sounds(mut self, sounds: SoundRegistry) -> Self {
+        self.sounds = sounds;
+        self
+    }
+
+    pub fn with_ambient(mut self, ambient: AmbientSettings) -> Self {
+        self.ambient = ambient;
+        self
+    }
+}
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<PlaySound>()
+            .add_message::<BlockSound>()
+            .insert_resource(self.sounds.clone())
+            .insert_resource(self.ambient.clone())
+            .init_resource::<ListenerBiome>()
+            .init_resource::<AmbientMix>()
+            .configure_sets(Update, (AudioSystems::Gather, AudioSystems::Play).chain())
+            .add_systems(
+                Update,
+                (
+                    (BlockAudio::footsteps, BlockAudio::resolve).chain(),
+                    (AmbientAudio::biome, AmbientAudio::mix).chain(),
+                )
+                    .in_set(AudioSystems::Gather),
+            );
+        match self.backend {
+            AudioBackend::Bevy => app.add_plugins(BevyAudioBackend),
+            AudioBackend::Null => app.add_plugins(NullAudioBackend),
+        };
+    }
 }
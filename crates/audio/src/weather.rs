@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+
+use engine::environment::Thunder;
+
+use crate::{AudioSystems, PlaySound, SoundListener};
+
+/// Speed of sound in blocks per second, thunder is heard after the flash
+const SPEED_OF_SOUND: f32 = 343.0;
+/// Strikes further than this are not heard
+const THUNDER_RANGE: f32 = 400.0;
+
+/// Sound played for `Thunder` strikes, relative to the asset folder
+#[derive(Resource, Clone, Debug)]
+pub struct WeatherSounds {
+    pub thunder: String,
+}
+
+impl Default for WeatherSounds {
+    fn default() -> Self {
+        Self { thunder: "sounds/thunder.ogg".into() }
+    }
+}
+
+/// Thunder on its way to the listener
+#[derive(Component)]
+struct PendingThunder {
+    delay: Timer,
+    volume: f32,
+}
+
+/// Plays thunder for the weather's lightning strikes, delayed and quieter with distance.
+/// Goes through the backend of `GameAudioPlugin`, which must be added too.
+#[derive(Default)]
+pub struct WeatherAudioPlugin {
+    pub sounds: WeatherSounds,
+}
+
+impl Plugin for WeatherAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.sounds.clone())
+            .add_systems(
+                Update,
+                (WeatherAudio::strike, WeatherAudio::play).chain().in_set(AudioSystems::Gather),
+            );
+    }
+}
+
+/// Delay and linear volume of a strike heard from `distance` blocks
+pub fn thunder_falloff(distance: f32) -> (f32, f32) {
+    let volume = (1.0 - distance / THUNDER_RANGE).clamp(0.0, 1.0);
+    (distance / SPEED_OF_SOUND, volume * volume)
+}
+
+pub struct WeatherAudio;
+
+impl WeatherAudio {
+    pub fn strike(
+        mut commands: Commands,
+        mut strikes: MessageReader<Thunder>,
+        listeners: Query<&GlobalTransform, With<SoundListener>>,
+    ) {
+        let listener = listeners.iter().next().map(GlobalTransform::translation).unwrap_or_default();
+        for strike in strikes.read() {
+            let (delay, volume) = thunder_falloff(strike.position.distance(listener));
+            if volume > 0.0 {
+                commands.spawn(PendingThunder { delay: Timer::from_seconds(delay, TimerMode::Once), volume });
+            }
+        }
+    }
+
+    fn play(
+        mut commands: Commands,
+        time: Res<Time>,
+        sounds: Res<WeatherSounds>,
+        mut pending: Query<(Entity, &mut PendingThunder)>,
+        mut play: MessageWriter<PlaySound>,
+    ) {
+        for (entity, mut thunder) in &mut pending {
+            if !thunder.delay.tick(time.delta()).is_finished() {
+                continue;
+            }
+            commands.entity(entity).despawn();
+            // Rolls around the whole sky, not spatial
+            play.write(PlaySound { path: sounds.thunder.clone(), position: None, volume: thunder.volume });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn far_thunder_is_late_and_quiet() {
+        let (near_delay, near_volume) = thunder_falloff(10.0);
+        let (far_delay, far_volume) = thunder_falloff(300.0);
+        assert!(far_delay > near_delay);
+        assert!(far_volume < near_volume);
+        assert_eq!(thunder_falloff(THUNDER_RANGE * 2.0).1, 0.0);
+    }
+}
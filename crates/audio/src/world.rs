@@ -0,0 +1,48 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use engine::terrain::ecs::components::chunk::{ChunkData, ChunkHeightmap, ChunkLight};
+use engine::terrain::ecs::resources::chunk::ChunkMap;
+use engine::terrain::generator::TerrainManager;
+use engine::terrain::structures::world_to_chunk;
+use engine::terrain::types::Voxel;
+
+type ChunkParts<'a> = (&'a ChunkData, Option<&'a ChunkLight>, Option<&'a ChunkHeightmap>);
+
+/// Read-only view of the loaded terrain around the listener.
+/// Everything is `None` without terrain, or outside the loaded chunks.
+#[derive(SystemParam)]
+pub struct TerrainProbe<'w, 's> {
+    chunk_map: Option<Res<'w, ChunkMap>>,
+    manager: Option<Res<'w, TerrainManager>>,
+    chunks: Query<'w, 's, (&'static ChunkData, Option<&'static ChunkLight>, Option<&'static ChunkHeightmap>)>,
+}
+
+impl TerrainProbe<'_, '_> {
+    fn chunk(&self, pos: IVec3) -> Option<(IVec3, ChunkParts<'_>)> {
+        let (coords, local) = world_to_chunk(pos);
+        let entity = self.chunk_map.as_ref()?.get(coords)?;
+        Some((local, self.chunks.get(entity).ok()?))
+    }
+
+    pub fn voxel(&self, pos: IVec3) -> Option<Voxel> {
+        let (local, (data, ..)) = self.chunk(pos)?;
+        ChunkData::in_bounds(local.x, local.y, local.z).then(|| data.get(local.x, local.y, local.z))
+    }
+
+    /// Sky light at the voxel, full above the world
+    pub fn sky_light(&self, pos: IVec3) -> Option<u8> {
+        let (local, (_, light, _)) = self.chunk(pos)?;
+        Some(light?.get(local.x, local.y, local.z))
+    }
+
+    /// Name of the biome at the column, the world's biome table decides even where nothing is loaded
+    pub fn biome(&self, x: i32, z: i32) -> Option<&str> {
+        Some(self.manager.as_ref()?.biome(x, z)?.name.as_str())
+    }
+
+    pub fn top_solid(&self, x: i32, z: i32) -> Option<i32> {
+        let (local, (_, _, heightmap)) = self.chunk(IVec3::new(x, 0, z))?;
+        heightmap?.top_solid(local.x, local.z)
+    }
+}
@@ -0,0 +1,135 @@
+//! Block sounds and ambient zones over a hand-built chunk, played through the null backend
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use bevy::transform::TransformPlugin;
+
+use assets::biomes::{Biome, BiomeTable};
+use audio::ambient::{AmbientMix, AmbientSettings, AmbientZone, ListenerBiome};
+use audio::backend::NullAudio;
+use audio::blocks::Footsteps;
+use audio::{GameAudioPlugin, PlaySound, SoundEmitter, SoundListener};
+use engine::terrain::ecs::components::chunk::{ChunkData, ChunkHeightmap};
+use engine::terrain::ecs::resources::{chunk::ChunkMap, voxel::VoxelRegistry};
+use engine::terrain::generator::TerrainManager;
+use engine::terrain::lighting::compute_skylight;
+use engine::terrain::meshing::binary_greedy::SolidTable;
+use engine::terrain::types::Voxel;
+
+/// Stone up to y = 39 in the chunk at the origin
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, TransformPlugin, GameAudioPlugin::null()))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(100)));
+
+    let registry = VoxelRegistry::default();
+    let mut chunk = ChunkData::new();
+    chunk.fill_layer_below(40, Voxel::STONE);
+    let light = compute_skylight(&chunk, &registry);
+    let heightmap = ChunkHeightmap::new(&chunk, &SolidTable::new(&registry));
+    let entity = app.world_mut().spawn((chunk, light, heightmap)).id();
+    let mut chunk_map = ChunkMap::default();
+    chunk_map.entities.insert(IVec2::ZERO, entity);
+    app.insert_resource(registry).insert_resource(chunk_map);
+    app
+}
+
+/// Audio reads `GlobalTransform`, propagated after `Update`, so the move is seen a frame later
+fn move_listener(app: &mut App, listener: Entity, to: Vec3) {
+    app.world_mut().get_mut::<Transform>(listener).unwrap().translation = to;
+    app.update();
+    app.update();
+}
+
+#[test]
+fn footsteps_on_stone() {
+    let mut app = app();
+    let start = Vec3::new(2.0, 41.6, 2.0);
+    let listener = app
+        .world_mut()
+        .spawn((SoundListener, Footsteps::new(2.0, 1.6), Transform::from_translation(start)))
+        .id();
+    app.update();
+
+    for step in 1..=10 {
+        move_listener(&mut app, listener, start + Vec3::X * step as f32 * 0.5);
+    }
+    // Falling does not make steps
+    move_listener(&mut app, listener, start + Vec3::new(5.0, -1.0, 0.0));
+
+    let null = app.world().resource::<NullAudio>();
+    assert_eq!(null.played.len(), 2, "{:?}", null.played);
+    for sound in &null.played {
+        assert!(sound.path.starts_with("sounds/blocks/stone/step"), "{}", sound.path);
+        assert_eq!(sound.position.unwrap().y, 39.5);
+    }
+}
+
+#[test]
+fn ambient_follows_the_listener_underground() {
+    let mut app = app();
+    let settings = app.world().resource::<AmbientSettings>().clone();
+    let listener = app
+        .world_mut()
+        .spawn((SoundListener, Transform::from_xyz(8.0, 60.0, 8.0)))
+        .id();
+    for _ in 0..40 {
+        app.update();
+    }
+    assert_eq!(app.world().resource::<AmbientMix>().zone, Some(AmbientZone::Surface));
+    assert_eq!(app.world().resource::<NullAudio>().ambient, vec![(settings.surface.clone(), settings.volume)]);
+
+    // Into the rock, the cave track fades in over `fade` seconds
+    move_listener(&mut app, listener, Vec3::new(8.0, 20.0, 8.0));
+    let mix = app.world().resource::<AmbientMix>();
+    assert_eq!(mix.zone, Some(AmbientZone::Underground));
+    assert!(mix.volume(&settings.underground) > 0.0);
+    assert!(mix.volume(&settings.surface) > 0.0);
+
+    for _ in 0..40 {
+        app.update();
+    }
+    assert_eq!(app.world().resource::<NullAudio>().ambient, vec![(settings.underground, settings.volume)]);
+}
+
+#[test]
+fn surface_track_follows_the_biome() {
+    let mut app = app();
+    let desert = Biome {
+        name: "Desert".into(),
+        surface: 4,
+        subsurface: 4,
+        temperature: (f64::NEG_INFINITY, f64::INFINITY),
+        humidity: (f64::NEG_INFINITY, f64::INFINITY),
+        height_scale: 1.0,
+    };
+    let table = BiomeTable { climate: BTreeMap::new(), biomes: vec![desert] };
+    app.insert_resource(TerrainManager::new(2, 1, 0).with_biomes(table));
+    app.world_mut().resource_mut::<AmbientSettings>().biomes.insert("Desert".into(), "desert.ogg".into());
+    let volume = app.world().resource::<AmbientSettings>().volume;
+
+    app.world_mut().spawn((SoundListener, Transform::from_xyz(8.0, 60.0, 8.0)));
+    for _ in 0..40 {
+        app.update();
+    }
+    assert_eq!(app.world().resource::<ListenerBiome>().0.as_deref(), Some("Desert"));
+    assert_eq!(app.world().resource::<NullAudio>().ambient, vec![("desert.ogg".to_string(), volume)]);
+}
+
+#[test]
+fn records_one_shots_and_emitters() {
+    let mut app = app();
+    let emitter = SoundEmitter { path: "sounds/waterfall.ogg".into(), volume: 0.8, looping: true };
+    let entity = app.world_mut().spawn((emitter.clone(), Transform::default())).id();
+    let sound = PlaySound { path: "sounds/click.ogg".into(), position: None, volume: 1.0 };
+    app.world_mut().write_message(sound.clone());
+    app.update();
+    app.update();
+
+    let null = app.world().resource::<NullAudio>();
+    assert_eq!(null.played, vec![sound]);
+    assert_eq!(null.emitters, vec![(entity, emitter)]);
+}
@@ -0,0 +1,106 @@
+use bevy::math::IVec3;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BrushShape {
+    #[default]
+    Single,
+    Box,
+    Sphere,
+    /// Upright, from the target voxel up
+    Cylinder,
+}
+
+impl BrushShape {
+    pub const ALL: [BrushShape; 4] = [BrushShape::Single, BrushShape::Box, BrushShape::Sphere, BrushShape::Cylinder];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BrushShape::Single => "Single",
+            BrushShape::Box => "Box",
+            BrushShape::Sphere => "Sphere",
+            BrushShape::Cylinder => "Cylinder",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Brush {
+    pub shape: BrushShape,
+    /// Voxels from the center to the edge, the center excluded
+    pub radius: i32,
+    /// Layers of a cylinder
+    pub height: i32,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self { shape: BrushShape::Single, radius: 2, height: 4 }
+    }
+}
+
+impl Brush {
+    /// Voxels covered with the brush on `center`, in y, z, x order
+    pub fn positions(&self, center: IVec3) -> Vec<IVec3> {
+        let r = self.radius.max(0);
+        // Half a voxel of slack, so small spheres and discs are not diamonds
+        let round = |d: IVec3| (d.x * d.x + d.y * d.y + d.z * d.z) as f32 <= (r as f32 + 0.5).powi(2);
+        let (min, max) = match self.shape {
+            BrushShape::Single => return vec![center],
+            BrushShape::Box | BrushShape::Sphere => (IVec3::splat(-r), IVec3::splat(r)),
+            BrushShape::Cylinder => (IVec3::new(-r, 0, -r), IVec3::new(r, self.height.max(1) - 1, r)),
+        };
+
+        let mut positions = Vec::new();
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                for x in min.x..=max.x {
+                    let d = IVec3::new(x, y, z);
+                    let inside = match self.shape {
+                        BrushShape::Sphere => round(d),
+                        BrushShape::Cylinder => round(IVec3::new(x, 0, z)),
+                        _ => true,
+                    };
+                    if inside {
+                        positions.push(center + d);
+                    }
+                }
+            }
+        }
+        positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brush(shape: BrushShape, radius: i32, height: i32) -> Brush {
+        Brush { shape, radius, height }
+    }
+
+    #[test]
+    fn shapes_cover_the_expected_voxels() {
+        let center = IVec3::new(10, 40, -3);
+        assert_eq!(brush(BrushShape::Single, 5, 5).positions(center), vec![center]);
+        assert_eq!(brush(BrushShape::Box, 1, 0).positions(center).len(), 27);
+
+        let sphere = brush(BrushShape::Sphere, 1, 0).positions(center);
+        // The box without its 8 corners
+        assert_eq!(sphere.len(), 19);
+        assert!(!sphere.contains(&(center + IVec3::ONE)));
+
+        // 5x5 discs without their corners
+        let cylinder = brush(BrushShape::Cylinder, 2, 3).positions(center);
+        assert_eq!(cylinder.len(), 21 * 3);
+        assert!(cylinder.iter().all(|p| (center.y..center.y + 3).contains(&p.y)));
+    }
+
+    #[test]
+    fn sphere_is_symmetric() {
+        let sphere = brush(BrushShape::Sphere, 3, 0).positions(IVec3::ZERO);
+        for p in &sphere {
+            assert!(sphere.contains(&-*p));
+            assert!(sphere.contains(&IVec3::new(p.z, p.x, p.y)));
+        }
+    }
+}
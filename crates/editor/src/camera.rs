@@ -0,0 +1,58 @@
+use bevy::input::mouse::MouseMotion;
+use bevy::prelude::*;
+use bevy_egui::input::EguiWantsInput;
+
+/// Fly camera: WASD to move, Space and Shift for up and down, hold the right
+/// mouse button to look around. Edits are raycast from it.
+#[derive(Component)]
+pub struct EditorCamera {
+    /// Voxels per second
+    pub speed: f32,
+    /// Radians per pixel of mouse motion
+    pub sensitivity: f32,
+}
+
+impl Default for EditorCamera {
+    fn default() -> Self {
+        Self { speed: 30.0, sensitivity: 0.003 }
+    }
+}
+
+impl EditorCamera {
+    pub fn fly(
+        time: Res<Time>,
+        keys: Res<ButtonInput<KeyCode>>,
+        mouse: Res<ButtonInput<MouseButton>>,
+        mut motion: MessageReader<MouseMotion>,
+        egui: Res<EguiWantsInput>,
+        mut cameras: Query<(&EditorCamera, &mut Transform)>,
+    ) {
+        let look: Vec2 = motion.read().map(|m| m.delta).sum();
+        for (camera, mut transform) in &mut cameras {
+            if mouse.pressed(MouseButton::Right) && !egui.wants_any_pointer_input() {
+                let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+                let yaw = yaw - look.x * camera.sensitivity;
+                let pitch = (pitch - look.y * camera.sensitivity).clamp(-1.54, 1.54);
+                transform.rotation = Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0);
+            }
+
+            if egui.wants_any_keyboard_input() {
+                continue;
+            }
+            let mut direction = Vec3::ZERO;
+            for (key, axis) in [
+                (KeyCode::KeyW, transform.forward().as_vec3()),
+                (KeyCode::KeyS, transform.back().as_vec3()),
+                (KeyCode::KeyA, transform.left().as_vec3()),
+                (KeyCode::KeyD, transform.right().as_vec3()),
+                (KeyCode::Space, Vec3::Y),
+                (KeyCode::ShiftLeft, Vec3::NEG_Y),
+            ] {
+                if keys.pressed(key) {
+                    direction += axis;
+                }
+            }
+            transform.translation += direction.normalize_or_zero() * camera.speed * time.delta_secs();
+        }
+    }
+}
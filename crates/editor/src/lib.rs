@@ -1,5 +1,12 @@
-//! Editor helpers (egui)
+//! World editor (egui): a block palette, brushes, selections with fill, replace and
+//! copy/paste, editing the chunks of a `WorldSave` and saving them back.
 
-pub fn open_editor() {
-    println!("editor open");
-}
+pub mod brush;
+pub mod camera;
+pub mod ops;
+pub mod plugin;
+pub mod selection;
+pub mod ui;
+
+pub use camera::EditorCamera;
+pub use plugin::EditorPlugin;
@@ -0,0 +1,88 @@
+use bevy::math::IVec3;
+
+use assets::structure::StructureTemplate;
+use engine::terrain::{
+    structures::{capture_structure, place_structure, Placement, VoxelAccess},
+    types::Voxel,
+};
+
+use crate::selection::Selection;
+
+/// Sets every position to `voxel`, skipping unloaded ones and the ones that already
+/// hold it. Returns how many changed.
+pub fn paint(access: &mut impl VoxelAccess, positions: impl IntoIterator<Item = IVec3>, voxel: Voxel) -> usize {
+    let mut changed = 0;
+    for pos in positions {
+        if access.get_voxel(pos).is_some_and(|current| current != voxel) && access.set_voxel(pos, voxel) {
+            changed += 1;
+        }
+    }
+    changed
+}
+
+/// Like `paint`, but only over voxels that are `from`
+pub fn replace(
+    access: &mut impl VoxelAccess,
+    positions: impl IntoIterator<Item = IVec3>,
+    from: Voxel,
+    to: Voxel,
+) -> usize {
+    let matching: Vec<IVec3> = positions.into_iter().filter(|pos| access.get_voxel(*pos) == Some(from)).collect();
+    paint(access, matching, to)
+}
+
+/// The selection as a template anchored on its minimum corner, air included
+pub fn copy(access: &impl VoxelAccess, selection: Selection) -> StructureTemplate {
+    capture_structure(access, selection.min, selection.max, selection.min, true)
+}
+
+/// Places a copied selection with its minimum corner on `origin`
+pub fn paste(access: &mut impl VoxelAccess, template: &StructureTemplate, origin: IVec3, placement: Placement) -> usize {
+    place_structure(access, template, origin, placement)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use bevy::math::IVec2;
+    use engine::terrain::{history::EditRecorder, structures::test_utils::empty_chunks};
+
+    use super::*;
+
+    const CHUNKS: [IVec2; 2] = [IVec2::ZERO, IVec2::X];
+
+    #[test]
+    fn paint_and_replace() {
+        let mut world = empty_chunks(CHUNKS);
+        let mut recorder = EditRecorder::new(&mut world);
+
+        let selection = Selection::from_corners(IVec3::new(14, 5, 0), IVec3::new(17, 6, 1));
+        // The chunk at x 1 is loaded, the one at x 2 is not
+        assert_eq!(paint(&mut recorder, selection.positions(), Voxel::STONE), 16);
+        assert_eq!(paint(&mut recorder, selection.positions(), Voxel::STONE), 0);
+        assert_eq!(paint(&mut recorder, [IVec3::new(40, 5, 0)], Voxel::STONE), 0);
+
+        let column = [IVec3::new(15, 4, 0), IVec3::new(15, 5, 0), IVec3::new(15, 6, 0)];
+        assert_eq!(replace(&mut recorder, column, Voxel::STONE, Voxel(3)), 2);
+        let chunks: HashSet<IVec2> = recorder.finish().chunks().collect();
+        assert_eq!(chunks, HashSet::from([IVec2::ZERO, IVec2::X]));
+        assert_eq!(world.get_voxel(IVec3::new(15, 4, 0)), Some(Voxel::AIR));
+        assert_eq!(world.get_voxel(IVec3::new(15, 6, 0)), Some(Voxel(3)));
+    }
+
+    #[test]
+    fn copy_paste_keeps_air() {
+        let mut world = empty_chunks(CHUNKS);
+        paint(&mut world, [IVec3::new(1, 1, 1), IVec3::new(2, 2, 2)], Voxel::STONE);
+        let template = copy(&world, Selection::from_corners(IVec3::ONE, IVec3::splat(2)));
+
+        // Pasted over solid ground, the air of the copy clears it
+        let target = IVec3::new(20, 10, 5);
+        paint(&mut world, Selection::from_corners(target, target + IVec3::ONE).positions(), Voxel(3));
+        assert_eq!(paste(&mut world, &template, target, Placement::default()), 8);
+        assert_eq!(world.get_voxel(target), Some(Voxel::STONE));
+        assert_eq!(world.get_voxel(target + IVec3::X), Some(Voxel::AIR));
+        assert_eq!(world.get_voxel(target + IVec3::ONE), Some(Voxel::STONE));
+    }
+}
@@ -0,0 +1,422 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{input::EguiWantsInput, EguiPlugin, EguiPrimaryContextPass};
+
+use assets::structure::StructureTemplate;
+use engine::terrain::{
+    collision::{raycast, RayHit},
+    config::WorldConfig,
+    constants::{CHUNK_DEPTH, CHUNK_WIDTH},
+    ecs::{
+        components::chunk::ChunkData,
+        resources::{chunk::ChunkMap, voxel::VoxelRegistry},
+    },
+    generator::TerrainManager,
+    history::{EditHistory, EditRecorder},
+    storage::WorldSave,
+    structures::{structure_bounds, Placement, WorldVoxels},
+    types::Voxel,
+};
+
+use crate::{
+    brush::Brush,
+    camera::EditorCamera,
+    ops::{copy, paint, paste, replace},
+    selection::Selection,
+    ui::EditorUi,
+};
+
+/// How far the cursor reaches into the world, in voxels
+pub const MAX_REACH: f32 = 256.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tool {
+    /// Brush of the palette block against the face under the cursor
+    #[default]
+    Place,
+    /// Brush of air on the voxel under the cursor
+    Erase,
+    /// Brush that only paints over the replaced block
+    Replace,
+    /// Two clicks for the two corners
+    Select,
+    /// The clipboard against the face under the cursor
+    Paste,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 5] = [Tool::Place, Tool::Erase, Tool::Replace, Tool::Select, Tool::Paste];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Tool::Place => "Place",
+            Tool::Erase => "Erase",
+            Tool::Replace => "Replace",
+            Tool::Select => "Select",
+            Tool::Paste => "Paste",
+        }
+    }
+
+    /// Keeps painting while the mouse button is held
+    fn is_brush(self) -> bool {
+        matches!(self, Tool::Place | Tool::Erase | Tool::Replace)
+    }
+
+    /// Whether the tool works on the voxel under the cursor or the one in front of it
+    fn target(self, hit: &RayHit) -> IVec3 {
+        match self {
+            Tool::Place | Tool::Paste => hit.position + hit.normal,
+            Tool::Erase | Tool::Replace | Tool::Select => hit.position,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct EditorState {
+    pub tool: Tool,
+    pub brush: Brush,
+    /// Palette block, placed by the brushes and used by fill and replace
+    pub block: Voxel,
+    /// Block the replace tools paint over
+    pub replace_from: Voxel,
+    pub selection: Option<Selection>,
+    /// First corner of a selection in progress
+    pub selection_start: Option<IVec3>,
+    pub clipboard: Option<StructureTemplate>,
+    pub placement: Placement,
+    /// Chunks edited since the last save
+    pub unsaved: HashSet<IVec2>,
+    /// Outcome of the last action, shown in the panel
+    pub status: String,
+}
+
+impl Default for EditorState {
+    fn default() -> Self {
+        Self {
+            tool: Tool::default(),
+            brush: Brush::default(),
+            block: Voxel::STONE,
+            replace_from: Voxel::STONE,
+            selection: None,
+            selection_start: None,
+            clipboard: None,
+            placement: Placement::default(),
+            unsaved: HashSet::new(),
+            status: String::new(),
+        }
+    }
+}
+
+/// What the cursor points at, updated every frame
+#[derive(Resource, Default)]
+pub struct EditorCursor {
+    pub hit: Option<RayHit>,
+}
+
+/// The world being edited
+#[derive(Resource)]
+pub struct EditorWorld(pub WorldSave);
+
+/// Requests from the panel and the keyboard shortcuts
+#[derive(Message, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditorAction {
+    /// Sets the whole selection to the palette block
+    Fill,
+    /// Replaces the replaced block with the palette block inside the selection
+    Replace,
+    Copy,
+    ClearSelection,
+    Undo,
+    Redo,
+    Save,
+}
+
+pub struct EditorTask;
+
+impl EditorTask {
+    /// Requests the chunks around the camera when it enters another chunk
+    pub fn stream(
+        mut last: Local<Option<IVec2>>,
+        cameras: Query<&GlobalTransform, With<EditorCamera>>,
+        config: Res<WorldConfig>,
+        mut manager: ResMut<TerrainManager>,
+    ) {
+        let Ok(camera) = cameras.single() else {
+            return;
+        };
+        let position = camera.translation();
+        let chunk = IVec2::new(
+            (position.x as i32).div_euclid(CHUNK_WIDTH),
+            (position.z as i32).div_euclid(CHUNK_DEPTH),
+        );
+        if *last != Some(chunk) {
+            *last = Some(chunk);
+            manager.request_around(chunk, config.view_radius);
+        }
+    }
+
+    /// Raycasts from the camera through the mouse cursor
+    pub fn pick(
+        windows: Query<&Window, With<PrimaryWindow>>,
+        cameras: Query<(&Camera, &GlobalTransform), With<EditorCamera>>,
+        voxels: WorldVoxels,
+        registry: Res<VoxelRegistry>,
+        mut cursor: ResMut<EditorCursor>,
+    ) {
+        cursor.hit = None;
+        let (Ok(window), Ok((camera, transform))) = (windows.single(), cameras.single()) else {
+            return;
+        };
+        let Some(ray) = window
+            .cursor_position()
+            .and_then(|position| camera.viewport_to_world(transform, position).ok())
+        else {
+            return;
+        };
+        cursor.hit = raycast(&voxels, &registry, ray.origin, *ray.direction, MAX_REACH);
+    }
+
+    /// Runs the current tool on a left click. Brushes keep painting while the button
+    /// is held and the cursor moves, the whole stroke is one undo step.
+    pub fn click(
+        mut stroke: Local<Option<IVec3>>,
+        mouse: Res<ButtonInput<MouseButton>>,
+        egui: Res<EguiWantsInput>,
+        cursor: Res<EditorCursor>,
+        mut state: ResMut<EditorState>,
+        mut history: ResMut<EditHistory>,
+        mut voxels: WorldVoxels,
+    ) {
+        if !mouse.pressed(MouseButton::Left) && stroke.take().is_some() {
+            history.end_stroke();
+        }
+        let clicked = mouse.just_pressed(MouseButton::Left) && !egui.wants_any_pointer_input();
+        if !clicked && stroke.is_none() {
+            return;
+        }
+        let Some(hit) = cursor.hit else {
+            return;
+        };
+        let state = &mut *state;
+        let target = state.tool.target(&hit);
+        if !clicked && *stroke == Some(target) {
+            return;
+        }
+        if clicked && state.tool.is_brush() {
+            history.begin_stroke();
+        }
+        if state.tool.is_brush() {
+            *stroke = Some(target);
+        }
+
+        let mut recorder = EditRecorder::new(&mut voxels);
+        match state.tool {
+            Tool::Place => {
+                paint(&mut recorder, state.brush.positions(target), state.block);
+            }
+            Tool::Erase => {
+                paint(&mut recorder, state.brush.positions(target), Voxel::AIR);
+            }
+            Tool::Replace => {
+                replace(&mut recorder, state.brush.positions(target), state.replace_from, state.block);
+            }
+            Tool::Select => match state.selection_start.take() {
+                Some(start) => {
+                    let selection = Selection::from_corners(start, target);
+                    state.status = format!("selected {} voxels", selection.volume());
+                    state.selection = Some(selection);
+                }
+                None => {
+                    state.selection = Some(Selection::from_corners(target, target));
+                    state.selection_start = Some(target);
+                }
+            },
+            Tool::Paste => match &state.clipboard {
+                Some(template) => {
+                    let written = paste(&mut recorder, template, target, state.placement);
+                    state.status = format!("pasted {written} voxels");
+                }
+                None => state.status = "nothing to paste, copy a selection first".to_string(),
+            },
+        }
+        let batch = recorder.finish();
+        state.unsaved.extend(batch.chunks());
+        history.push(batch);
+    }
+
+    /// Ctrl+S saves, Ctrl+C copies, Ctrl+Z and Ctrl+Y (or Ctrl+Shift+Z) undo and redo,
+    /// Escape drops the selection
+    pub fn shortcuts(
+        keys: Res<ButtonInput<KeyCode>>,
+        egui: Res<EguiWantsInput>,
+        mut actions: MessageWriter<EditorAction>,
+    ) {
+        if egui.wants_any_keyboard_input() {
+            return;
+        }
+        let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+        if ctrl && keys.just_pressed(KeyCode::KeyS) {
+            actions.write(EditorAction::Save);
+        }
+        if ctrl && keys.just_pressed(KeyCode::KeyC) {
+            actions.write(EditorAction::Copy);
+        }
+        let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        if ctrl && keys.just_pressed(KeyCode::KeyZ) {
+            actions.write(if shift { EditorAction::Redo } else { EditorAction::Undo });
+        }
+        if ctrl && keys.just_pressed(KeyCode::KeyY) {
+            actions.write(EditorAction::Redo);
+        }
+        if keys.just_pressed(KeyCode::Escape) {
+            actions.write(EditorAction::ClearSelection);
+        }
+    }
+
+    /// Undo, redo, and fill, replace and copy over the selection
+    pub fn apply_actions(
+        mut actions: MessageReader<EditorAction>,
+        mut state: ResMut<EditorState>,
+        mut history: ResMut<EditHistory>,
+        mut voxels: WorldVoxels,
+    ) {
+        let state = &mut *state;
+        for action in actions.read() {
+            match action {
+                EditorAction::ClearSelection => {
+                    state.selection = None;
+                    state.selection_start = None;
+                    continue;
+                }
+                EditorAction::Undo | EditorAction::Redo => {
+                    let chunks = if *action == EditorAction::Undo {
+                        history.undo(&mut voxels)
+                    } else {
+                        history.redo(&mut voxels)
+                    };
+                    match chunks {
+                        Some(chunks) => state.unsaved.extend(chunks),
+                        None => state.status = "nothing to undo or redo".to_string(),
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            let Some(selection) = state.selection else {
+                continue;
+            };
+            match action {
+                EditorAction::Fill | EditorAction::Replace => {
+                    let mut recorder = EditRecorder::new(&mut voxels);
+                    let changed = if *action == EditorAction::Fill {
+                        paint(&mut recorder, selection.positions(), state.block)
+                    } else {
+                        replace(&mut recorder, selection.positions(), state.replace_from, state.block)
+                    };
+                    let batch = recorder.finish();
+                    state.unsaved.extend(batch.chunks());
+                    history.push(batch);
+                    state.status = format!("changed {changed} voxels");
+                }
+                EditorAction::Copy => {
+                    state.clipboard = Some(copy(&voxels, selection));
+                    state.tool = Tool::Paste;
+                    state.status = format!("copied {} voxels", selection.volume());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Writes the edited chunks into the world's region files
+    pub fn save(
+        mut actions: MessageReader<EditorAction>,
+        world: Res<EditorWorld>,
+        mut state: ResMut<EditorState>,
+        chunk_map: Res<ChunkMap>,
+        chunks: Query<&ChunkData>,
+    ) {
+        if !actions.read().any(|action| *action == EditorAction::Save) {
+            return;
+        }
+        let edited: Vec<(IVec2, &ChunkData)> = state
+            .unsaved
+            .iter()
+            .filter_map(|coords| Some((*coords, chunks.get(chunk_map.get(*coords)?).ok()?)))
+            .collect();
+        match world.0.save_chunks(edited) {
+            Ok(saved) => {
+                state.status = format!("saved {saved} chunks to {}", world.0.store().root().display());
+                state.unsaved.clear();
+            }
+            Err(e) => state.status = format!("save failed: {e}"),
+        }
+    }
+
+    /// Outlines the voxel under the cursor, the selection and the paste preview
+    pub fn draw(mut gizmos: Gizmos, cursor: Res<EditorCursor>, state: Res<EditorState>) {
+        let outline = |gizmos: &mut Gizmos, min: IVec3, max: IVec3, color: Color| {
+            let size = (max - min + IVec3::ONE).as_vec3();
+            let center = min.as_vec3() + size / 2.0;
+            // Slightly larger, so the faces don't hide the lines
+            gizmos.cube(Transform::from_translation(center).with_scale(size + 0.02), color);
+        };
+
+        if let Some(selection) = state.selection {
+            outline(&mut gizmos, selection.min, selection.max, Color::srgb(0.2, 0.6, 1.0));
+        }
+        let Some(hit) = cursor.hit else {
+            return;
+        };
+        let target = state.tool.target(&hit);
+        match (&state.tool, &state.clipboard) {
+            (Tool::Paste, Some(template)) => {
+                let (min, max) = structure_bounds(template, target, state.placement);
+                outline(&mut gizmos, min, max, Color::srgb(1.0, 0.8, 0.2));
+            }
+            _ => outline(&mut gizmos, target, target, Color::WHITE),
+        }
+    }
+}
+
+/// Egui world editor over `VoxelTerrainPlugin`, which the app adds with the world's
+/// config and generator. The camera to edit from needs an `EditorCamera`.
+pub struct EditorPlugin {
+    world: WorldSave,
+}
+
+impl EditorPlugin {
+    pub fn new(world: WorldSave) -> Self {
+        Self { world }
+    }
+}
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin::default());
+        }
+        app.insert_resource(EditorWorld(self.world.clone()))
+            .init_resource::<EditorState>()
+            .init_resource::<EditorCursor>()
+            .init_resource::<EditHistory>()
+            .add_message::<EditorAction>()
+            .add_systems(
+                Update,
+                (
+                    EditorCamera::fly,
+                    EditorTask::stream,
+                    EditorTask::pick,
+                    EditorTask::click,
+                    EditorTask::shortcuts,
+                    EditorTask::apply_actions,
+                    EditorTask::save,
+                    EditorTask::draw,
+                )
+                    .chain(),
+            )
+            .add_systems(EguiPrimaryContextPass, EditorUi::panel);
+    }
+}
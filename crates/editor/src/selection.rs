@@ -0,0 +1,35 @@
+use bevy::math::IVec3;
+
+/// A box of voxels, both corners included
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Selection {
+    pub min: IVec3,
+    pub max: IVec3,
+}
+
+impl Selection {
+    pub fn from_corners(a: IVec3, b: IVec3) -> Self {
+        Self { min: a.min(b), max: a.max(b) }
+    }
+
+    pub fn size(&self) -> IVec3 {
+        self.max - self.min + IVec3::ONE
+    }
+
+    pub fn volume(&self) -> usize {
+        let size = self.size();
+        size.x as usize * size.y as usize * size.z as usize
+    }
+
+    pub fn contains(&self, pos: IVec3) -> bool {
+        pos.cmpge(self.min).all() && pos.cmple(self.max).all()
+    }
+
+    /// Every voxel, in y, z, x order
+    pub fn positions(&self) -> impl Iterator<Item = IVec3> {
+        let Selection { min, max } = *self;
+        (min.y..=max.y).flat_map(move |y| {
+            (min.z..=max.z).flat_map(move |z| (min.x..=max.x).map(move |x| IVec3::new(x, y, z)))
+        })
+    }
+}
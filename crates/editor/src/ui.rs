@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use engine::terrain::{
+    ecs::resources::voxel::VoxelRegistry,
+    history::EditHistory,
+    structures::Rotation,
+    types::Voxel,
+};
+
+use crate::{
+    brush::BrushShape,
+    plugin::{EditorAction, EditorCursor, EditorState, Tool},
+};
+
+fn block_name(registry: &VoxelRegistry, voxel: Voxel) -> String {
+    match registry.definitions.get(&voxel) {
+        Some(definition) => definition.name.clone(),
+        None => format!("#{}", voxel.id()),
+    }
+}
+
+fn rotation_label(rotation: Rotation) -> &'static str {
+    match rotation {
+        Rotation::None => "0°",
+        Rotation::Cw90 => "90°",
+        Rotation::Cw180 => "180°",
+        Rotation::Cw270 => "270°",
+    }
+}
+
+pub struct EditorUi;
+
+impl EditorUi {
+    /// Side panel with the tools, brush, palette, selection and save button
+    pub fn panel(
+        mut contexts: EguiContexts,
+        mut state: ResMut<EditorState>,
+        registry: Res<VoxelRegistry>,
+        cursor: Res<EditorCursor>,
+        history: Res<EditHistory>,
+        mut actions: MessageWriter<EditorAction>,
+    ) -> Result {
+        let state = &mut *state;
+        egui::SidePanel::left("editor").show(contexts.ctx_mut()?, |ui| {
+            ui.heading("Tool");
+            ui.horizontal_wrapped(|ui| {
+                for tool in Tool::ALL {
+                    ui.selectable_value(&mut state.tool, tool, tool.label());
+                }
+            });
+
+            ui.horizontal(|ui| {
+                if ui.add_enabled(history.undo_len() > 0, egui::Button::new("Undo")).clicked() {
+                    actions.write(EditorAction::Undo);
+                }
+                if ui.add_enabled(history.redo_len() > 0, egui::Button::new("Redo")).clicked() {
+                    actions.write(EditorAction::Redo);
+                }
+                ui.label(format!("{} KiB of history", history.size_bytes() / 1024));
+            });
+
+            ui.separator();
+            ui.heading("Brush");
+            egui::ComboBox::from_label("Shape")
+                .selected_text(state.brush.shape.label())
+                .show_ui(ui, |ui| {
+                    for shape in BrushShape::ALL {
+                        ui.selectable_value(&mut state.brush.shape, shape, shape.label());
+                    }
+                });
+            if state.brush.shape != BrushShape::Single {
+                ui.add(egui::Slider::new(&mut state.brush.radius, 0..=16).text("Radius"));
+            }
+            if state.brush.shape == BrushShape::Cylinder {
+                ui.add(egui::Slider::new(&mut state.brush.height, 1..=64).text("Height"));
+            }
+
+            ui.separator();
+            ui.heading("Palette");
+            ui.label("Click to place, right click to replace");
+            let mut blocks: Vec<Voxel> = registry.definitions.keys().copied().collect();
+            blocks.sort();
+            egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                for voxel in blocks {
+                    let mut text = format!("{} {}", voxel.id(), block_name(&registry, voxel));
+                    if voxel == state.replace_from {
+                        text.push_str("  (replaced)");
+                    }
+                    let response = ui.selectable_label(voxel == state.block, text);
+                    if response.clicked() {
+                        state.block = voxel;
+                    }
+                    if response.secondary_clicked() {
+                        state.replace_from = voxel;
+                    }
+                }
+            });
+
+            ui.separator();
+            ui.heading("Selection");
+            match state.selection {
+                Some(selection) => {
+                    let size = selection.size();
+                    ui.label(format!("{} to {}, {}x{}x{}", selection.min, selection.max, size.x, size.y, size.z));
+                    ui.horizontal_wrapped(|ui| {
+                        for (label, action) in [
+                            ("Fill", EditorAction::Fill),
+                            ("Replace", EditorAction::Replace),
+                            ("Copy", EditorAction::Copy),
+                            ("Clear", EditorAction::ClearSelection),
+                        ] {
+                            if ui.button(label).clicked() {
+                                actions.write(action);
+                            }
+                        }
+                    });
+                }
+                None => {
+                    ui.label("Click two corners with the Select tool");
+                }
+            }
+
+            if let Some(template) = &state.clipboard {
+                ui.separator();
+                ui.heading("Clipboard");
+                let (x, y, z) = template.size;
+                ui.label(format!("{x}x{y}x{z}"));
+                egui::ComboBox::from_label("Rotation")
+                    .selected_text(rotation_label(state.placement.rotation))
+                    .show_ui(ui, |ui| {
+                        for rotation in Rotation::ALL {
+                            ui.selectable_value(&mut state.placement.rotation, rotation, rotation_label(rotation));
+                        }
+                    });
+                ui.checkbox(&mut state.placement.mirror_x, "Mirror X");
+                ui.checkbox(&mut state.placement.mirror_z, "Mirror Z");
+            }
+
+            ui.separator();
+            ui.heading("World");
+            ui.label(format!("{} unsaved chunks", state.unsaved.len()));
+            if ui.button("Save").clicked() {
+                actions.write(EditorAction::Save);
+            }
+
+            ui.separator();
+            match cursor.hit {
+                Some(hit) => ui.label(format!("{} at {}", block_name(&registry, hit.voxel), hit.position)),
+                None => ui.label("-"),
+            };
+            ui.label(&state.status);
+        });
+        Ok(())
+    }
+}
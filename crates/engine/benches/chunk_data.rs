@@ -0,0 +1,53 @@
+//! Voxel access on `ChunkData`, see `meshing.rs` for comparing against a baseline.
+
+mod common;
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use engine::terrain::{
+    constants::{CHUNK_DEPTH, CHUNK_HEIGHT, CHUNK_WIDTH},
+    ecs::components::chunk::ChunkData,
+    types::Voxel,
+};
+
+/// Every position of a chunk in storage order
+fn positions() -> impl Iterator<Item = (i32, i32, i32)> {
+    (0..CHUNK_HEIGHT).flat_map(|y| (0..CHUNK_DEPTH).flat_map(move |z| (0..CHUNK_WIDTH).map(move |x| (x, y, z))))
+}
+
+fn access(c: &mut Criterion) {
+    let chunk = common::typical();
+
+    let mut group = c.benchmark_group("chunk_data");
+    group.bench_function("get every voxel", |b| {
+        b.iter(|| {
+            positions()
+                .filter(|&(x, y, z)| black_box(&chunk).get(x, y, z) != Voxel::AIR)
+                .count()
+        })
+    });
+    group.bench_function("set every voxel", |b| {
+        b.iter_batched_ref(
+            ChunkData::new,
+            |chunk| {
+                for (x, y, z) in positions() {
+                    chunk.set(x, y, z, black_box(Voxel::STONE));
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("fill_layer_below", |b| {
+        b.iter_batched_ref(
+            ChunkData::new,
+            |chunk| chunk.fill_layer_below(black_box(CHUNK_HEIGHT / 2), Voxel::STONE),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, access);
+criterion_main!(benches);
@@ -0,0 +1,44 @@
+//! Deterministic inputs shared by the benches, so runs on different commits measure
+//! the same work.
+
+#![allow(dead_code)]
+
+use bevy::math::IVec2;
+
+use engine::terrain::{
+    constants::{CHUNK_DEPTH, CHUNK_HEIGHT, CHUNK_WIDTH},
+    ecs::components::chunk::{ChunkCoords, ChunkData},
+    generator::TerrainManager,
+    types::Voxel,
+};
+
+pub const SEED: i32 = 42;
+
+/// Every other voxel solid, the most faces a chunk can have
+pub fn checkerboard() -> ChunkData {
+    let mut chunk = ChunkData::new();
+    for y in 0..CHUNK_HEIGHT {
+        for z in 0..CHUNK_DEPTH {
+            for x in 0..CHUNK_WIDTH {
+                if (x + y + z) % 2 == 0 {
+                    chunk.set(x, y, z, Voxel::STONE);
+                }
+            }
+        }
+    }
+    chunk
+}
+
+/// A generated chunk with the default noise settings
+pub fn typical() -> ChunkData {
+    TerrainManager::new(1, 1, SEED).run(ChunkCoords(IVec2::new(12, -7)))
+}
+
+pub fn empty() -> ChunkData {
+    ChunkData::new()
+}
+
+/// Named inputs for the per-chunk benches
+pub fn chunks() -> [(&'static str, ChunkData); 3] {
+    [("checkerboard", checkerboard()), ("typical", typical()), ("empty", empty())]
+}
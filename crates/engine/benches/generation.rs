@@ -0,0 +1,30 @@
+//! Terrain shape generation, see `meshing.rs` for comparing against a baseline.
+
+mod common;
+
+use std::hint::black_box;
+
+use bevy::math::IVec2;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use engine::terrain::{ecs::components::chunk::ChunkCoords, generator::TerrainManager};
+
+const SEEDS: [i32; 3] = [common::SEED, 7, -1_234_567];
+const POSITIONS: [IVec2; 3] = [IVec2::new(0, 0), IVec2::new(12, -7), IVec2::new(-4_000, 9_000)];
+
+fn generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generation");
+    for seed in SEEDS {
+        let manager = TerrainManager::new(1, 1, seed);
+        for coords in POSITIONS {
+            let id = format!("seed {seed} at {},{}", coords.x, coords.y);
+            group.bench_with_input(BenchmarkId::from_parameter(id), &coords, |b, coords| {
+                b.iter(|| manager.run(ChunkCoords(black_box(*coords))))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, generation);
+criterion_main!(benches);
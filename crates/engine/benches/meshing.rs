@@ -0,0 +1,46 @@
+//! `cargo bench -p engine --bench meshing -- --save-baseline main` on the commit before the
+//! change being measured, then `-- --baseline main` on the change to compare.
+
+mod common;
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+
+use engine::terrain::{
+    ecs::resources::voxel::VoxelRegistry,
+    meshing::{bevy_meshing::meshdata_to_bevy_mesh, binary_greedy::binary_greedy_mesh, greedy::greedy_mesh},
+};
+
+/// Greedy vs binary greedy meshing on worst case, generated and empty chunks
+fn meshers(c: &mut Criterion) {
+    let registry = VoxelRegistry::default();
+
+    let mut group = c.benchmark_group("meshing");
+    for (name, chunk) in common::chunks() {
+        group.bench_with_input(BenchmarkId::new("greedy", name), &chunk, |b, chunk| {
+            b.iter(|| greedy_mesh(black_box(chunk), &registry))
+        });
+        group.bench_with_input(BenchmarkId::new("binary_greedy", name), &chunk, |b, chunk| {
+            b.iter(|| binary_greedy_mesh(black_box(chunk), &registry))
+        });
+    }
+    group.finish();
+}
+
+/// Mesh data to a Bevy mesh, the CPU side of the upload
+fn bevy_meshes(c: &mut Criterion) {
+    let registry = VoxelRegistry::default();
+
+    let mut group = c.benchmark_group("bevy_mesh");
+    for (name, chunk) in common::chunks() {
+        let mesh = greedy_mesh(&chunk, &registry);
+        group.bench_with_input(BenchmarkId::from_parameter(name), &mesh, |b, mesh| {
+            b.iter_batched(|| mesh.clone(), meshdata_to_bevy_mesh, BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, meshers, bevy_meshes);
+criterion_main!(benches);
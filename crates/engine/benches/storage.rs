@@ -0,0 +1,51 @@
+//! Chunk and region encoding, see `meshing.rs` for comparing against a baseline.
+
+mod common;
+
+use std::hint::black_box;
+
+use bevy::math::IVec2;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use engine::terrain::storage::{decode_chunk, encode_chunk, Region, REGION_SIZE};
+
+fn codec(c: &mut Criterion) {
+    let mut group = c.benchmark_group("codec");
+    for (name, chunk) in common::chunks() {
+        group.bench_with_input(BenchmarkId::new("encode", name), &chunk, |b, chunk| {
+            b.iter(|| encode_chunk(black_box(chunk)))
+        });
+        let bytes = encode_chunk(&chunk);
+        group.bench_with_input(BenchmarkId::new("decode", name), &bytes, |b, bytes| {
+            b.iter(|| decode_chunk(black_box(bytes)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// A full region of generated chunks, written to and read from memory
+fn regions(c: &mut Criterion) {
+    let chunk = common::typical();
+    let mut region = Region::new();
+    for z in 0..REGION_SIZE {
+        for x in 0..REGION_SIZE {
+            region.insert(IVec2::new(x, z), &chunk);
+        }
+    }
+    let mut bytes = Vec::new();
+    region.write(&mut bytes).unwrap();
+
+    let mut group = c.benchmark_group("region");
+    group.bench_function("write", |b| {
+        b.iter(|| {
+            let mut out = Vec::with_capacity(bytes.len());
+            black_box(&region).write(&mut out).unwrap();
+            out
+        })
+    });
+    group.bench_function("read", |b| b.iter(|| Region::read(&mut black_box(bytes.as_slice())).unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, codec, regions);
+criterion_main!(benches);
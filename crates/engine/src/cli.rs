@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use bevy::math::IVec2;
+
+/// `--key value` pairs after the subcommand of a command line tool
+pub struct Args {
+    values: HashMap<String, String>,
+}
+
+impl Args {
+    pub fn parse(raw: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut values = HashMap::new();
+        let mut raw = raw.into_iter();
+        while let Some(key) = raw.next() {
+            let Some(name) = key.strip_prefix("--") else {
+                return Err(format!("unexpected argument `{key}`"));
+            };
+            let value = raw.next().ok_or_else(|| format!("missing value for `{key}`"))?;
+            values.insert(name.to_string(), value);
+        }
+        Ok(Self { values })
+    }
+
+    pub fn get<T: FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        self.values
+            .get(name)
+            .map(|v| v.parse().map_err(|_| format!("invalid value `{v}` for --{name}")))
+            .transpose()
+    }
+
+    pub fn get_or<T: FromStr>(&self, name: &str, default: T) -> Result<T, String> {
+        Ok(self.get(name)?.unwrap_or(default))
+    }
+
+    pub fn required<T: FromStr>(&self, name: &str) -> Result<T, String> {
+        self.get(name)?.ok_or_else(|| format!("--{name} is required"))
+    }
+
+    /// Chunk coordinates written as `x,z`
+    pub fn coords(&self, name: &str) -> Result<IVec2, String> {
+        let raw: String = self.required(name)?;
+        let (x, z) = raw
+            .split_once(',')
+            .ok_or_else(|| format!("--{name} expects `x,z`, got `{raw}`"))?;
+        let parse = |v: &str| v.trim().parse::<i32>().map_err(|_| format!("invalid coordinate in --{name}: `{raw}`"));
+        Ok(IVec2::new(parse(x)?, parse(z)?))
+    }
+}
@@ -0,0 +1,199 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin, EguiPrimaryContextPass};
+
+use crate::terrain::constants::{CHUNK_DEPTH, CHUNK_HEIGHT, CHUNK_WIDTH};
+use crate::terrain::ecs::components::chunk::{
+    ChunkCoords, ChunkData, ChunkDirty, ChunkGenTask, ChunkLight, ChunkMeshInfo, ChunkMeshTask, ChunkStage,
+};
+use crate::terrain::generator::TerrainManager;
+use crate::terrain::stats::{PipelineStage, TerrainStats};
+
+const STAGES: [ChunkStage; 6] = [
+    ChunkStage::Queued,
+    ChunkStage::Generated,
+    ChunkStage::Decorated,
+    ChunkStage::Lit,
+    ChunkStage::Meshed,
+    ChunkStage::Uploaded,
+];
+
+/// What the overlay shows. F3 toggles the panel, F4 the chunk borders.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct TerrainDebugSettings {
+    pub show_panel: bool,
+    pub chunk_borders: bool,
+}
+
+impl Default for TerrainDebugSettings {
+    fn default() -> Self {
+        Self { show_panel: true, chunk_borders: false }
+    }
+}
+
+/// egui panel with the chunk pipeline state, and gizmo chunk borders
+pub struct TerrainDebugPlugin;
+
+impl Plugin for TerrainDebugPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin::default());
+        }
+        app.init_resource::<TerrainDebugSettings>()
+            .add_systems(Update, (TerrainDebug::toggle, TerrainDebug::chunk_borders).chain())
+            .add_systems(EguiPrimaryContextPass, TerrainDebug::panel);
+    }
+}
+
+fn stage_color(stage: ChunkStage) -> Color {
+    match stage {
+        ChunkStage::Queued => Color::srgb(0.5, 0.5, 0.5),
+        ChunkStage::Generated => Color::srgb(1.0, 0.3, 0.3),
+        ChunkStage::Decorated => Color::srgb(1.0, 0.6, 0.2),
+        ChunkStage::Lit => Color::srgb(1.0, 1.0, 0.3),
+        ChunkStage::Meshed => Color::srgb(0.3, 0.6, 1.0),
+        ChunkStage::Uploaded => Color::srgb(0.3, 1.0, 0.3),
+    }
+}
+
+fn millis(duration: Option<Duration>) -> String {
+    match duration {
+        Some(duration) => format!("{:.2} ms", duration.as_secs_f64() * 1000.0),
+        None => "-".to_string(),
+    }
+}
+
+fn timing_row(ui: &mut egui::Ui, label: &str, percentile: impl Fn(f32) -> Option<Duration>) {
+    ui.label(label);
+    for p in [0.5, 0.9, 0.99] {
+        ui.label(millis(percentile(p)));
+    }
+    ui.end_row();
+}
+
+pub struct TerrainDebug;
+
+impl TerrainDebug {
+    pub fn toggle(keys: Res<ButtonInput<KeyCode>>, mut settings: ResMut<TerrainDebugSettings>) {
+        if keys.just_pressed(KeyCode::F3) {
+            settings.show_panel = !settings.show_panel;
+        }
+        if keys.just_pressed(KeyCode::F4) {
+            settings.chunk_borders = !settings.chunk_borders;
+        }
+    }
+
+    /// A box around every chunk, colored by its stage
+    pub fn chunk_borders(
+        settings: Res<TerrainDebugSettings>,
+        chunks: Query<(&ChunkCoords, &ChunkStage)>,
+        mut gizmos: Gizmos,
+    ) {
+        if !settings.chunk_borders {
+            return;
+        }
+        let size = Vec3::new(CHUNK_WIDTH as f32, CHUNK_HEIGHT as f32, CHUNK_DEPTH as f32);
+        for (coords, stage) in &chunks {
+            let min = Vec3::new(coords.x as f32 * size.x, 0.0, coords.y as f32 * size.z);
+            let transform = Transform::from_translation(min + size / 2.0).with_scale(size);
+            gizmos.cube(transform, stage_color(*stage));
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn panel(
+        mut contexts: EguiContexts,
+        mut settings: ResMut<TerrainDebugSettings>,
+        manager: Res<TerrainManager>,
+        stats: Res<TerrainStats>,
+        chunks: Query<(&ChunkCoords, &ChunkStage, Option<&ChunkMeshInfo>, Has<ChunkDirty>)>,
+        data: Query<(&ChunkData, Option<&ChunkLight>)>,
+        gen_tasks: Query<(), With<ChunkGenTask>>,
+        mesh_tasks: Query<(), With<ChunkMeshTask>>,
+    ) -> Result {
+        if !settings.show_panel {
+            return Ok(());
+        }
+
+        let mut per_stage = [0usize; STAGES.len()];
+        let mut vertices = 0;
+        let mut triangles = 0;
+        let mut rows: Vec<(IVec2, ChunkStage, usize, bool)> = Vec::new();
+        for (coords, stage, info, dirty) in &chunks {
+            per_stage[*stage as usize] += 1;
+            let info = info.copied().unwrap_or_default();
+            vertices += info.vertices;
+            triangles += info.triangles;
+            rows.push((coords.0, *stage, info.vertices, dirty));
+        }
+        rows.sort_by_key(|(coords, ..)| (coords.y, coords.x));
+
+        let mut voxel_bytes = 0;
+        let mut light_bytes = 0;
+        for (chunk, light) in &data {
+            voxel_bytes += std::mem::size_of_val(&*chunk.voxels);
+            light_bytes += light.map_or(0, |light| light.sky.len());
+        }
+
+        let settings = &mut *settings;
+        egui::Window::new("Terrain").default_width(320.0).show(contexts.ctx_mut()?, |ui| {
+            egui::Grid::new("stages").show(ui, |ui| {
+                for (stage, count) in STAGES.iter().zip(per_stage) {
+                    ui.label(format!("{stage:?}"));
+                    ui.label(count.to_string());
+                    ui.end_row();
+                }
+                ui.label("Requested");
+                ui.label(manager.requested.len().to_string());
+                ui.end_row();
+            });
+
+            ui.separator();
+            ui.label(format!(
+                "In flight: {} generating, {} meshing, {}/{} permits",
+                gen_tasks.iter().len(),
+                mesh_tasks.iter().len(),
+                manager.active_permits,
+                manager.config.threads,
+            ));
+            ui.label(format!("Completed: {} generated, {} meshed", stats.generated, stats.meshed));
+            egui::Grid::new("timings").show(ui, |ui| {
+                ui.label("");
+                for header in ["p50", "p90", "p99"] {
+                    ui.label(header);
+                }
+                ui.end_row();
+                timing_row(ui, "Generation", |p| stats.generation.percentile(p));
+                timing_row(ui, "Meshing", |p| stats.meshing.percentile(p));
+                // Main thread stages, bucketed since startup
+                for stage in [PipelineStage::Decoration, PipelineStage::Lighting, PipelineStage::Upload] {
+                    timing_row(ui, &format!("{stage:?}"), |p| stats.histogram(stage).percentile(p));
+                }
+            });
+
+            ui.separator();
+            ui.label(format!("{vertices} vertices, {triangles} triangles"));
+            ui.label(format!(
+                "Voxels {:.1} MiB, light {:.1} MiB",
+                voxel_bytes as f64 / (1024.0 * 1024.0),
+                light_bytes as f64 / (1024.0 * 1024.0),
+            ));
+            ui.checkbox(&mut settings.chunk_borders, "Chunk borders (F4)");
+
+            ui.separator();
+            egui::ScrollArea::vertical().max_height(240.0).show_rows(
+                ui,
+                ui.text_style_height(&egui::TextStyle::Body),
+                rows.len(),
+                |ui, range| {
+                    for (coords, stage, vertices, dirty) in &rows[range] {
+                        let dirty = if *dirty { ", dirty" } else { "" };
+                        ui.label(format!("{coords}: {stage:?}, {vertices} vertices{dirty}"));
+                    }
+                },
+            );
+        });
+        Ok(())
+    }
+}
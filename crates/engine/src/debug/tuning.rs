@@ -0,0 +1,190 @@
+use std::sync::Arc;
+
+use bevy::asset::RenderAssetUsages;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy_egui::{egui, EguiContexts, EguiPlugin, EguiPrimaryContextPass, EguiTextureHandle};
+
+use crate::terrain::config::WorldConfig;
+use crate::terrain::constants::{CHUNK_DEPTH, CHUNK_HEIGHT, CHUNK_WIDTH};
+use crate::terrain::ecs::resources::chunk::ChunkMap;
+use crate::terrain::generator::{ChunkGenerator, GeneratorKind, GeneratorParams, TerrainManager};
+
+/// Side of the heightmap preview, one pixel per column
+pub const PREVIEW_SIZE: u32 = 128;
+
+/// Generator settings edited by the tuning panel
+#[derive(Resource, Clone, Debug)]
+pub struct TerrainTuning {
+    pub seed: i32,
+    pub params: GeneratorParams,
+    /// Settings changed, the preview is redrawn
+    changed: bool,
+    /// An edit was finished, the chunks are generated again
+    regenerate: bool,
+    preview: Handle<Image>,
+}
+
+/// egui panel editing the noise generator at runtime. Finished edits despawn every
+/// chunk and generate them again around the camera, with the new settings.
+///
+/// Replaces the `ChunkGenerator`, so it is meant for apps using the built-in one.
+pub struct TerrainTuningPlugin;
+
+impl Plugin for TerrainTuningPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin::default());
+        }
+        app.add_systems(Startup, TerrainTuner::setup)
+            .add_systems(EguiPrimaryContextPass, TerrainTuner::panel)
+            .add_systems(Update, (TerrainTuner::preview, TerrainTuner::regenerate).chain());
+    }
+}
+
+fn height_color(height: i32) -> [u8; 4] {
+    let shade = (height.clamp(0, CHUNK_HEIGHT) * 255 / CHUNK_HEIGHT) as u8;
+    [shade, shade, shade, 255]
+}
+
+/// Chunk under the camera, or the origin without one
+fn camera_chunk(cameras: &Query<&GlobalTransform, With<Camera3d>>) -> IVec2 {
+    let Some(camera) = cameras.iter().next() else {
+        return IVec2::ZERO;
+    };
+    let position = camera.translation().floor().as_ivec3();
+    IVec2::new(position.x.div_euclid(CHUNK_WIDTH), position.z.div_euclid(CHUNK_DEPTH))
+}
+
+pub struct TerrainTuner;
+
+impl TerrainTuner {
+    /// Starts from the world config, noise defaults for a flat world
+    pub fn setup(mut commands: Commands, config: Res<WorldConfig>, mut images: ResMut<Assets<Image>>) {
+        let params = match &config.generator {
+            GeneratorKind::Noise(params) => params.clone(),
+            GeneratorKind::Flat { .. } => GeneratorParams::default(),
+        };
+        let image = Image::new_fill(
+            Extent3d { width: PREVIEW_SIZE, height: PREVIEW_SIZE, depth_or_array_layers: 1 },
+            TextureDimension::D2,
+            &height_color(0),
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        commands.insert_resource(TerrainTuning {
+            seed: config.seed,
+            params,
+            changed: true,
+            regenerate: false,
+            preview: images.add(image),
+        });
+    }
+
+    pub fn panel(mut contexts: EguiContexts, tuning: Option<ResMut<TerrainTuning>>) -> Result {
+        let Some(mut tuning) = tuning else {
+            return Ok(());
+        };
+        let preview = contexts.add_image(EguiTextureHandle::Weak(tuning.preview.id()));
+        let tuning = &mut *tuning;
+        egui::Window::new("Generator").show(contexts.ctx_mut()?, |ui| {
+            ui.horizontal(|ui| {
+                let mut responses = Vec::new();
+                ui.vertical(|ui| {
+                    let params = &mut tuning.params;
+                    responses.push(ui.add(egui::DragValue::new(&mut tuning.seed).prefix("Seed ")));
+                    responses.push(ui.add(egui::Slider::new(&mut params.octaves, 1..=12).text("Octaves")));
+                    responses.push(ui.add(egui::Slider::new(&mut params.persistence, 0.05..=1.0).text("Gain")));
+                    responses.push(ui.add(egui::Slider::new(&mut params.lacunarity, 1.0..=4.0).text("Lacunarity")));
+                    responses.push(
+                        ui.add(egui::Slider::new(&mut params.frequency, 0.001..=0.1).logarithmic(true).text("Frequency")),
+                    );
+                    responses.push(ui.add(egui::Slider::new(&mut params.strength, 0.1..=4.0).text("Strength")));
+                    ui.separator();
+                    responses.push(
+                        ui.add(egui::Slider::new(&mut params.height_scale, 0.0..=CHUNK_HEIGHT as f32).text("Height scale")),
+                    );
+                    responses.push(
+                        ui.add(egui::Slider::new(&mut params.base_height, 0.0..=CHUNK_HEIGHT as f32).text("Base height")),
+                    );
+                    responses.push(ui.add(egui::Slider::new(&mut params.height_curve, 0.25..=4.0).text("Height curve")));
+                    if ui.button("Defaults").clicked() {
+                        *params = GeneratorParams::default();
+                        tuning.changed = true;
+                        tuning.regenerate = true;
+                    }
+                });
+                ui.image((preview, egui::vec2(PREVIEW_SIZE as f32 * 2.0, PREVIEW_SIZE as f32 * 2.0)));
+
+                for response in responses {
+                    if response.changed() {
+                        tuning.changed = true;
+                    }
+                    // Sliders regenerate once they are let go, typed values right away
+                    if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                        tuning.regenerate = true;
+                    }
+                }
+            });
+        });
+        Ok(())
+    }
+
+    /// Redraws the heightmap around the camera after a change
+    pub fn preview(
+        tuning: Option<ResMut<TerrainTuning>>,
+        cameras: Query<&GlobalTransform, With<Camera3d>>,
+        mut images: ResMut<Assets<Image>>,
+    ) {
+        let Some(mut tuning) = tuning else {
+            return;
+        };
+        if !tuning.changed {
+            return;
+        }
+        tuning.changed = false;
+        let Some(image) = images.get_mut(&tuning.preview) else {
+            return;
+        };
+        let Some(pixels) = image.data.as_mut() else {
+            return;
+        };
+
+        let manager = TerrainManager::with_params(0, 1, tuning.seed, tuning.params.clone());
+        let center = camera_chunk(&cameras) * IVec2::new(CHUNK_WIDTH, CHUNK_DEPTH);
+        let half = PREVIEW_SIZE as i32 / 2;
+        for (i, pixel) in pixels.chunks_exact_mut(4).enumerate() {
+            let x = center.x - half + (i as u32 % PREVIEW_SIZE) as i32;
+            let z = center.y - half + (i as u32 / PREVIEW_SIZE) as i32;
+            pixel.copy_from_slice(&height_color(manager.surface_height(x, z)));
+        }
+    }
+
+    /// Despawns every chunk and starts loading again around the camera with the new settings
+    pub fn regenerate(
+        mut commands: Commands,
+        tuning: Option<ResMut<TerrainTuning>>,
+        mut config: ResMut<WorldConfig>,
+        mut manager: ResMut<TerrainManager>,
+        mut generator: ResMut<ChunkGenerator>,
+        mut chunk_map: ResMut<ChunkMap>,
+        cameras: Query<&GlobalTransform, With<Camera3d>>,
+    ) {
+        let Some(mut tuning) = tuning else {
+            return;
+        };
+        if !tuning.regenerate {
+            return;
+        }
+        tuning.regenerate = false;
+
+        for (_, entity) in chunk_map.entities.drain() {
+            commands.entity(entity).despawn();
+        }
+        manager.regenerate(tuning.seed, &tuning.params, camera_chunk(&cameras));
+        *generator = ChunkGenerator(Arc::new(manager.clone()));
+        config.seed = tuning.seed;
+        config.generator = GeneratorKind::Noise(tuning.params.clone());
+        info!("regenerating terrain with seed {}", tuning.seed);
+    }
+}
@@ -0,0 +1,195 @@
+use std::f32::consts::TAU;
+
+use bevy::light::light_consts::lux;
+use bevy::prelude::*;
+
+use crate::terrain::render::{TerrainMaterial, TerrainMaterialHandle};
+
+use super::Weather;
+
+/// Tilt of the sun path away from the zenith, so the sun is never straight overhead
+const SUN_TILT: f32 = 0.35;
+/// `TerrainParams::sky_intensity` on a moonless night
+pub const NIGHT_SKY_INTENSITY: f32 = 0.12;
+
+/// Time of day, advanced by `Environment::advance`
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct DayNightCycle {
+    /// Real seconds per full day
+    pub day_length: f32,
+    /// Fraction of the day: 0 midnight, 0.25 sunrise, 0.5 noon, 0.75 sunset
+    pub time: f32,
+    pub paused: bool,
+}
+
+impl Default for DayNightCycle {
+    fn default() -> Self {
+        Self {
+            day_length: 20.0 * 60.0,
+            time: 0.3,
+            paused: false,
+        }
+    }
+}
+
+impl DayNightCycle {
+    pub fn advance(&mut self, seconds: f32) {
+        if !self.paused && self.day_length > 0.0 {
+            self.time = (self.time + seconds / self.day_length).rem_euclid(1.0);
+        }
+    }
+
+    /// Unit vector towards the sun, rising in +X and setting in -X
+    pub fn sun_direction(&self) -> Vec3 {
+        let angle = (self.time - 0.25) * TAU;
+        Quat::from_rotation_x(SUN_TILT) * Vec3::new(angle.cos(), angle.sin(), 0.0)
+    }
+
+    /// Sine of the sun height above the horizon, negative at night
+    pub fn sun_elevation(&self) -> f32 {
+        self.sun_direction().y
+    }
+
+    /// 0 at night, 1 once the sun is well above the horizon, smooth through dawn and dusk
+    pub fn daylight(&self) -> f32 {
+        let t = ((self.sun_elevation() + 0.1) / 0.35).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+/// The directional light following `DayNightCycle`. It lights and shades regular meshes only:
+/// `TerrainMaterial` ignores scene lights and shadows, terrain goes by its baked sky light.
+#[derive(Component)]
+pub struct Sun;
+
+/// Directional sun, sky dome and distance fog on a day/night cycle.
+/// The skylight baked in the chunk vertices is scaled with the daylight.
+#[derive(Clone, Default)]
+pub struct EnvironmentPlugin {
+    cycle: DayNightCycle,
+}
+
+impl EnvironmentPlugin {
+    /// Real seconds per full day
+    pub fn with_day_length(mut self, seconds: f32) -> Self {
+        self.cycle.day_length = seconds;
+        self
+    }
+
+    /// Fraction of the day to start at, 0.5 is noon
+    pub fn starting_at(mut self, time: f32) -> Self {
+        self.cycle.time = time.rem_euclid(1.0);
+        self
+    }
+}
+
+impl Plugin for EnvironmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.cycle.clone())
+            .add_systems(Startup, (Environment::spawn_sun, Environment::spawn_sky))
+            .add_systems(
+                Update,
+                (
+                    Environment::advance,
+                    (Environment::sun, Environment::skylight, Environment::fog, Environment::sky),
+                )
+                    .chain(),
+            );
+    }
+}
+
+pub struct Environment;
+
+impl Environment {
+    pub fn spawn_sun(mut commands: Commands) {
+        commands.spawn((
+            Sun,
+            DirectionalLight {
+                illuminance: lux::AMBIENT_DAYLIGHT,
+                ..default()
+            },
+            Transform::default(),
+        ));
+    }
+
+    pub fn advance(time: Res<Time>, mut cycle: ResMut<DayNightCycle>) {
+        cycle.advance(time.delta_secs());
+    }
+
+    /// Points the sun light from the sun, dims it and warms it up near the horizon.
+    /// Clouds from `Weather` dim it further, lightning flashes the ambient light.
+    pub fn sun(
+        cycle: Res<DayNightCycle>,
+        weather: Option<Res<Weather>>,
+        mut ambient: ResMut<GlobalAmbientLight>,
+        mut suns: Query<(&mut Transform, &mut DirectionalLight), With<Sun>>,
+    ) {
+        let daylight = cycle.daylight();
+        let direction = cycle.sun_direction();
+        // Below the horizon the light comes from the moon, opposite the sun
+        let towards_light = if direction.y >= 0.0 { direction } else { -direction };
+        let low_sun = 1.0 - direction.y.abs().min(1.0);
+        let (overcast, flash) = weather.map_or((0.0, 0.0), |weather| (weather.overcast(), weather.flash));
+
+        for (mut transform, mut light) in &mut suns {
+            *transform = Transform::default().looking_to(-towards_light, Vec3::Y);
+            light.illuminance = lux::FULL_MOON_NIGHT.max(lux::AMBIENT_DAYLIGHT * daylight * (1.0 - overcast));
+            light.color = Color::srgb(1.0, 1.0 - 0.35 * low_sun * daylight, 1.0 - 0.6 * low_sun * daylight);
+        }
+        ambient.brightness = 20.0 + 180.0 * daylight * (1.0 - overcast * 0.5) + 2000.0 * flash;
+    }
+
+    /// Scales the sky light baked in the vertices
+    pub fn skylight(
+        cycle: Res<DayNightCycle>,
+        handle: Option<Res<TerrainMaterialHandle>>,
+        mut materials: ResMut<Assets<TerrainMaterial>>,
+    ) {
+        let Some(handle) = handle else {
+            return;
+        };
+        let intensity = NIGHT_SKY_INTENSITY + (1.0 - NIGHT_SKY_INTENSITY) * cycle.daylight();
+        let Some(material) = materials.get(&handle.0) else {
+            return;
+        };
+        // Touching the asset re-uploads it, skip changes nobody can see
+        if (material.params.sky_intensity - intensity).abs() < 0.002 {
+            return;
+        }
+        if let Some(material) = materials.get_mut(&handle.0) {
+            material.params.sky_intensity = intensity;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: f32) -> DayNightCycle {
+        DayNightCycle { time, ..default() }
+    }
+
+    #[test]
+    fn sun_path() {
+        assert_eq!(at(0.5).daylight(), 1.0);
+        assert_eq!(at(0.0).daylight(), 0.0);
+        assert!(at(0.25).sun_elevation().abs() < 1e-6);
+        assert!(at(0.25).sun_direction().x > 0.9);
+        assert!(at(0.75).sun_direction().x < -0.9);
+        // Dawn is in between
+        let dawn = at(0.26).daylight();
+        assert!(dawn > 0.0 && dawn < 1.0);
+        assert!((at(0.5).sun_direction().length() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn advances_and_wraps() {
+        let mut cycle = DayNightCycle { day_length: 100.0, time: 0.9, paused: false };
+        cycle.advance(20.0);
+        assert!((cycle.time - 0.1).abs() < 1e-5);
+        cycle.paused = true;
+        cycle.advance(20.0);
+        assert!((cycle.time - 0.1).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,204 @@
+use bevy::camera::visibility::NoFrustumCulling;
+use bevy::light::{NotShadowCaster, NotShadowReceiver};
+use bevy::mesh::VertexAttributeValues;
+use bevy::pbr::{DistanceFog, FogFalloff};
+use bevy::prelude::*;
+
+use crate::terrain::config::WorldConfig;
+use crate::terrain::constants::CHUNK_WIDTH;
+
+use super::{DayNightCycle, Weather};
+
+const DAY_ZENITH: Vec3 = Vec3::new(0.22, 0.45, 0.85);
+const DAY_HORIZON: Vec3 = Vec3::new(0.65, 0.78, 0.92);
+const DUSK_HORIZON: Vec3 = Vec3::new(0.95, 0.5, 0.25);
+const NIGHT_ZENITH: Vec3 = Vec3::new(0.01, 0.01, 0.04);
+const NIGHT_HORIZON: Vec3 = Vec3::new(0.04, 0.05, 0.1);
+const SUN_GLOW: Vec3 = Vec3::new(1.0, 0.85, 0.6);
+/// How far the colors or the sun move before the dome is recolored
+const RECOLOR_THRESHOLD: f32 = 0.004;
+
+/// Inverted sphere around the camera with the sky gradient in its vertex colors
+#[derive(Component, Default)]
+pub struct SkyDome {
+    /// Colors and sun direction the vertex colors were last computed for
+    painted: Option<(SkyColors, Vec3)>,
+}
+
+/// Sky colors for the current time of day
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SkyColors {
+    pub zenith: Vec3,
+    pub horizon: Vec3,
+}
+
+impl SkyColors {
+    pub fn at(cycle: &DayNightCycle) -> Self {
+        let daylight = cycle.daylight();
+        // Orange horizon while the sun is low, on both sides of the horizon
+        let dusk = (1.0 - cycle.sun_elevation().abs() / 0.3).clamp(0.0, 1.0);
+        let horizon = NIGHT_HORIZON.lerp(DAY_HORIZON, daylight).lerp(DUSK_HORIZON, dusk * 0.6);
+        Self {
+            zenith: NIGHT_ZENITH.lerp(DAY_ZENITH, daylight),
+            horizon,
+        }
+    }
+
+    /// Washed out towards grey by clouds, 1 is fully overcast
+    pub fn overcast(self, amount: f32) -> Self {
+        let grey = |color: Vec3| Vec3::splat(color.dot(Vec3::new(0.2126, 0.7152, 0.0722)) * 0.7);
+        Self {
+            zenith: self.zenith.lerp(grey(self.zenith), amount),
+            horizon: self.horizon.lerp(grey(self.horizon), amount),
+        }
+    }
+
+    /// Whether either color moved by more than `threshold` from `other`
+    pub fn differs(&self, other: &Self, threshold: f32) -> bool {
+        self.zenith.distance(other.zenith) > threshold || self.horizon.distance(other.horizon) > threshold
+    }
+
+    /// Gradient from the horizon up, darker below it, with a glow towards the sun
+    pub fn sample(&self, direction: Vec3, sun: Vec3) -> Vec3 {
+        let up = direction.y;
+        let base = if up >= 0.0 {
+            self.horizon.lerp(self.zenith, up.sqrt())
+        } else {
+            self.horizon * (1.0 + up * 0.6)
+        };
+        let glow = direction.dot(sun).max(0.0).powi(8) * (sun.y + 0.2).clamp(0.0, 1.0);
+        base + SUN_GLOW * glow * 0.5
+    }
+}
+
+/// Fog and clear color, linear like the dome's vertex colors so they meet seamlessly
+fn horizon_color(horizon: Vec3) -> Color {
+    Color::linear_rgb(horizon.x, horizon.y, horizon.z)
+}
+
+fn sky_colors(cycle: &DayNightCycle, weather: Option<&Weather>) -> SkyColors {
+    SkyColors::at(cycle).overcast(weather.map_or(0.0, Weather::overcast))
+}
+
+/// Distance where the fog is opaque: just inside the loaded chunks, so their edge never shows
+pub fn fog_distance(config: &WorldConfig) -> f32 {
+    (config.view_radius * CHUNK_WIDTH) as f32 * 0.9
+}
+
+impl super::Environment {
+    pub fn spawn_sky(
+        mut commands: Commands,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+    ) {
+        let mut mesh = Sphere::new(1.0).mesh().uv(32, 16);
+        let vertices = mesh.count_vertices();
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, vec![[1.0f32; 4]; vertices]);
+        commands.spawn((
+            SkyDome::default(),
+            Mesh3d(meshes.add(mesh)),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                unlit: true,
+                fog_enabled: false,
+                cull_mode: None,
+                ..default()
+            })),
+            Transform::default(),
+            NoFrustumCulling,
+            NotShadowCaster,
+            NotShadowReceiver,
+        ));
+    }
+
+    /// Adds fog to new 3D cameras and keeps its color on the horizon
+    pub fn fog(
+        mut commands: Commands,
+        cycle: Res<DayNightCycle>,
+        weather: Option<Res<Weather>>,
+        config: Res<WorldConfig>,
+        new_cameras: Query<Entity, (With<Camera3d>, Without<DistanceFog>)>,
+        mut fogs: Query<&mut DistanceFog>,
+    ) {
+        let end = fog_distance(&config);
+        let color = horizon_color(sky_colors(&cycle, weather.as_deref()).horizon);
+        for camera in &new_cameras {
+            commands.entity(camera).insert(DistanceFog {
+                color,
+                falloff: FogFalloff::Linear { start: end * 0.55, end },
+                ..default()
+            });
+        }
+        for mut fog in &mut fogs {
+            fog.color = color;
+            fog.falloff = FogFalloff::Linear { start: end * 0.55, end };
+        }
+    }
+
+    /// Keeps the dome centered on the camera, past the fog, and recolors it once the sky
+    /// has changed visibly since the last time
+    pub fn sky(
+        cycle: Res<DayNightCycle>,
+        weather: Option<Res<Weather>>,
+        config: Res<WorldConfig>,
+        mut clear_color: ResMut<ClearColor>,
+        mut meshes: ResMut<Assets<Mesh>>,
+        cameras: Query<&GlobalTransform, (With<Camera3d>, Without<SkyDome>)>,
+        mut domes: Query<(&mut Transform, &Mesh3d, &mut SkyDome)>,
+    ) {
+        let colors = sky_colors(&cycle, weather.as_deref());
+        let sun = cycle.sun_direction();
+        clear_color.0 = horizon_color(colors.horizon);
+
+        let camera = cameras.iter().next().map(GlobalTransform::translation).unwrap_or_default();
+        for (mut transform, mesh, mut dome) in &mut domes {
+            transform.translation = camera;
+            transform.scale = Vec3::splat(fog_distance(&config) * 1.5);
+            let recolor = dome.painted.is_none_or(|(last, last_sun)| {
+                colors.differs(&last, RECOLOR_THRESHOLD) || sun.distance(last_sun) > RECOLOR_THRESHOLD
+            });
+            if !recolor {
+                continue;
+            }
+
+            let Some(mesh) = meshes.get_mut(&mesh.0) else {
+                continue;
+            };
+            let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+                continue;
+            };
+            let sky: Vec<[f32; 4]> = positions
+                .iter()
+                .map(|p| colors.sample(Vec3::from(*p).normalize_or_zero(), sun).extend(1.0).to_array())
+                .collect();
+            mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, sky);
+            dome.painted = Some((colors, sun));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn brighter_by_day() {
+        let noon = SkyColors::at(&DayNightCycle { time: 0.5, ..default() });
+        let midnight = SkyColors::at(&DayNightCycle { time: 0.0, ..default() });
+        assert_eq!(noon.zenith, DAY_ZENITH);
+        assert_eq!(midnight.zenith, NIGHT_ZENITH);
+        assert!(noon.horizon.length() > midnight.horizon.length());
+
+        // Looking at the sun is brighter than looking away from it
+        let sun = Vec3::new(0.0, 0.5, 0.8).normalize();
+        assert!(noon.sample(sun, sun).length() > noon.sample(-sun, sun).length());
+    }
+
+    #[test]
+    fn small_changes_keep_the_colors() {
+        let cycle = DayNightCycle { time: 0.3, ..default() };
+        let colors = SkyColors::at(&cycle);
+        let a_moment_later = SkyColors::at(&DayNightCycle { time: 0.3 + 1e-5, ..cycle });
+        assert!(!a_moment_later.differs(&colors, RECOLOR_THRESHOLD));
+        assert!(colors.overcast(0.5).differs(&colors, RECOLOR_THRESHOLD));
+    }
+}
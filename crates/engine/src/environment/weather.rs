@@ -0,0 +1,484 @@
+use bevy::prelude::*;
+
+use crate::terrain::constants::CHUNK_HEIGHT;
+use crate::terrain::ecs::resources::voxel::VoxelRegistry;
+use crate::terrain::generator::{Climate, TerrainManager};
+use crate::terrain::structures::{SurfaceHeights, VoxelAccess, WorldVoxels};
+use crate::terrain::types::Voxel;
+
+use super::Environment;
+
+/// Seconds to fade one weather out, and the next one in
+const FADE_TIME: f32 = 6.0;
+/// Drops start this far above the camera
+const FALL_HEIGHT: f32 = 24.0;
+const RAIN_SPEED: f32 = 14.0;
+const SNOW_SPEED: f32 = 2.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WeatherKind {
+    Clear,
+    Rain,
+    Snow,
+    Storm,
+}
+
+impl WeatherKind {
+    pub const ALL: [WeatherKind; 4] = [WeatherKind::Clear, WeatherKind::Rain, WeatherKind::Snow, WeatherKind::Storm];
+
+    /// How much the sun and sky are dimmed at full intensity
+    pub fn overcast(self) -> f32 {
+        match self {
+            WeatherKind::Clear => 0.0,
+            WeatherKind::Rain | WeatherKind::Snow => 0.5,
+            WeatherKind::Storm => 0.8,
+        }
+    }
+
+    /// Share of `WeatherSettings::max_drops` alive at full intensity
+    pub fn density(self) -> f32 {
+        match self {
+            WeatherKind::Clear => 0.0,
+            WeatherKind::Rain | WeatherKind::Snow => 0.6,
+            WeatherKind::Storm => 1.0,
+        }
+    }
+}
+
+/// Current weather. Changes fade the old kind out before the new one fades in.
+#[derive(Resource, Clone, Debug)]
+pub struct Weather {
+    pub kind: WeatherKind,
+    /// 0..=1, how far the current kind has faded in
+    pub intensity: f32,
+    /// Lightning brightness, 1 on a strike and decaying right after
+    pub flash: f32,
+    /// Seconds between automatic changes on average, 0 keeps the weather until `set`
+    pub change_interval: f32,
+    target: WeatherKind,
+    until_change: f32,
+    until_thunder: f32,
+    rng: u32,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Self::new(WeatherKind::Clear, 0)
+    }
+}
+
+impl Weather {
+    pub fn new(kind: WeatherKind, seed: u32) -> Self {
+        let mut weather = Self {
+            kind,
+            intensity: 1.0,
+            flash: 0.0,
+            change_interval: 0.0,
+            target: kind,
+            until_change: 0.0,
+            until_thunder: 0.0,
+            rng: seed.max(1),
+        };
+        weather.until_thunder = weather.thunder_delay();
+        weather
+    }
+
+    /// The kind being faded in, same as `kind` once a change is done
+    pub fn target(&self) -> WeatherKind {
+        self.target
+    }
+
+    pub fn set(&mut self, kind: WeatherKind) {
+        self.target = kind;
+    }
+
+    /// Effective sky dimming
+    pub fn overcast(&self) -> f32 {
+        self.kind.overcast() * self.intensity
+    }
+
+    /// Uniform in 0..1, xorshift so runs are reproducible from the seed
+    fn random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn thunder_delay(&mut self) -> f32 {
+        4.0 + self.random() * 16.0
+    }
+
+    /// Steps the fades and the automatic changes. Returns true when lightning strikes.
+    pub fn advance(&mut self, seconds: f32) -> bool {
+        self.flash = (self.flash - seconds * 4.0).max(0.0);
+
+        if self.change_interval > 0.0 {
+            self.until_change -= seconds;
+            if self.until_change <= 0.0 {
+                self.until_change = self.change_interval * (0.5 + self.random());
+                let next = WeatherKind::ALL[(self.random() * WeatherKind::ALL.len() as f32) as usize % 4];
+                self.set(next);
+            }
+        }
+
+        let step = seconds / FADE_TIME;
+        if self.kind != self.target {
+            self.intensity -= step;
+            if self.intensity <= 0.0 {
+                self.kind = self.target;
+                self.intensity = 0.0;
+            }
+        } else {
+            self.intensity = (self.intensity + step).min(1.0);
+        }
+
+        if self.kind != WeatherKind::Storm || self.intensity < 0.5 {
+            return false;
+        }
+        self.until_thunder -= seconds;
+        if self.until_thunder > 0.0 {
+            return false;
+        }
+        self.until_thunder = self.thunder_delay();
+        self.flash = 1.0;
+        true
+    }
+}
+
+/// A lightning strike, for sound and effects
+#[derive(Message, Clone, Copy, Debug)]
+pub struct Thunder {
+    pub position: Vec3,
+}
+
+/// How precipitation is simulated around the camera
+#[derive(Resource, Clone, Debug)]
+pub struct WeatherSettings {
+    /// Half side of the square around the camera where drops fall, in blocks
+    pub radius: f32,
+    pub max_drops: usize,
+    /// Columns whose biome temperature is below this count as cold. Rain falls as snow there,
+    /// and snow settles. Worlds without biomes are never cold.
+    pub freezing: f64,
+    /// Block stacked by settling snow, the registry block named "snow" when `None`.
+    /// Without either, snow never settles.
+    pub snow_block: Option<Voxel>,
+    /// Highest snow stack
+    pub snow_layers: i32,
+    /// Snow blocks placed per second at most, every one re-meshes a chunk
+    pub snow_rate: f32,
+}
+
+impl Default for WeatherSettings {
+    fn default() -> Self {
+        Self {
+            radius: 24.0,
+            max_drops: 1500,
+            freezing: -0.3,
+            snow_block: None,
+            snow_layers: 3,
+            snow_rate: 2.0,
+        }
+    }
+}
+
+pub struct Drop {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub snow: bool,
+}
+
+/// Drops falling around the camera, removed when they reach the top-most solid voxel
+#[derive(Resource, Default)]
+pub struct Precipitation {
+    pub drops: Vec<Drop>,
+    spawn_carry: f32,
+    snow_carry: f32,
+}
+
+/// Rain, snow and storms falling onto the terrain. Drops collide with the column heightmaps,
+/// snow piles up on cold columns, and storms send `Thunder` messages.
+#[derive(Clone, Default)]
+pub struct WeatherPlugin {
+    weather: Weather,
+    settings: WeatherSettings,
+}
+
+impl WeatherPlugin {
+    pub fn starting_with(mut self, kind: WeatherKind) -> Self {
+        self.weather = Weather { change_interval: self.weather.change_interval, ..Weather::new(kind, self.weather.rng) };
+        self
+    }
+
+    /// Average seconds between random changes, 0 keeps the weather fixed
+    pub fn with_change_interval(mut self, seconds: f32) -> Self {
+        self.weather.change_interval = seconds;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u32) -> Self {
+        self.weather.rng = seed.max(1);
+        self
+    }
+
+    pub fn with_settings(mut self, settings: WeatherSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+}
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.weather.clone())
+            .insert_resource(self.settings.clone())
+            .init_resource::<Precipitation>()
+            .add_message::<Thunder>()
+            .add_systems(
+                Update,
+                (Environment::weather, Environment::precipitate, Environment::draw_precipitation).chain(),
+            );
+    }
+}
+
+/// Stacks one snow block on the column whose top-most solid voxel is at `top`.
+/// Returns false when the stack is full or the spot above it is taken.
+pub fn settle_snow(voxels: &mut impl VoxelAccess, top: IVec3, snow: Voxel, max_layers: i32) -> bool {
+    let mut above = top + IVec3::Y;
+    while voxels.get_voxel(above) == Some(snow) {
+        above.y += 1;
+    }
+    let mut layers = 0;
+    while layers < above.y && voxels.get_voxel(above - IVec3::Y * (layers + 1)) == Some(snow) {
+        layers += 1;
+    }
+    if layers >= max_layers || above.y >= CHUNK_HEIGHT || voxels.get_voxel(above) != Some(Voxel::AIR) {
+        return false;
+    }
+    voxels.set_voxel(above, snow)
+}
+
+/// Whether a column with this climate is cold enough for snow
+pub fn is_cold(climate: Option<Climate>, freezing: f64) -> bool {
+    climate.is_some_and(|climate| climate.temperature < freezing)
+}
+
+fn snow_block(settings: &WeatherSettings, registry: &VoxelRegistry) -> Option<Voxel> {
+    settings.snow_block.or_else(|| {
+        registry
+            .definitions
+            .iter()
+            .find(|(_, definition)| definition.name.eq_ignore_ascii_case("snow"))
+            .map(|(voxel, _)| *voxel)
+    })
+}
+
+impl Environment {
+    pub fn weather(
+        time: Res<Time>,
+        mut weather: ResMut<Weather>,
+        mut thunder: MessageWriter<Thunder>,
+        cameras: Query<&GlobalTransform, With<Camera3d>>,
+    ) {
+        if !weather.advance(time.delta_secs()) {
+            return;
+        }
+        let camera = cameras.iter().next().map(GlobalTransform::translation).unwrap_or_default();
+        let angle = weather.random() * std::f32::consts::TAU;
+        let distance = 40.0 + weather.random() * 160.0;
+        let position = camera + Vec3::new(angle.cos() * distance, 60.0, angle.sin() * distance);
+        thunder.write(Thunder { position });
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn precipitate(
+        time: Res<Time>,
+        mut weather: ResMut<Weather>,
+        settings: Res<WeatherSettings>,
+        registry: Res<VoxelRegistry>,
+        manager: Res<TerrainManager>,
+        mut precipitation: ResMut<Precipitation>,
+        heights: SurfaceHeights,
+        mut voxels: WorldVoxels,
+        cameras: Query<&GlobalTransform, With<Camera3d>>,
+    ) {
+        let Some(camera) = cameras.iter().next().map(GlobalTransform::translation) else {
+            return;
+        };
+        let dt = time.delta_secs();
+        let precipitation = &mut *precipitation;
+        let cold = |x: i32, z: i32| is_cold(manager.climate(x, z), settings.freezing);
+
+        // Spawn at the rate that keeps the target count alive over a full fall
+        let target = (settings.max_drops as f32 * weather.kind.density() * weather.intensity) as usize;
+        let rate = target as f32 * RAIN_SPEED / FALL_HEIGHT;
+        precipitation.spawn_carry += rate * dt;
+        while precipitation.spawn_carry >= 1.0 {
+            precipitation.spawn_carry -= 1.0;
+            if precipitation.drops.len() >= target {
+                continue;
+            }
+            let x = camera.x + (weather.random() * 2.0 - 1.0) * settings.radius;
+            let z = camera.z + (weather.random() * 2.0 - 1.0) * settings.radius;
+            let snow = weather.kind == WeatherKind::Snow || cold(x.floor() as i32, z.floor() as i32);
+            // Snow falls slower, so fewer of its drops are spawned for the same count
+            if snow && weather.random() > SNOW_SPEED / RAIN_SPEED * 2.0 {
+                continue;
+            }
+            let wind = if weather.kind == WeatherKind::Storm { 4.0 } else { 1.0 };
+            let velocity = if snow {
+                Vec3::new(wind * 0.3, -SNOW_SPEED, 0.0)
+            } else {
+                Vec3::new(wind, -RAIN_SPEED, wind * 0.3)
+            };
+            let y = camera.y + FALL_HEIGHT * (0.5 + weather.random() * 0.5);
+            precipitation.drops.push(Drop { position: Vec3::new(x, y, z), velocity, snow });
+        }
+
+        let snow = snow_block(&settings, &registry);
+        precipitation.snow_carry = (precipitation.snow_carry + settings.snow_rate * dt).min(settings.snow_rate.max(1.0));
+        let elapsed = time.elapsed_secs();
+        let mut landed = Vec::new();
+        precipitation.drops.retain_mut(|drop| {
+            drop.position += drop.velocity * dt;
+            if drop.snow {
+                drop.position.x += (elapsed * 2.0 + drop.position.y).sin() * 0.5 * dt;
+            }
+            let column = drop.position.floor().as_ivec3();
+            let offset = (drop.position - camera).xz().abs().max_element();
+            match heights.top_solid(column.x, column.z) {
+                Some(top) if drop.position.y <= (top + 1) as f32 => {
+                    if drop.snow && cold(column.x, column.z) {
+                        landed.push(IVec3::new(column.x, top, column.z));
+                    }
+                    false
+                }
+                _ => drop.position.y > camera.y - FALL_HEIGHT * 2.0 && offset < settings.radius * 1.5,
+            }
+        });
+
+        let Some(snow) = snow else {
+            return;
+        };
+        for top in landed {
+            if precipitation.snow_carry < 1.0 {
+                break;
+            }
+            if weather.random() < 0.05 && settle_snow(&mut voxels, top, snow, settings.snow_layers) {
+                precipitation.snow_carry -= 1.0;
+            }
+        }
+    }
+
+    /// Streaks for rain, small crosses for snow
+    pub fn draw_precipitation(precipitation: Res<Precipitation>, mut gizmos: Gizmos) {
+        let rain = Color::srgba(0.6, 0.7, 0.9, 0.5);
+        let snow = Color::srgba(1.0, 1.0, 1.0, 0.9);
+        for drop in &precipitation.drops {
+            if drop.snow {
+                gizmos.cross(Isometry3d::from_translation(drop.position), 0.06, snow);
+            } else {
+                gizmos.line(drop.position, drop.position - drop.velocity * 0.04, rain);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::ecs::components::chunk::{ChunkData, ChunkHeightmap};
+    use crate::terrain::meshing::binary_greedy::SolidTable;
+    use crate::terrain::structures::test_utils::stone_ground;
+
+    const SNOW: Voxel = Voxel(7);
+
+    #[test]
+    fn fades_between_kinds() {
+        let mut weather = Weather::new(WeatherKind::Clear, 1);
+        weather.set(WeatherKind::Rain);
+        weather.advance(FADE_TIME * 0.5);
+        assert_eq!(weather.kind, WeatherKind::Clear);
+        assert!(weather.intensity < 1.0);
+
+        weather.advance(FADE_TIME);
+        assert_eq!(weather.kind, WeatherKind::Rain);
+        assert_eq!(weather.intensity, 0.0);
+
+        weather.advance(FADE_TIME);
+        assert_eq!(weather.intensity, 1.0);
+        assert_eq!(weather.overcast(), WeatherKind::Rain.overcast());
+    }
+
+    #[test]
+    fn thunder_only_in_storms() {
+        let mut weather = Weather::new(WeatherKind::Rain, 3);
+        assert!((0..1000).all(|_| !weather.advance(0.1)));
+
+        let mut weather = Weather::new(WeatherKind::Storm, 3);
+        let strikes = (0..1000).filter(|_| weather.advance(0.1)).count();
+        // 100 seconds, one strike every 4 to 20
+        assert!((5..=25).contains(&strikes), "{strikes} strikes");
+    }
+
+    #[test]
+    fn changes_on_its_own() {
+        let mut weather = Weather::new(WeatherKind::Clear, 9);
+        weather.change_interval = 10.0;
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..10_000 {
+            weather.advance(0.1);
+            seen.insert(weather.kind);
+        }
+        assert_eq!(seen.len(), WeatherKind::ALL.len());
+    }
+
+    #[test]
+    fn column_heights() {
+        let mut chunk = ChunkData::new();
+        chunk.fill_layer_below(40, Voxel::STONE);
+        chunk.set(3, 90, 5, Voxel::STONE);
+        // Unregistered, so not solid
+        chunk.set(3, 100, 5, Voxel(200));
+        chunk.set(0, 0, 0, Voxel::AIR);
+        for y in 0..40 {
+            chunk.set(8, y, 8, Voxel::AIR);
+        }
+
+        let solid = SolidTable::new(&VoxelRegistry::default());
+        let heightmap = ChunkHeightmap::new(&chunk, &solid);
+        for (x, z, expected) in [(1, 1, Some(39)), (3, 5, Some(90)), (0, 0, Some(39)), (8, 8, None)] {
+            assert_eq!(chunk.top_solid(x, z, &solid), expected);
+            assert_eq!(heightmap.top_solid(x, z), expected);
+        }
+        assert_eq!(heightmap.top_solid(-1, 0), None);
+    }
+
+    #[test]
+    fn snow_stacks_up_to_the_limit() {
+        let mut world = stone_ground([IVec2::ZERO], 70);
+        let top = IVec3::new(4, 69, 4);
+
+        for layer in 0..3 {
+            assert!(settle_snow(&mut world, top, SNOW, 3));
+            assert_eq!(world.get_voxel(top + IVec3::Y * (layer + 1)), Some(SNOW));
+        }
+        assert!(!settle_snow(&mut world, top, SNOW, 3));
+        assert_eq!(world.get_voxel(top + IVec3::Y * 4), Some(Voxel::AIR));
+
+        // Something non-solid on top keeps the snow off
+        world.set_voxel(IVec3::new(5, 70, 4), Voxel(200));
+        assert!(!settle_snow(&mut world, IVec3::new(5, 69, 4), SNOW, 3));
+        // Unloaded columns are left alone
+        assert!(!settle_snow(&mut world, IVec3::new(40, 69, 4), SNOW, 3));
+    }
+
+    #[test]
+    fn cold_below_freezing() {
+        let climate = |temperature| Some(Climate { temperature, humidity: 0.0 });
+        assert!(is_cold(climate(-0.5), -0.3));
+        assert!(!is_cold(climate(0.2), -0.3));
+        // No biomes, no climate
+        assert!(!is_cold(None, -0.3));
+    }
+}
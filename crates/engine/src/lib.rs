@@ -1,7 +1,33 @@
+pub mod cli;
+
 pub mod debug {
-    mod chunk_gen;
+    mod overlay;
+    mod tuning;
+
+    pub use overlay::*;
+    pub use tuning::*;
+}
+
+pub mod environment {
+    mod day_night;
+    mod sky;
+    mod weather;
+
+    pub use day_night::*;
+    pub use sky::*;
+    pub use weather::*;
+}
 
-    pub use chunk_gen::*;
+pub mod net {
+    mod client;
+    mod protocol;
+    mod server;
+    mod transport;
+
+    pub use client::*;
+    pub use protocol::*;
+    pub use server::*;
+    pub use transport::*;
 }
 
 pub mod terrain {
@@ -31,17 +57,57 @@ pub mod terrain {
         pub use terrain::*;
     }
     pub mod generator {
+        mod biomes;
+        #[allow(clippy::module_inception)]
         mod generator;
         mod heightmap;
         mod noise;
 
-        pub use generator::TerrainManager;
+        pub use biomes::{blend_corners, BiomeMap, Climate};
+        pub use generator::{ChunkGenerator, ChunkLoading, GeneratorKind, TerrainGenerator, TerrainManager};
+        pub use heightmap::{generate_height, generate_height_scaled};
+        pub use noise::{GeneratorParams, TerrainNoise};
     }
+    pub mod collision;
+    pub mod config;
     pub mod constants;
+    pub mod export;
+    pub mod history;
+    pub mod hot_reload;
+    pub mod lighting;
+    pub mod stats;
+    pub mod storage {
+        mod archive;
+        mod codec;
+        mod region;
+        mod world;
+
+        pub use archive::*;
+        pub use codec::*;
+        pub use region::*;
+        pub use world::*;
+    }
     pub mod meshing {
-        pub(crate) mod bevy_meshing;
-        pub(crate) mod greedy;
-        pub(crate) mod mesh_data;
+        pub mod bevy_meshing;
+        pub mod binary_greedy;
+        pub mod greedy;
+        pub mod mesh_data;
+        pub mod mesher;
+        pub mod packed;
+        pub mod surface_nets;
+    }
+    pub mod structures {
+        mod access;
+        mod placement;
+        #[cfg(any(test, feature = "test-utils"))]
+        pub mod test_utils;
+
+        pub use access::*;
+        pub use placement::*;
+    }
+    pub mod render {
+        mod material;
+        pub use material::*;
     }
 }
 
@@ -0,0 +1,185 @@
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+
+use crate::terrain::{
+    ecs::{
+        components::chunk::{ChunkCoords, ChunkData, ChunkDirty, ChunkStage},
+        resources::chunk::ChunkMap,
+    },
+    tasks::chunk_bundle,
+    types::Voxel,
+};
+
+use super::{
+    protocol::{ClientId, ClientMessage, ServerMessage},
+    transport::ClientTransport,
+};
+
+/// Connection to a `NetServer`. Received chunks enter the local pipeline as
+/// decorated, so they are lit and meshed here.
+#[derive(Resource)]
+pub struct NetClient {
+    transport: Box<dyn ClientTransport>,
+    /// Assigned by the server's welcome
+    pub id: Option<ClientId>,
+    pub seed: Option<i32>,
+    connected: bool,
+    /// Revision of every chunk held, deltas only apply on top of the matching one
+    revisions: HashMap<IVec2, u32>,
+    /// Chunks asked for again after a delta did not match
+    pub resyncs: usize,
+    /// Edits the server refused, with its reason
+    pub rejected_edits: Vec<(IVec3, String)>,
+}
+
+impl NetClient {
+    pub fn new(transport: impl ClientTransport, view_distance: i32) -> Self {
+        let mut client = Self {
+            transport: Box::new(transport),
+            id: None,
+            seed: None,
+            connected: true,
+            revisions: HashMap::new(),
+            resyncs: 0,
+            rejected_edits: Vec::new(),
+        };
+        client.send(&ClientMessage::Hello { view_distance });
+        client
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Revision of a chunk held by the client
+    pub fn revision(&self, coords: IVec2) -> Option<u32> {
+        self.revisions.get(&coords).copied()
+    }
+
+    fn send(&mut self, message: &ClientMessage) {
+        self.transport.send(&message.encode());
+    }
+
+    /// Moves the streamed area
+    pub fn set_position(&mut self, chunk: IVec2) {
+        self.send(&ClientMessage::SetPosition(chunk));
+    }
+
+    /// Asks the server to change a block, the change comes back as a broadcast
+    pub fn request_edit(&mut self, position: IVec3, voxel: Voxel) {
+        self.send(&ClientMessage::EditBlock { position, voxel });
+    }
+}
+
+pub struct ClientTask;
+
+impl ClientTask {
+    /// Applies server messages to the local chunks
+    pub fn receive(
+        mut commands: Commands,
+        mut client: ResMut<NetClient>,
+        mut chunk_map: ResMut<ChunkMap>,
+        mut chunks: Query<&mut ChunkData>,
+    ) {
+        if !client.connected {
+            return;
+        }
+        let messages = match client.transport.poll() {
+            Ok(messages) => messages,
+            Err(e) => {
+                warn!("lost connection to the server: {e}");
+                client.connected = false;
+                return;
+            }
+        };
+
+        // Chunks of this batch, edits may follow them before the commands are applied
+        let mut received: HashMap<IVec2, ChunkData> = HashMap::new();
+        for bytes in messages {
+            let message = match ServerMessage::decode(&bytes) {
+                Ok(message) => message,
+                Err(e) => {
+                    warn!("server sent an invalid message: {e}");
+                    continue;
+                }
+            };
+            match message {
+                ServerMessage::Welcome { client: id, seed } => {
+                    client.id = Some(id);
+                    client.seed = Some(seed);
+                }
+                ServerMessage::Chunk { coords, revision, data } => {
+                    client.revisions.insert(coords, revision);
+                    received.insert(coords, data);
+                }
+                ServerMessage::UnloadChunk(coords) => {
+                    client.revisions.remove(&coords);
+                    received.remove(&coords);
+                    if let Some(entity) = chunk_map.entities.remove(&coords) {
+                        commands.entity(entity).despawn();
+                    }
+                }
+                ServerMessage::ChunkDelta { coords, base_revision, changes } => {
+                    match client.revisions.get(&coords) {
+                        Some(revision) if *revision == base_revision => {}
+                        // A delta was missed, the chunk is stale until the full resend
+                        Some(_) => {
+                            warn!("chunk {coords} is out of sync, requesting it again");
+                            client.revisions.remove(&coords);
+                            client.resyncs += 1;
+                            client.send(&ClientMessage::RequestChunk(coords));
+                            continue;
+                        }
+                        None => continue,
+                    }
+                    client.revisions.insert(coords, base_revision + 1);
+
+                    let apply = |data: &mut ChunkData| {
+                        for change in &changes {
+                            data.voxels[change.index as usize] = change.voxel;
+                        }
+                    };
+                    if let Some(data) = received.get_mut(&coords) {
+                        apply(data);
+                        continue;
+                    }
+                    let Some(entity) = chunk_map.get(coords) else {
+                        continue;
+                    };
+                    if let Ok(mut data) = chunks.get_mut(entity) {
+                        apply(&mut data);
+                        commands.entity(entity).insert(ChunkDirty);
+                    }
+                }
+                ServerMessage::EditRejected { position, reason } => {
+                    warn!("edit at {position} rejected: {reason}");
+                    client.rejected_edits.push((position, reason));
+                }
+            }
+        }
+
+        for (coords, data) in received {
+            match chunk_map.get(coords) {
+                Some(entity) => {
+                    commands.entity(entity).insert((data, ChunkDirty));
+                }
+                None => {
+                    let entity = commands
+                        .spawn((chunk_bundle(ChunkCoords(coords), ChunkStage::Decorated), data))
+                        .id();
+                    chunk_map.entities.insert(coords, entity);
+                }
+            }
+        }
+    }
+}
+
+/// Runs the client systems while a `NetClient` resource exists.
+/// The local world should use `ChunkLoading::Manual`, chunks come from the server.
+pub struct NetClientPlugin;
+
+impl Plugin for NetClientPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, ClientTask::receive.run_if(resource_exists::<NetClient>));
+    }
+}
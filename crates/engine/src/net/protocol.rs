@@ -0,0 +1,426 @@
+use std::fmt;
+
+use bevy::math::{IVec2, IVec3};
+
+use crate::terrain::{
+    constants::{CHUNK_DEPTH, CHUNK_HEIGHT, CHUNK_WIDTH},
+    ecs::components::chunk::ChunkData,
+    storage::{decode_chunk, encode_chunk, read_varint, write_varint, CodecError},
+    types::Voxel,
+};
+
+/// First byte of every message, peers on another version can't be decoded
+pub const PROTOCOL_VERSION: u8 = 2;
+
+/// Voxels per chunk, every local index is below it
+pub const CHUNK_VOLUME: usize = (CHUNK_WIDTH * CHUNK_HEIGHT * CHUNK_DEPTH) as usize;
+// `BlockChange::index` is a u16, bigger chunks need a wider index
+const _: () = assert!(CHUNK_VOLUME <= u16::MAX as usize + 1);
+
+pub type ClientId = u32;
+
+/// One voxel of a chunk delta, `index` is the `ChunkData` storage index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockChange {
+    pub index: u16,
+    pub voxel: Voxel,
+}
+
+impl BlockChange {
+    pub fn new(local: IVec3, voxel: Voxel) -> Self {
+        Self { index: ChunkData::index(local.x, local.y, local.z) as u16, voxel }
+    }
+
+    /// Position inside the chunk
+    pub fn local(&self) -> IVec3 {
+        ChunkData::position(self.index as usize)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientMessage {
+    Hello { view_distance: i32 },
+    /// Chunk the player is in, chunks are streamed around it
+    SetPosition(IVec2),
+    EditBlock { position: IVec3, voxel: Voxel },
+    /// Asks for a full resend, after a delta that did not match the local revision
+    RequestChunk(IVec2),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerMessage {
+    Welcome { client: ClientId, seed: i32 },
+    /// Full chunk at a revision, compressed with the chunk codec
+    Chunk { coords: IVec2, revision: u32, data: ChunkData },
+    UnloadChunk(IVec2),
+    /// Changes that take a chunk from `base_revision` to `base_revision + 1`,
+    /// sorted by index without duplicates
+    ChunkDelta { coords: IVec2, base_revision: u32, changes: Vec<BlockChange> },
+    EditRejected { position: IVec3, reason: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolError {
+    Truncated,
+    UnsupportedVersion(u8),
+    UnknownMessage(u8),
+    /// Bytes left over after a complete message
+    TrailingBytes(usize),
+    InvalidString,
+    /// Delta indices out of range or not increasing
+    InvalidDelta,
+    Chunk(CodecError),
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::Truncated => write!(f, "message is truncated"),
+            ProtocolError::UnsupportedVersion(v) => write!(f, "unsupported protocol version {v}"),
+            ProtocolError::UnknownMessage(tag) => write!(f, "unknown message type {tag}"),
+            ProtocolError::TrailingBytes(n) => write!(f, "{n} unexpected bytes after the message"),
+            ProtocolError::InvalidString => write!(f, "string is not valid UTF-8"),
+            ProtocolError::InvalidDelta => write!(f, "chunk delta indices are out of order"),
+            ProtocolError::Chunk(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+impl From<CodecError> for ProtocolError {
+    fn from(e: CodecError) -> Self {
+        ProtocolError::Chunk(e)
+    }
+}
+
+/// Little-endian message writer
+#[derive(Default)]
+pub(crate) struct ByteWriter(pub(crate) Vec<u8>);
+
+impl ByteWriter {
+    pub(crate) fn u8(&mut self, v: u8) -> &mut Self {
+        self.0.push(v);
+        self
+    }
+
+    pub(crate) fn u32(&mut self, v: u32) -> &mut Self {
+        self.0.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    pub(crate) fn i32(&mut self, v: i32) -> &mut Self {
+        self.0.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    pub(crate) fn ivec2(&mut self, v: IVec2) -> &mut Self {
+        self.i32(v.x).i32(v.y)
+    }
+
+    pub(crate) fn ivec3(&mut self, v: IVec3) -> &mut Self {
+        self.i32(v.x).i32(v.y).i32(v.z)
+    }
+
+    pub(crate) fn varint(&mut self, v: u32) -> &mut Self {
+        write_varint(&mut self.0, v);
+        self
+    }
+
+    /// Length-prefixed bytes
+    pub(crate) fn bytes(&mut self, v: &[u8]) -> &mut Self {
+        self.u32(v.len() as u32);
+        self.0.extend_from_slice(v);
+        self
+    }
+}
+
+/// Bounds-checked reader over one message
+pub(crate) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ProtocolError> {
+        let end = self.pos.checked_add(n).filter(|end| *end <= self.bytes.len());
+        let slice = &self.bytes[self.pos..end.ok_or(ProtocolError::Truncated)?];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, ProtocolError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, ProtocolError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn i32(&mut self) -> Result<i32, ProtocolError> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn ivec2(&mut self) -> Result<IVec2, ProtocolError> {
+        Ok(IVec2::new(self.i32()?, self.i32()?))
+    }
+
+    pub(crate) fn ivec3(&mut self) -> Result<IVec3, ProtocolError> {
+        Ok(IVec3::new(self.i32()?, self.i32()?, self.i32()?))
+    }
+
+    pub(crate) fn varint(&mut self) -> Result<u32, ProtocolError> {
+        read_varint(self.bytes, &mut self.pos).map_err(|_| ProtocolError::Truncated)
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    pub(crate) fn bytes(&mut self) -> Result<&'a [u8], ProtocolError> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    pub(crate) fn string(&mut self) -> Result<String, ProtocolError> {
+        let bytes = self.bytes()?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| ProtocolError::InvalidString)
+    }
+
+    pub(crate) fn finish(self) -> Result<(), ProtocolError> {
+        match self.remaining() {
+            0 => Ok(()),
+            n => Err(ProtocolError::TrailingBytes(n)),
+        }
+    }
+}
+
+/// Version byte, then the message
+fn writer() -> ByteWriter {
+    let mut w = ByteWriter::default();
+    w.u8(PROTOCOL_VERSION);
+    w
+}
+
+fn reader(bytes: &[u8]) -> Result<ByteReader<'_>, ProtocolError> {
+    let mut r = ByteReader::new(bytes);
+    match r.u8()? {
+        PROTOCOL_VERSION => Ok(r),
+        version => Err(ProtocolError::UnsupportedVersion(version)),
+    }
+}
+
+/// Count, then `(index gap varint, voxel id)` pairs
+fn write_changes(w: &mut ByteWriter, changes: &[BlockChange]) {
+    w.varint(changes.len() as u32);
+    let mut previous = 0;
+    for change in changes {
+        debug_assert!(change.index >= previous, "changes must be sorted");
+        w.varint((change.index - previous) as u32).u8(change.voxel.id());
+        previous = change.index;
+    }
+}
+
+fn read_changes(r: &mut ByteReader) -> Result<Vec<BlockChange>, ProtocolError> {
+    let count = r.varint()? as usize;
+    // Every change takes at least two bytes, a bogus count can't allocate much
+    let mut changes = Vec::with_capacity(count.min(r.remaining() / 2));
+    let mut index = 0usize;
+    for i in 0..count {
+        let gap = r.varint()? as usize;
+        if i > 0 && gap == 0 {
+            return Err(ProtocolError::InvalidDelta);
+        }
+        index += gap;
+        if index >= CHUNK_VOLUME {
+            return Err(ProtocolError::InvalidDelta);
+        }
+        changes.push(BlockChange { index: index as u16, voxel: Voxel(r.u8()?) });
+    }
+    Ok(changes)
+}
+
+impl ClientMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut w = writer();
+        match self {
+            ClientMessage::Hello { view_distance } => w.u8(0).i32(*view_distance),
+            ClientMessage::SetPosition(coords) => w.u8(1).ivec2(*coords),
+            ClientMessage::EditBlock { position, voxel } => w.u8(2).ivec3(*position).u8(voxel.id()),
+            ClientMessage::RequestChunk(coords) => w.u8(3).ivec2(*coords),
+        };
+        w.0
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, ProtocolError> {
+        let mut r = reader(bytes)?;
+        let message = match r.u8()? {
+            0 => ClientMessage::Hello { view_distance: r.i32()? },
+            1 => ClientMessage::SetPosition(r.ivec2()?),
+            2 => ClientMessage::EditBlock { position: r.ivec3()?, voxel: Voxel(r.u8()?) },
+            3 => ClientMessage::RequestChunk(r.ivec2()?),
+            tag => return Err(ProtocolError::UnknownMessage(tag)),
+        };
+        r.finish()?;
+        Ok(message)
+    }
+}
+
+impl ServerMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut w = writer();
+        match self {
+            ServerMessage::Welcome { client, seed } => w.u8(0).u32(*client).i32(*seed),
+            ServerMessage::Chunk { coords, revision, data } => {
+                w.u8(1).ivec2(*coords).u32(*revision).bytes(&encode_chunk(data))
+            }
+            ServerMessage::UnloadChunk(coords) => w.u8(2).ivec2(*coords),
+            ServerMessage::ChunkDelta { coords, base_revision, changes } => {
+                w.u8(3).ivec2(*coords).u32(*base_revision);
+                write_changes(&mut w, changes);
+                &mut w
+            }
+            ServerMessage::EditRejected { position, reason } => w.u8(4).ivec3(*position).bytes(reason.as_bytes()),
+        };
+        w.0
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, ProtocolError> {
+        let mut r = reader(bytes)?;
+        let message = match r.u8()? {
+            0 => ServerMessage::Welcome { client: r.u32()?, seed: r.i32()? },
+            1 => ServerMessage::Chunk { coords: r.ivec2()?, revision: r.u32()?, data: decode_chunk(r.bytes()?)? },
+            2 => ServerMessage::UnloadChunk(r.ivec2()?),
+            3 => ServerMessage::ChunkDelta { coords: r.ivec2()?, base_revision: r.u32()?, changes: read_changes(&mut r)? },
+            4 => ServerMessage::EditRejected { position: r.ivec3()?, reason: r.string()? },
+            tag => return Err(ProtocolError::UnknownMessage(tag)),
+        };
+        r.finish()?;
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn ivec2() -> impl Strategy<Value = IVec2> {
+        (any::<i32>(), any::<i32>()).prop_map(|(x, z)| IVec2::new(x, z))
+    }
+
+    fn ivec3() -> impl Strategy<Value = IVec3> {
+        (any::<i32>(), any::<i32>(), any::<i32>()).prop_map(|(x, y, z)| IVec3::new(x, y, z))
+    }
+
+    /// Runs of a few voxel ids, like generated terrain
+    fn chunk() -> impl Strategy<Value = ChunkData> {
+        prop::collection::vec((0u8..4, 1usize..4096), 0..16).prop_map(|runs| {
+            let mut data = ChunkData::new();
+            let mut start = 0;
+            for (id, len) in runs {
+                let end = (start + len).min(CHUNK_VOLUME);
+                data.voxels[start..end].fill(Voxel(id));
+                start = end;
+            }
+            data
+        })
+    }
+
+    fn changes() -> impl Strategy<Value = Vec<BlockChange>> {
+        prop::collection::btree_map(0..CHUNK_VOLUME as u16, any::<u8>(), 0..64).prop_map(|changes| {
+            changes.into_iter().map(|(index, id)| BlockChange { index, voxel: Voxel(id) }).collect()
+        })
+    }
+
+    fn client_message() -> impl Strategy<Value = ClientMessage> {
+        prop_oneof![
+            any::<i32>().prop_map(|view_distance| ClientMessage::Hello { view_distance }),
+            ivec2().prop_map(ClientMessage::SetPosition),
+            (ivec3(), any::<u8>()).prop_map(|(position, id)| ClientMessage::EditBlock { position, voxel: Voxel(id) }),
+            ivec2().prop_map(ClientMessage::RequestChunk),
+        ]
+    }
+
+    fn server_message() -> impl Strategy<Value = ServerMessage> {
+        prop_oneof![
+            (any::<u32>(), any::<i32>()).prop_map(|(client, seed)| ServerMessage::Welcome { client, seed }),
+            (ivec2(), any::<u32>(), chunk())
+                .prop_map(|(coords, revision, data)| ServerMessage::Chunk { coords, revision, data }),
+            ivec2().prop_map(ServerMessage::UnloadChunk),
+            (ivec2(), any::<u32>(), changes())
+                .prop_map(|(coords, base_revision, changes)| ServerMessage::ChunkDelta { coords, base_revision, changes }),
+            (ivec3(), ".{0,40}").prop_map(|(position, reason)| ServerMessage::EditRejected { position, reason }),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn client_messages_round_trip(message in client_message()) {
+            prop_assert_eq!(ClientMessage::decode(&message.encode()), Ok(message));
+        }
+
+        #[test]
+        fn server_messages_round_trip(message in server_message()) {
+            prop_assert_eq!(ServerMessage::decode(&message.encode()), Ok(message));
+        }
+
+        #[test]
+        fn random_bytes_never_panic(bytes in prop::collection::vec(any::<u8>(), 0..256)) {
+            let _ = ClientMessage::decode(&bytes);
+            let _ = ServerMessage::decode(&bytes);
+            // Past the version byte, so the message parsers see them too
+            let versioned: Vec<u8> = [PROTOCOL_VERSION].into_iter().chain(bytes).collect();
+            let _ = ClientMessage::decode(&versioned);
+            let _ = ServerMessage::decode(&versioned);
+        }
+
+        #[test]
+        fn truncated_messages_are_errors(message in server_message(), cut in any::<prop::sample::Index>()) {
+            let bytes = message.encode();
+            let len = cut.index(bytes.len());
+            prop_assert!(ServerMessage::decode(&bytes[..len]).is_err());
+        }
+
+        #[test]
+        fn corrupted_messages_never_panic(message in server_message(), at in any::<prop::sample::Index>(), byte in any::<u8>()) {
+            let mut bytes = message.encode();
+            let i = at.index(bytes.len());
+            bytes[i] = byte;
+            let _ = ServerMessage::decode(&bytes);
+        }
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut bytes = ClientMessage::SetPosition(IVec2::ONE).encode();
+        bytes[0] = PROTOCOL_VERSION + 1;
+        assert_eq!(ClientMessage::decode(&bytes), Err(ProtocolError::UnsupportedVersion(PROTOCOL_VERSION + 1)));
+    }
+
+    #[test]
+    fn deltas_are_compact() {
+        let changes: Vec<BlockChange> =
+            (0..8).map(|x| BlockChange::new(IVec3::new(x, 20, 3), Voxel::STONE)).collect();
+        let delta = ServerMessage::ChunkDelta { coords: IVec2::ZERO, base_revision: 4, changes };
+        // Version, tag, coords, revision, count, a 2 byte first index, then two bytes a change
+        assert_eq!(delta.encode().len(), 2 + 8 + 4 + 1 + 1 + 16);
+    }
+
+    #[test]
+    fn rejects_unsorted_deltas() {
+        let mut w = writer();
+        w.u8(3).ivec2(IVec2::ZERO).u32(0).varint(2).varint(5).u8(1).varint(0).u8(1);
+        assert_eq!(ServerMessage::decode(&w.0), Err(ProtocolError::InvalidDelta));
+    }
+
+    #[test]
+    fn change_positions() {
+        let local = IVec3::new(5, 77, 11);
+        assert_eq!(BlockChange::new(local, Voxel::STONE).local(), local);
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::BTreeMap;
+
+use bevy::platform::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+
+use crate::terrain::{
+    config::WorldConfig,
+    constants::CHUNK_HEIGHT,
+    ecs::{
+        components::chunk::{ChunkData, ChunkGenTask, ChunkMeshTask, ChunkStage},
+        resources::{chunk::ChunkMap, voxel::VoxelRegistry},
+    },
+    generator::TerrainManager,
+    structures::{world_to_chunk, VoxelAccess, WorldVoxels},
+    types::Voxel,
+};
+
+use super::{
+    protocol::{BlockChange, ClientId, ClientMessage, ServerMessage},
+    transport::{ServerEvent, ServerTransport},
+};
+
+/// Clients asking for more are clamped to it
+pub const MAX_VIEW_DISTANCE: i32 = 16;
+
+/// Changes to one chunk in a frame above which the whole chunk is resent
+pub const FULL_RESEND_CHANGES: usize = 512;
+
+/// Chunks still being generated or meshed
+type WithTask = Or<(With<ChunkGenTask>, With<ChunkMeshTask>)>;
+
+struct RemoteClient {
+    /// Set by `Hello`, nothing is streamed before
+    view_distance: Option<i32>,
+    center: IVec2,
+    /// Chunks the client holds, edits are only accepted inside them
+    sent: HashSet<IVec2>,
+}
+
+/// Authoritative server: owns the world, streams chunks around each client
+/// and applies the block edits it accepts.
+#[derive(Resource)]
+pub struct NetServer {
+    transport: Box<dyn ServerTransport>,
+    clients: HashMap<ClientId, RemoteClient>,
+    /// Bumped once per frame with edits, chunks never edited are at 0
+    revisions: HashMap<IVec2, u32>,
+    /// Edits of this frame by chunk and local index, the last one wins
+    pending: HashMap<IVec2, BTreeMap<u16, Voxel>>,
+    /// Chunks sent to one client per frame
+    pub chunks_per_frame: usize,
+    /// Above it, edited chunks are resent whole instead of as a delta
+    pub full_resend_changes: usize,
+}
+
+impl NetServer {
+    pub fn new(transport: impl ServerTransport) -> Self {
+        Self {
+            transport: Box::new(transport),
+            clients: HashMap::new(),
+            revisions: HashMap::new(),
+            pending: HashMap::new(),
+            chunks_per_frame: 8,
+            full_resend_changes: FULL_RESEND_CHANGES,
+        }
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn revision(&self, coords: IVec2) -> u32 {
+        self.revisions.get(&coords).copied().unwrap_or(0)
+    }
+
+    /// Whether some client holds the chunk or is about to, with the same margin as unloading
+    fn in_view(&self, coords: IVec2) -> bool {
+        self.clients.values().any(|client| {
+            client
+                .view_distance
+                .is_some_and(|view_distance| (coords - client.center).abs().max_element() <= view_distance + 1)
+        })
+    }
+
+    fn send(&mut self, client: ClientId, message: &ServerMessage) {
+        self.transport.send(client, &message.encode());
+    }
+
+    /// Sends to every client holding the chunk
+    fn broadcast_in(&mut self, coords: IVec2, message: &ServerMessage) {
+        let bytes = message.encode();
+        for (id, client) in &self.clients {
+            if client.sent.contains(&coords) {
+                self.transport.send(*id, &bytes);
+            }
+        }
+    }
+
+    /// Why an edit can't be applied, if it can't
+    fn validate_edit(
+        &self,
+        client: ClientId,
+        position: IVec3,
+        voxel: &Voxel,
+        registry: &VoxelRegistry,
+    ) -> Option<&'static str> {
+        let (coords, _) = world_to_chunk(position);
+        if !(0..CHUNK_HEIGHT).contains(&position.y) {
+            Some("outside the world height")
+        } else if !registry.definitions.contains_key(voxel) {
+            Some("unknown block")
+        } else if !self.clients.get(&client).is_some_and(|c| c.sent.contains(&coords)) {
+            Some("chunk is not loaded for this client")
+        } else {
+            None
+        }
+    }
+}
+
+pub struct ServerTask;
+
+impl ServerTask {
+    /// Handles connections and client messages, accepted edits are applied and broadcast
+    pub fn receive(
+        mut server: ResMut<NetServer>,
+        mut voxels: WorldVoxels,
+        registry: Res<VoxelRegistry>,
+        config: Res<WorldConfig>,
+    ) {
+        for event in server.transport.poll() {
+            let (id, bytes) = match event {
+                ServerEvent::Connected(id) => {
+                    let client = RemoteClient { view_distance: None, center: IVec2::ZERO, sent: HashSet::new() };
+                    server.clients.insert(id, client);
+                    continue;
+                }
+                ServerEvent::Disconnected(id) => {
+                    server.clients.remove(&id);
+                    continue;
+                }
+                ServerEvent::Message(id, bytes) => (id, bytes),
+            };
+
+            let message = match ClientMessage::decode(&bytes) {
+                Ok(message) => message,
+                Err(e) => {
+                    warn!("client {id} sent an invalid message: {e}");
+                    server.transport.disconnect(id);
+                    server.clients.remove(&id);
+                    continue;
+                }
+            };
+            let Some(client) = server.clients.get_mut(&id) else {
+                continue;
+            };
+
+            match message {
+                ClientMessage::Hello { view_distance } => {
+                    client.view_distance = Some(view_distance.clamp(0, MAX_VIEW_DISTANCE));
+                    server.send(id, &ServerMessage::Welcome { client: id, seed: config.seed });
+                }
+                ClientMessage::SetPosition(center) => client.center = center,
+                // Streamed again by the next `stream`
+                ClientMessage::RequestChunk(coords) => {
+                    client.sent.remove(&coords);
+                }
+                ClientMessage::EditBlock { position, voxel } => {
+                    if let Some(reason) = server.validate_edit(id, position, &voxel, &registry) {
+                        let rejected = ServerMessage::EditRejected { position, reason: reason.to_string() };
+                        server.send(id, &rejected);
+                        continue;
+                    }
+                    if voxels.get_voxel(position) != Some(voxel) && voxels.set_voxel(position, voxel) {
+                        let (coords, local) = world_to_chunk(position);
+                        let change = BlockChange::new(local, voxel);
+                        server.pending.entry(coords).or_default().insert(change.index, voxel);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sends decorated chunks within view distance, nearest first, and unloads the ones
+    /// left behind. Missing chunks are requested from the generator.
+    /// The frame's edits go out first, as one delta or snapshot per chunk.
+    pub fn stream(
+        mut server: ResMut<NetServer>,
+        mut manager: ResMut<TerrainManager>,
+        chunk_map: Res<ChunkMap>,
+        chunks: Query<(&ChunkStage, &ChunkData)>,
+    ) {
+        for (coords, changes) in std::mem::take(&mut server.pending) {
+            let revision = server.revisions.entry(coords).or_default();
+            let base_revision = *revision;
+            *revision += 1;
+
+            let data = chunk_map.get(coords).and_then(|e| chunks.get(e).ok()).map(|(_, data)| data);
+            let message = match data {
+                Some(data) if changes.len() > server.full_resend_changes => {
+                    ServerMessage::Chunk { coords, revision: base_revision + 1, data: data.clone() }
+                }
+                _ => {
+                    let changes = changes.into_iter().map(|(index, voxel)| BlockChange { index, voxel }).collect();
+                    ServerMessage::ChunkDelta { coords, base_revision, changes }
+                }
+            };
+            server.broadcast_in(coords, &message);
+        }
+
+        let NetServer { transport, clients, revisions, chunks_per_frame, .. } = &mut *server;
+        for (id, client) in clients.iter_mut() {
+            let Some(view_distance) = client.view_distance else {
+                continue;
+            };
+            let center = client.center;
+
+            let outside: Vec<IVec2> = client
+                .sent
+                .iter()
+                .filter(|c| (**c - center).abs().max_element() > view_distance + 1)
+                .copied()
+                .collect();
+            for coords in outside {
+                client.sent.remove(&coords);
+                transport.send(*id, &ServerMessage::UnloadChunk(coords).encode());
+            }
+
+            let mut wanted: Vec<IVec2> = (-view_distance..=view_distance)
+                .flat_map(|z| (-view_distance..=view_distance).map(move |x| center + IVec2::new(x, z)))
+                .filter(|c| !client.sent.contains(c))
+                .collect();
+            wanted.sort_by_key(|c| (*c - center).length_squared());
+
+            let mut budget = *chunks_per_frame;
+            for coords in wanted {
+                let ready = chunk_map
+                    .get(coords)
+                    .and_then(|e| chunks.get(e).ok())
+                    .filter(|(stage, _)| **stage >= ChunkStage::Decorated);
+                match ready {
+                    Some((_, data)) if budget > 0 => {
+                        let revision = revisions.get(&coords).copied().unwrap_or(0);
+                        let message = ServerMessage::Chunk { coords, revision, data: data.clone() };
+                        transport.send(*id, &message.encode());
+                        client.sent.insert(coords);
+                        budget -= 1;
+                    }
+                    Some(_) => {}
+                    None => manager.request(coords),
+                }
+            }
+        }
+        transport.flush();
+    }
+
+    /// Despawns chunks outside every client's view, so the world does not keep everything
+    /// ever visited. Edited chunks stay, the server has nowhere else to keep their edits.
+    /// Chunks with a task in flight are left for a later frame.
+    pub fn unload(
+        mut commands: Commands,
+        server: Res<NetServer>,
+        mut manager: ResMut<TerrainManager>,
+        mut chunk_map: ResMut<ChunkMap>,
+        busy: Query<(), WithTask>,
+    ) {
+        manager.requested.retain(|coords| server.in_view(*coords));
+        chunk_map.entities.retain(|coords, entity| {
+            if server.in_view(*coords) || server.revisions.contains_key(coords) || busy.contains(*entity) {
+                return true;
+            }
+            manager.spawned_chunks.remove(coords);
+            commands.entity(*entity).despawn();
+            false
+        });
+    }
+}
+
+/// Runs the server systems while a `NetServer` resource exists.
+/// The world should use `ChunkLoading::Manual`, clients decide what gets generated.
+pub struct NetServerPlugin;
+
+impl Plugin for NetServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (ServerTask::receive, ServerTask::stream, ServerTask::unload)
+                .chain()
+                .run_if(resource_exists::<NetServer>),
+        );
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+
+use super::protocol::ClientId;
+
+/// Largest frame a TCP connection accepts, a full chunk is far below it
+pub const MAX_FRAME: usize = 4 * 1024 * 1024;
+
+pub enum ServerEvent {
+    Connected(ClientId),
+    Disconnected(ClientId),
+    Message(ClientId, Vec<u8>),
+}
+
+/// Server side of a message transport. Messages are delivered whole and in order.
+pub trait ServerTransport: Send + Sync + 'static {
+    fn poll(&mut self) -> Vec<ServerEvent>;
+    /// Queues a message, it is sent on the next `flush` or `poll`
+    fn send(&mut self, client: ClientId, message: &[u8]);
+    fn flush(&mut self) {}
+    fn disconnect(&mut self, client: ClientId);
+}
+
+/// Client side of a message transport
+pub trait ClientTransport: Send + Sync + 'static {
+    /// Messages received since the last poll, an error once the connection is gone
+    fn poll(&mut self) -> io::Result<Vec<Vec<u8>>>;
+    /// Queues a message, it is sent on the next `poll`
+    fn send(&mut self, message: &[u8]);
+}
+
+/// A TCP stream carrying `u32` length-prefixed frames, without blocking
+struct FramedStream {
+    stream: TcpStream,
+    incoming: Vec<u8>,
+    outgoing: Vec<u8>,
+}
+
+impl FramedStream {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self { stream, incoming: Vec::new(), outgoing: Vec::new() })
+    }
+
+    fn queue(&mut self, message: &[u8]) {
+        self.outgoing.extend_from_slice(&(message.len() as u32).to_le_bytes());
+        self.outgoing.extend_from_slice(message);
+    }
+
+    /// Writes what the socket takes without blocking
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => drop(self.outgoing.drain(..n)),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes, then reads every complete frame
+    fn pump(&mut self) -> io::Result<Vec<Vec<u8>>> {
+        self.flush()?;
+
+        let mut buf = [0; 16 * 1024];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => self.incoming.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut frames = Vec::new();
+        let mut start = 0;
+        while let Some(header) = self.incoming.get(start..start + 4) {
+            let len = u32::from_le_bytes(header.try_into().unwrap()) as usize;
+            if len > MAX_FRAME {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{len} byte frame")));
+            }
+            let Some(frame) = self.incoming.get(start + 4..start + 4 + len) else {
+                break;
+            };
+            frames.push(frame.to_vec());
+            start += 4 + len;
+        }
+        self.incoming.drain(..start);
+        Ok(frames)
+    }
+}
+
+pub struct TcpServerTransport {
+    listener: TcpListener,
+    clients: HashMap<ClientId, FramedStream>,
+    next_id: ClientId,
+}
+
+impl TcpServerTransport {
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self { listener, clients: HashMap::new(), next_id: 1 })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+}
+
+impl ServerTransport for TcpServerTransport {
+    fn poll(&mut self) -> Vec<ServerEvent> {
+        let mut events = Vec::new();
+        while let Ok((stream, _)) = self.listener.accept() {
+            if let Ok(stream) = FramedStream::new(stream) {
+                let id = self.next_id;
+                self.next_id += 1;
+                self.clients.insert(id, stream);
+                events.push(ServerEvent::Connected(id));
+            }
+        }
+
+        let mut dropped = Vec::new();
+        for (id, stream) in &mut self.clients {
+            match stream.pump() {
+                Ok(frames) => events.extend(frames.into_iter().map(|f| ServerEvent::Message(*id, f))),
+                Err(_) => dropped.push(*id),
+            }
+        }
+        for id in dropped {
+            self.clients.remove(&id);
+            events.push(ServerEvent::Disconnected(id));
+        }
+        events
+    }
+
+    fn send(&mut self, client: ClientId, message: &[u8]) {
+        if let Some(stream) = self.clients.get_mut(&client) {
+            stream.queue(message);
+        }
+    }
+
+    fn flush(&mut self) {
+        // Broken connections are reported by the next poll
+        for stream in self.clients.values_mut() {
+            let _ = stream.flush();
+        }
+    }
+
+    fn disconnect(&mut self, client: ClientId) {
+        self.clients.remove(&client);
+    }
+}
+
+pub struct TcpClientTransport(FramedStream);
+
+impl TcpClientTransport {
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        FramedStream::new(TcpStream::connect(addr)?).map(Self)
+    }
+}
+
+impl ClientTransport for TcpClientTransport {
+    fn poll(&mut self) -> io::Result<Vec<Vec<u8>>> {
+        self.0.pump()
+    }
+
+    fn send(&mut self, message: &[u8]) {
+        self.0.queue(message);
+    }
+}
+
+/// Messages waiting on one side of an in-memory connection
+type Queue = Arc<Mutex<VecDeque<Vec<u8>>>>;
+
+#[derive(Default)]
+struct MemoryHub {
+    /// Connections not yet seen by the server: id, to server, to client
+    pending: Vec<(ClientId, Queue, Queue)>,
+    next_id: ClientId,
+}
+
+/// In-process transport for tests, clients connect with `MemoryServerTransport::connector`
+#[derive(Default)]
+pub struct MemoryServerTransport {
+    hub: Arc<Mutex<MemoryHub>>,
+    clients: HashMap<ClientId, (Queue, Queue)>,
+}
+
+#[derive(Clone)]
+pub struct MemoryConnector(Arc<Mutex<MemoryHub>>);
+
+impl MemoryServerTransport {
+    pub fn connector(&self) -> MemoryConnector {
+        MemoryConnector(self.hub.clone())
+    }
+}
+
+impl MemoryConnector {
+    pub fn connect(&self) -> MemoryClientTransport {
+        let (to_server, to_client) = (Queue::default(), Queue::default());
+        let mut hub = self.0.lock().unwrap();
+        hub.next_id += 1;
+        let id = hub.next_id;
+        hub.pending.push((id, to_server.clone(), to_client.clone()));
+        MemoryClientTransport { to_server, to_client }
+    }
+}
+
+impl ServerTransport for MemoryServerTransport {
+    fn poll(&mut self) -> Vec<ServerEvent> {
+        let mut events = Vec::new();
+        for (id, to_server, to_client) in self.hub.lock().unwrap().pending.drain(..) {
+            self.clients.insert(id, (to_server, to_client));
+            events.push(ServerEvent::Connected(id));
+        }
+        for (id, (to_server, _)) in &self.clients {
+            events.extend(to_server.lock().unwrap().drain(..).map(|m| ServerEvent::Message(*id, m)));
+        }
+        events
+    }
+
+    fn send(&mut self, client: ClientId, message: &[u8]) {
+        if let Some((_, to_client)) = self.clients.get(&client) {
+            to_client.lock().unwrap().push_back(message.to_vec());
+        }
+    }
+
+    fn disconnect(&mut self, client: ClientId) {
+        self.clients.remove(&client);
+    }
+}
+
+pub struct MemoryClientTransport {
+    to_server: Queue,
+    to_client: Queue,
+}
+
+impl ClientTransport for MemoryClientTransport {
+    fn poll(&mut self) -> io::Result<Vec<Vec<u8>>> {
+        Ok(self.to_client.lock().unwrap().drain(..).collect())
+    }
+
+    fn send(&mut self, message: &[u8]) {
+        self.to_server.lock().unwrap().push_back(message.to_vec());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(events: Vec<ServerEvent>) -> Vec<(ClientId, Vec<u8>)> {
+        events
+            .into_iter()
+            .filter_map(|e| match e {
+                ServerEvent::Message(id, m) => Some((id, m)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn memory_transport_delivers_in_order() {
+        let mut server = MemoryServerTransport::default();
+        let mut client = server.connector().connect();
+        client.send(b"one");
+        client.send(b"two");
+
+        let events = server.poll();
+        assert!(matches!(events[0], ServerEvent::Connected(1)));
+        assert_eq!(messages(events), [(1, b"one".to_vec()), (1, b"two".to_vec())]);
+
+        server.send(1, b"back");
+        assert_eq!(client.poll().unwrap(), [b"back".to_vec()]);
+    }
+
+    #[test]
+    fn tcp_frames_survive_partial_reads() {
+        let mut server = TcpServerTransport::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpClientTransport::connect(server.local_addr().unwrap()).unwrap();
+        let big: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+        client.send(&big);
+        client.send(b"");
+
+        let mut received = Vec::new();
+        for _ in 0..1000 {
+            client.poll().unwrap();
+            received.extend(messages(server.poll()));
+            if received.len() == 2 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(received, [(1, big), (1, Vec::new())]);
+    }
+}
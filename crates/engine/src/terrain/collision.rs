@@ -0,0 +1,114 @@
+use bevy::math::{IVec3, Vec3};
+
+use crate::terrain::{
+    ecs::resources::voxel::VoxelRegistry,
+    structures::VoxelAccess,
+    types::Voxel,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    /// The solid voxel that was hit
+    pub position: IVec3,
+    /// Face that was entered, points back towards the ray origin. Zero when the ray starts inside.
+    pub normal: IVec3,
+    pub voxel: Voxel,
+    pub distance: f32,
+}
+
+fn is_solid(access: &impl VoxelAccess, registry: &VoxelRegistry, pos: IVec3) -> Option<Voxel> {
+    access.get_voxel(pos).filter(|voxel| registry.get(voxel).is_solid)
+}
+
+/// First solid voxel along a ray, voxel by voxel (Amanatides & Woo).
+/// Unloaded chunks count as empty.
+pub fn raycast(
+    access: &impl VoxelAccess,
+    registry: &VoxelRegistry,
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+) -> Option<RayHit> {
+    let direction = direction.try_normalize()?;
+    let mut position = origin.floor().as_ivec3();
+    let step = direction.signum().as_ivec3();
+
+    // Ray length to the next boundary on each axis, and between two boundaries
+    let delta = direction.recip().abs();
+    let next_boundary = |axis: usize| {
+        let p = origin[axis];
+        if direction[axis] > 0.0 {
+            (p.floor() + 1.0 - p) * delta[axis]
+        } else if direction[axis] < 0.0 {
+            (p - p.floor()) * delta[axis]
+        } else {
+            f32::INFINITY
+        }
+    };
+    let mut t_max = Vec3::new(next_boundary(0), next_boundary(1), next_boundary(2));
+    let mut normal = IVec3::ZERO;
+    let mut distance = 0.0;
+
+    while distance <= max_distance {
+        if let Some(voxel) = is_solid(access, registry, position) {
+            return Some(RayHit { position, normal, voxel, distance });
+        }
+        let axis = if t_max.x < t_max.y && t_max.x < t_max.z {
+            0
+        } else if t_max.y < t_max.z {
+            1
+        } else {
+            2
+        };
+        distance = t_max[axis];
+        t_max[axis] += delta[axis];
+        position[axis] += step[axis];
+        normal = IVec3::ZERO;
+        normal[axis] = -step[axis];
+    }
+    None
+}
+
+/// Whether a world-space box touches any solid voxel
+pub fn aabb_collides(access: &impl VoxelAccess, registry: &VoxelRegistry, min: Vec3, max: Vec3) -> bool {
+    let (lo, hi) = (min.floor().as_ivec3(), (max.ceil() - Vec3::ONE).as_ivec3());
+    (lo.y..=hi.y).any(|y| {
+        (lo.z..=hi.z).any(|z| (lo.x..=hi.x).any(|x| is_solid(access, registry, IVec3::new(x, y, z)).is_some()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::IVec2;
+
+    use super::*;
+    use crate::terrain::structures::test_utils::stone_ground;
+
+    /// The four chunks around the origin
+    const CHUNKS: [IVec2; 4] = [IVec2::new(-1, -1), IVec2::new(0, -1), IVec2::new(-1, 0), IVec2::new(0, 0)];
+
+    #[test]
+    fn hits_the_ground() {
+        let (world, registry) = (stone_ground(CHUNKS, 10), VoxelRegistry::default());
+        let hit = raycast(&world, &registry, Vec3::new(-3.5, 20.5, 4.5), Vec3::NEG_Y, 64.0).unwrap();
+        assert_eq!((hit.position, hit.normal), (IVec3::new(-4, 9, 4), IVec3::Y));
+        assert!((hit.distance - 10.5).abs() < 1e-4);
+
+        // Diagonal ray across a chunk border
+        let hit = raycast(&world, &registry, Vec3::new(-0.5, 12.5, -0.5), Vec3::new(1.0, -1.0, 1.0), 64.0).unwrap();
+        assert_eq!(hit.position.y, 9);
+        assert_eq!(hit.voxel, Voxel::STONE);
+
+        assert!(raycast(&world, &registry, Vec3::new(0.5, 20.5, 0.5), Vec3::Y, 64.0).is_none());
+        assert!(raycast(&world, &registry, Vec3::new(0.5, 20.5, 0.5), Vec3::NEG_Y, 5.0).is_none());
+        // Off the loaded chunks nothing is solid
+        assert!(raycast(&world, &registry, Vec3::new(40.5, 20.5, 0.5), Vec3::NEG_Y, 64.0).is_none());
+    }
+
+    #[test]
+    fn boxes_collide_with_solid_voxels() {
+        let (world, registry) = (stone_ground(CHUNKS, 10), VoxelRegistry::default());
+        assert!(aabb_collides(&world, &registry, Vec3::new(0.2, 9.5, 0.2), Vec3::new(0.8, 11.0, 0.8)));
+        assert!(!aabb_collides(&world, &registry, Vec3::new(0.2, 10.0, 0.2), Vec3::new(0.8, 11.8, 0.8)));
+    }
+}
@@ -0,0 +1,235 @@
+use std::path::Path;
+use std::str::FromStr;
+use std::thread;
+
+use bevy::ecs::resource::Resource;
+use serde::{Deserialize, Serialize};
+
+use crate::terrain::{
+    constants::{CHUNK_DEPTH, CHUNK_HEIGHT, CHUNK_WIDTH},
+    generator::{GeneratorKind, GeneratorParams},
+};
+
+/// File name of the config inside a world save directory
+pub const WORLD_CONFIG_FILE: &str = "world.ron";
+/// Prefix of the environment variables read by `WorldConfig::apply_env`
+pub const ENV_PREFIX: &str = "VOXEL_";
+
+/// Everything needed to generate the same world again.
+///
+/// Built in code, loaded from a file, overridden from the command line or environment,
+/// and saved next to the regions so a world reopens with the settings it was created with.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldConfig {
+    pub seed: i32,
+    /// Chunks loaded around the center, in chunks
+    pub view_radius: i32,
+    /// Background generation and meshing tasks, half the cores when `None`
+    pub threads: Option<usize>,
+    pub generator: GeneratorKind,
+    /// Biome table (`*.biomes.ron`), relative to the asset folder. All stone when `None`.
+    pub biomes: Option<String>,
+    /// Structure templates (`*.structure.ron`) scattered by decoration, relative to the asset folder
+    pub structures: Vec<String>,
+    pub sea_level: i32,
+    /// Chunk size the world was created with, checked against the engine's constants
+    pub chunk_dims: (i32, i32, i32),
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        Self {
+            seed: 42,
+            view_radius: 64,
+            threads: None,
+            generator: GeneratorKind::default(),
+            biomes: None,
+            structures: Vec::new(),
+            sea_level: 56,
+            chunk_dims: (CHUNK_WIDTH, CHUNK_HEIGHT, CHUNK_DEPTH),
+        }
+    }
+}
+
+fn parse<T: FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value `{value}` for {key}"))
+}
+
+impl WorldConfig {
+    pub fn thread_budget(&self) -> usize {
+        self.threads
+            .unwrap_or_else(|| thread::available_parallelism().map(|n| n.get() / 2).unwrap_or(1))
+            .max(1)
+    }
+
+    /// Sets one field by its option name, e.g. `seed` or `height-scale`
+    pub fn apply_override(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "seed" => self.seed = parse(key, value)?,
+            "view-radius" => self.view_radius = parse(key, value)?,
+            "threads" => self.threads = Some(parse(key, value)?),
+            "sea-level" => self.sea_level = parse(key, value)?,
+            "biomes" => self.biomes = Some(value.to_string()),
+            "generator" => {
+                self.generator = match value {
+                    "noise" => GeneratorKind::Noise(GeneratorParams::default()),
+                    "flat" => GeneratorKind::Flat { height: self.sea_level },
+                    _ => return Err(format!("unknown generator `{value}`, expected `noise` or `flat`")),
+                }
+            }
+            "flat-height" => match &mut self.generator {
+                GeneratorKind::Flat { height } => *height = parse(key, value)?,
+                GeneratorKind::Noise(_) => return Err("flat-height needs generator `flat`".to_string()),
+            },
+            _ => {
+                let GeneratorKind::Noise(params) = &mut self.generator else {
+                    return Err(format!("unknown option `{key}`"));
+                };
+                match key {
+                    "frequency" => params.frequency = parse(key, value)?,
+                    "octaves" => params.octaves = parse(key, value)?,
+                    "persistence" => params.persistence = parse(key, value)?,
+                    "lacunarity" => params.lacunarity = parse(key, value)?,
+                    "strength" => params.strength = parse(key, value)?,
+                    "height-scale" => params.height_scale = parse(key, value)?,
+                    "base-height" => params.base_height = parse(key, value)?,
+                    "height-curve" => params.height_curve = parse(key, value)?,
+                    _ => return Err(format!("unknown option `{key}`")),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// `--key value` or `--key=value` pairs, in order
+    pub fn apply_args(&mut self, args: impl IntoIterator<Item = String>) -> Result<(), String> {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let Some(option) = arg.strip_prefix("--") else {
+                return Err(format!("unexpected argument `{arg}`"));
+            };
+            match option.split_once('=') {
+                Some((key, value)) => self.apply_override(key, value)?,
+                None => {
+                    let value = args.next().ok_or_else(|| format!("missing value for `{arg}`"))?;
+                    self.apply_override(option, &value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// `VOXEL_SEED`, `VOXEL_VIEW_RADIUS`, `VOXEL_HEIGHT_SCALE`, ...
+    pub fn apply_env(&mut self) -> Result<(), String> {
+        // The generator goes first, the other keys may depend on it
+        let mut vars: Vec<(String, String, String)> = std::env::vars()
+            .filter_map(|(name, value)| {
+                let key = name.strip_prefix(ENV_PREFIX)?.to_ascii_lowercase().replace('_', "-");
+                Some((key, name, value))
+            })
+            .collect();
+        vars.sort_by_key(|(key, _, _)| key != "generator");
+        for (key, name, value) in vars {
+            self.apply_override(&key, &value).map_err(|e| format!("{name}: {e}"))?;
+        }
+        Ok(())
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, String> {
+        let config: Self = ron::from_str(text).map_err(|e| format!("invalid world config: {e}"))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn to_ron(&self) -> String {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .expect("world config is always serializable")
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        Self::from_ron(&text)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.to_ron()).map_err(|e| format!("cannot write {}: {e}", path.display()))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let built = (CHUNK_WIDTH, CHUNK_HEIGHT, CHUNK_DEPTH);
+        if self.chunk_dims != built {
+            return Err(format!(
+                "world uses {:?} chunks, the engine was built with {built:?}",
+                self.chunk_dims
+            ));
+        }
+        if self.view_radius < 0 {
+            return Err("view radius can't be negative".to_string());
+        }
+        Ok(())
+    }
+
+    /// Opens the world saved in `dir`, or creates it with this config.
+    ///
+    /// A saved world keeps its seed, generator and sea level; only the runtime
+    /// settings (view radius, threads) are taken from `self`.
+    pub fn open_world(&self, dir: &Path) -> Result<Self, String> {
+        let path = dir.join(WORLD_CONFIG_FILE);
+        if path.exists() {
+            let saved = Self::load(&path)?;
+            return Ok(Self {
+                view_radius: self.view_radius,
+                threads: self.threads,
+                ..saved
+            });
+        }
+        std::fs::create_dir_all(dir).map_err(|e| format!("cannot create {}: {e}", dir.display()))?;
+        self.save(&path)?;
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_and_round_trips() {
+        let mut config = WorldConfig::default();
+        let args = ["--seed", "7", "--view-radius=12", "--height-scale", "30", "--threads", "3"];
+        config.apply_args(args.map(String::from)).unwrap();
+        assert_eq!((config.seed, config.view_radius, config.thread_budget()), (7, 12, 3));
+        let GeneratorKind::Noise(params) = &config.generator else { panic!() };
+        assert_eq!(params.height_scale, 30.0);
+        config.apply_override("height-curve", "1.5").unwrap();
+        config.apply_override("biomes", "worldgen/default.biomes.ron").unwrap();
+
+        assert_eq!(WorldConfig::from_ron(&config.to_ron()).unwrap(), config);
+        // Missing fields take their defaults
+        assert_eq!(WorldConfig::from_ron("(seed: 7)").unwrap().view_radius, 64);
+
+        assert!(config.apply_override("flat-height", "10").is_err());
+        config.apply_override("generator", "flat").unwrap();
+        config.apply_override("flat-height", "10").unwrap();
+        assert_eq!(config.generator, GeneratorKind::Flat { height: 10 });
+        assert!(config.apply_override("bogus", "1").is_err());
+        assert!(WorldConfig::from_ron("(chunk_dims: (32, 256, 32))").is_err());
+    }
+
+    #[test]
+    fn reopens_with_saved_settings() {
+        let dir = std::env::temp_dir().join(format!("world-config-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let created = WorldConfig { seed: 1234, ..Default::default() }.open_world(&dir).unwrap();
+        assert_eq!(created.seed, 1234);
+
+        let reopened = WorldConfig { seed: 1, view_radius: 8, ..Default::default() }
+            .open_world(&dir)
+            .unwrap();
+        assert_eq!((reopened.seed, reopened.view_radius), (1234, 8));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
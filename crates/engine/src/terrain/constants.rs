@@ -2,10 +2,18 @@ pub const CHUNK_WIDTH: i32 = 16;
 pub const CHUNK_HEIGHT: i32 = 128;
 pub const CHUNK_DEPTH: i32 = 16;
 
+/// Chance for a chunk to hold a structure site during decoration
+pub const STRUCTURE_CHANCE: f32 = 0.25;
+/// Layers of a biome's subsurface block under its surface block
+pub const SUBSURFACE_DEPTH: usize = 3;
+
 pub mod noise {
     pub const PERLIN_SCALE: f64 = 0.01;
     pub const FBM_GAIN: f64 = 0.5;
     pub const FBM_WEIGHTED_STRENGTH: f64 = 1.5;
     pub const FBM_OCTAVES: usize = 8;
     pub const FBM_LACUNARITY: f64 = 2.0;
+    pub const HEIGHT_MULTIPLIER: f32 = 20.0;
+    pub const BASE_HEIGHT: f32 = 50.0;
+    pub const HEIGHT_CURVE: f32 = 1.0;
 }
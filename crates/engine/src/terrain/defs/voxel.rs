@@ -1,5 +1,11 @@
+#[derive(Clone)]
 pub struct VoxelDefinition {
-    pub name: &'static str,
-    //pub texture_index: u32, // Index in a TextureArray or Atlas
+    pub name: String,
     pub is_solid: bool,
-}
\ No newline at end of file
+    /// Drawn as smooth terrain by `MesherKind::PerBlock`, as cubes otherwise
+    pub smooth: bool,
+    /// Group of step, break and place sounds, silent when `None`
+    pub sound_group: Option<String>,
+    /// Texture array layer per face (+X, -X, +Y, -Y, +Z, -Z), None for untextured blocks
+    pub texture_layers: Option<[u32; 6]>,
+}
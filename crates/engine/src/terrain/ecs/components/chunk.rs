@@ -1,8 +1,13 @@
 use std::ops::Deref;
+use std::time::Duration;
 
-use bevy::{ecs::component::Component, math::IVec2, tasks::Task};
+use bevy::{ecs::component::Component, math::{IVec2, IVec3}, tasks::Task};
 
-use crate::terrain::{constants::*, meshing::mesh_data::MeshData, types::Voxel};
+use crate::terrain::{
+    constants::*,
+    meshing::{binary_greedy::SolidTable, mesh_data::MeshData, packed::PackedMeshData},
+    types::Voxel,
+};
 
 #[derive(Component)]
 pub struct Chunk;
@@ -18,18 +23,32 @@ impl Deref for ChunkCoords {
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, PartialEq, Eq)]
 pub struct ChunkData {
     // Fixed-size array of voxels, stored in xzy order (x changes fastest, then z, then y)
     pub voxels: Box<[Voxel]>,
 }
 
+impl std::fmt::Debug for ChunkData {
+    // 32k voxels are not worth printing
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let solid = self.voxels.iter().filter(|v| **v != Voxel::AIR).count();
+        f.debug_struct("ChunkData").field("non_air", &solid).finish()
+    }
+}
+
+impl Default for ChunkData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ChunkData {
     pub fn new() -> Self {
         let len = (CHUNK_WIDTH * CHUNK_HEIGHT * CHUNK_DEPTH) as usize;
         Self {
             // Initialize with 0 (Assuming 0 is always Air in your Global Palette)
-            voxels: vec![Voxel::Air; len].into_boxed_slice(),
+            voxels: vec![Voxel::AIR; len].into_boxed_slice(),
         }
     }
 
@@ -42,6 +61,13 @@ impl ChunkData {
         (x + z * CHUNK_WIDTH + y * CHUNK_WIDTH * CHUNK_DEPTH) as usize
     }
 
+    /// Inverse of `index`
+    #[inline]
+    pub fn position(index: usize) -> IVec3 {
+        let i = index as i32;
+        IVec3::new(i % CHUNK_WIDTH, i / (CHUNK_WIDTH * CHUNK_DEPTH), (i / CHUNK_WIDTH) % CHUNK_DEPTH)
+    }
+
     #[inline]
     pub fn in_bounds(x: i32, y: i32, z: i32) -> bool {
         (0..CHUNK_WIDTH).contains(&x)
@@ -53,7 +79,7 @@ impl ChunkData {
     #[inline]
     pub fn get(&self, x: i32, y: i32, z: i32) -> Voxel {
         if !Self::in_bounds(x, y, z) {
-            return Voxel::Air; 
+            return Voxel::AIR; 
         }
         self.voxels[Self::index(x, y, z)]
     }
@@ -75,7 +101,7 @@ impl ChunkData {
     /// Common shorthand to fill with Air, since it's the most common "reset" state
     #[inline]
     pub fn clear_air(&mut self) {
-        self.fill(Voxel::Air);
+        self.fill(Voxel::AIR);
     }
 
     pub fn fill_layer_below(&mut self, height: i32, pallete: Voxel) {
@@ -84,9 +110,142 @@ impl ChunkData {
         let split_point = h * layer_size;
 
         self.voxels[..split_point].fill(pallete);
-        self.voxels[split_point..].fill(Voxel::Air);
+        self.voxels[split_point..].fill(Voxel::AIR);
+    }
+
+    /// Height of the top-most solid voxel in the column, `None` for an empty or out of bounds column
+    #[inline]
+    pub fn top_solid(&self, x: i32, z: i32, solid: &SolidTable) -> Option<i32> {
+        if !Self::in_bounds(x, 0, z) {
+            return None;
+        }
+        let column = Self::index(x, 0, z);
+        let layer = (CHUNK_WIDTH * CHUNK_DEPTH) as usize;
+        (0..CHUNK_HEIGHT)
+            .rev()
+            .find(|&y| solid.is_solid(self.voxels[column + y as usize * layer].id()))
     }
 }
 
+/// Top-most solid voxel per column, built next to the sky light so edits refresh it.
+/// Stored as height + 1, 0 for an empty column.
+#[derive(Component, Clone)]
+pub struct ChunkHeightmap {
+    pub columns: [u8; (CHUNK_WIDTH * CHUNK_DEPTH) as usize],
+}
+
+impl ChunkHeightmap {
+    pub fn new(chunk: &ChunkData, solid: &SolidTable) -> Self {
+        let mut columns = [0; (CHUNK_WIDTH * CHUNK_DEPTH) as usize];
+        for z in 0..CHUNK_DEPTH {
+            for x in 0..CHUNK_WIDTH {
+                columns[(x + z * CHUNK_WIDTH) as usize] = chunk.top_solid(x, z, solid).map_or(0, |y| y as u8 + 1);
+            }
+        }
+        Self { columns }
+    }
+
+    /// Same as `ChunkData::top_solid`, from the cache
+    #[inline]
+    pub fn top_solid(&self, x: i32, z: i32) -> Option<i32> {
+        if !ChunkData::in_bounds(x, 0, z) {
+            return None;
+        }
+        match self.columns[(x + z * CHUNK_WIDTH) as usize] {
+            0 => None,
+            h => Some(h as i32 - 1),
+        }
+    }
+}
+
+/// Lifecycle of a chunk entity. Every stage is advanced by its own system,
+/// so later stages can be re-run without redoing the earlier ones.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChunkStage {
+    /// Entity spawned, generation task in flight
+    Queued,
+    /// Terrain shape is known
+    Generated,
+    /// Decoration passes (trees, ores, structures) were applied
+    Decorated,
+    /// Light values are up to date
+    Lit,
+    /// Mesh data was built on a background thread
+    Meshed,
+    /// Mesh asset lives on the entity
+    Uploaded,
+}
+
+/// Marker: the chunk voxels changed and it has to be re-lit and re-meshed.
+/// Generation and decoration are not re-run.
+#[derive(Component)]
+pub struct ChunkDirty;
+
+/// Neighbours a smooth mesh was built without, read as Air, as bits over
+/// `ChunkNeighborhood::OFFSETS`. The chunk is re-meshed once one of them is decorated.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MissingNeighbours(pub u16);
+
+/// Sky light per voxel (0..=15), same xzy layout as `ChunkData`.
+#[derive(Component, Clone)]
+pub struct ChunkLight {
+    pub sky: Box<[u8]>,
+}
+
+impl ChunkLight {
+    #[inline]
+    pub fn get(&self, x: i32, y: i32, z: i32) -> u8 {
+        if !ChunkData::in_bounds(x, y, z) {
+            return 15;
+        }
+        self.sky[ChunkData::index(x, y, z)]
+    }
+}
+
+/// Output of the meshing stage: full vertices for `StandardMaterial`,
+/// or packed ones for `TerrainMaterial`
+pub enum ChunkMesh {
+    Full(MeshData),
+    Packed(PackedMeshData),
+}
+
+impl Default for ChunkMesh {
+    fn default() -> Self {
+        ChunkMesh::Full(MeshData::new())
+    }
+}
+
+impl ChunkMesh {
+    pub fn vertex_count(&self) -> usize {
+        match self {
+            ChunkMesh::Full(mesh) => mesh.positions.len(),
+            ChunkMesh::Packed(mesh) => mesh.vertices.len(),
+        }
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        match self {
+            ChunkMesh::Full(mesh) => mesh.indices.len() / 3,
+            ChunkMesh::Packed(mesh) => mesh.indices.len() / 3,
+        }
+    }
+}
+
+/// Mesh built by the meshing stage, waiting to be uploaded
+#[derive(Component)]
+pub struct ChunkMeshData(pub ChunkMesh);
+
+/// Size of the uploaded mesh
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChunkMeshInfo {
+    pub vertices: usize,
+    pub triangles: usize,
+}
+
+/// Generation work, with the time the worker spent on it
+#[derive(Component)]
+pub struct ChunkGenTask(pub Task<(ChunkData, Duration)>);
+
+/// Meshing work, with the time the worker spent on it
 #[derive(Component)]
-pub struct ChunkCompute(pub Task<(ChunkCoords, ChunkData, MeshData)>);
+pub struct ChunkMeshTask(pub Task<(ChunkMesh, Duration)>);
@@ -1,4 +1,4 @@
-use bevy::ecs::resource::Resource;
+use bevy::{ecs::{entity::Entity, resource::Resource}, math::IVec2, platform::collections::HashMap};
 
 #[derive(Resource)]
 pub struct ChunkSemaphore {
@@ -24,4 +24,16 @@ impl ChunkSemaphore {
     pub fn release(&mut self) {
         self.permits = (self.permits + 1).min(self.max);
     }
-}
\ No newline at end of file
+}
+
+/// Chunk coordinates -> chunk entity, for every chunk spawned by the pipeline
+#[derive(Resource, Default)]
+pub struct ChunkMap {
+    pub entities: HashMap<IVec2, Entity>,
+}
+
+impl ChunkMap {
+    pub fn get(&self, coords: IVec2) -> Option<Entity> {
+        self.entities.get(&coords).copied()
+    }
+}
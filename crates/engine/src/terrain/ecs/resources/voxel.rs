@@ -1,18 +1,98 @@
 use bevy::prelude::*;
 
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use assets::blocks::BlockManifest;
 
 use crate::terrain::{defs::voxel::VoxelDefinition, types::Voxel};
 
+/// Not solid, so it is neither meshed nor collided with
+fn water() -> VoxelDefinition {
+    VoxelDefinition {
+        name: "Water".into(),
+        is_solid: false,
+        smooth: false,
+        sound_group: None,
+        texture_layers: None,
+    }
+}
+
 #[derive(Resource, Clone)]
 pub struct VoxelRegistry {
     // Maps the Enum/ID to the actual metadata
     pub definitions: Arc<HashMap<Voxel, VoxelDefinition>>,
 }
 
+impl Default for VoxelRegistry {
+    /// Built-in palette: Air, Stone and Water
+    fn default() -> Self {
+        let mut definitions = HashMap::new();
+
+        definitions.insert(Voxel::AIR, VoxelDefinition {
+            name: "Air".into(),
+            is_solid: false,
+            smooth: false,
+            sound_group: None,
+            texture_layers: None,
+        });
+
+        definitions.insert(Voxel::STONE, VoxelDefinition {
+            name: "Stone".into(),
+            is_solid: true,
+            smooth: false,
+            sound_group: Some("stone".into()),
+            texture_layers: None,
+        });
+
+        definitions.insert(Voxel::WATER, water());
+
+        Self { definitions: definitions.into() }
+    }
+}
+
 impl VoxelRegistry {
     pub fn get(&self, voxel: &Voxel) -> &VoxelDefinition {
         self.definitions.get(voxel)
-            .unwrap_or_else(|| self.definitions.get(&Voxel::Air).expect("Air must be registered"))
+            .unwrap_or_else(|| self.definitions.get(&Voxel::AIR).expect("Air must be registered"))
     }
-}
\ No newline at end of file
+
+    /// Blocks compiled by `asset_compiler`. Air and Water are added when the manifest
+    /// doesn't define IDs 0 and 255.
+    pub fn from_manifest(manifest: &BlockManifest) -> Self {
+        let mut definitions = HashMap::new();
+
+        definitions.insert(Voxel::AIR, VoxelDefinition {
+            name: "Air".into(),
+            is_solid: false,
+            smooth: false,
+            sound_group: None,
+            texture_layers: None,
+        });
+
+        for block in &manifest.blocks {
+            definitions.insert(Voxel(block.id), VoxelDefinition {
+                name: block.name.clone(),
+                is_solid: block.solid,
+                smooth: block.smooth,
+                sound_group: block.sound.clone(),
+                texture_layers: block.faces,
+            });
+        }
+        definitions.entry(Voxel::WATER).or_insert_with(water);
+
+        Self { definitions: definitions.into() }
+    }
+
+    /// Same blocks, with those `keep` rejects made non-solid so a mesher leaves them out
+    pub fn solid_where(&self, keep: impl Fn(&VoxelDefinition) -> bool) -> Self {
+        let definitions = self.definitions.iter().map(|(voxel, definition)| {
+            let is_solid = definition.is_solid && keep(definition);
+            (*voxel, VoxelDefinition { is_solid, ..definition.clone() })
+        });
+        Self { definitions: Arc::new(definitions.collect()) }
+    }
+
+    pub fn load_manifest(path: &Path) -> Result<Self, String> {
+        BlockManifest::load(path).map(|manifest| Self::from_manifest(&manifest))
+    }
+}
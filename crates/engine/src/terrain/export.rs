@@ -0,0 +1,345 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use bevy::math::IVec2;
+
+use crate::terrain::{
+    constants::{CHUNK_DEPTH, CHUNK_WIDTH},
+    ecs::{components::chunk::ChunkCoords, resources::voxel::VoxelRegistry},
+    generator::TerrainManager,
+    meshing::{
+        mesh_data::MeshData,
+        mesher::{mesh_chunk, MesherKind},
+        surface_nets::ChunkNeighborhood,
+    },
+};
+
+/// Generates the chunks in `min..=max` (chunk coordinates) and merges their meshes,
+/// with every chunk moved to its world position. Needs no GPU nor Bevy app.
+pub fn build_terrain_mesh(
+    seed: i32,
+    min: IVec2,
+    max: IVec2,
+    mesher: MesherKind,
+    registry: &VoxelRegistry,
+) -> MeshData {
+    let manager = TerrainManager::new(0, 1, seed);
+
+    // One ring more than requested, so smooth meshers see their neighbours
+    let ring = mesher.needs_neighbors() as i32;
+    let mut chunks = BTreeMap::new();
+    for z in min.y - ring..=max.y + ring {
+        for x in min.x - ring..=max.x + ring {
+            let coords = ChunkCoords(IVec2::new(x, z));
+            let mut data = manager.run(coords);
+            manager.decorate(coords, &mut data);
+            chunks.insert((x, z), data);
+        }
+    }
+
+    let mut merged = MeshData::new();
+    for z in min.y..=max.y {
+        for x in min.x..=max.x {
+            let neighborhood = ChunkNeighborhood::from_fn(|offset| {
+                chunks.get(&(x + offset.x, z + offset.y))
+            });
+            let mesh = mesh_chunk(mesher, &neighborhood, registry);
+            let offset = [(x * CHUNK_WIDTH) as f32, 0.0, (z * CHUNK_DEPTH) as f32];
+            merged.append(&mesh, offset);
+        }
+    }
+    merged
+}
+
+/// Triangles grouped by block ID, in ascending ID order
+fn triangles_by_block(mesh: &MeshData) -> BTreeMap<u32, Vec<[u32; 3]>> {
+    let mut groups: BTreeMap<u32, Vec<[u32; 3]>> = BTreeMap::new();
+    for tri in mesh.indices.chunks_exact(3) {
+        let block = mesh.texture_indices[tri[0] as usize];
+        groups.entry(block).or_default().push([tri[0], tri[1], tri[2]]);
+    }
+    groups
+}
+
+fn registered_name(registry: &VoxelRegistry, id: u32) -> Option<&str> {
+    registry
+        .definitions
+        .iter()
+        .find(|(voxel, _)| voxel.id() as u32 == id)
+        .map(|(_, definition)| definition.name.as_str())
+}
+
+fn block_name(registry: &VoxelRegistry, id: u32) -> String {
+    registered_name(registry, id).map_or_else(|| format!("block_{id}"), str::to_string)
+}
+
+/// OBJ material name: `block_{id}_{name}`, whitespace in the name replaced so loaders
+/// read it as one token
+fn material_name(registry: &VoxelRegistry, id: u32) -> String {
+    match registered_name(registry, id) {
+        Some(name) => format!("block_{id}_{}", name.replace(char::is_whitespace, "_")),
+        None => format!("block_{id}"),
+    }
+}
+
+/// Wavefront OBJ, one `usemtl` group per block ID.
+/// `mtl_file` is referenced with `mtllib` when given, see `write_mtl`.
+pub fn write_obj(
+    mesh: &MeshData,
+    registry: &VoxelRegistry,
+    mtl_file: Option<&str>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(out, "# voxel-engine terrain export")?;
+    if let Some(mtl_file) = mtl_file {
+        writeln!(out, "mtllib {mtl_file}")?;
+    }
+    writeln!(out, "o terrain")?;
+
+    for p in &mesh.positions {
+        writeln!(out, "v {} {} {}", p[0], p[1], p[2])?;
+    }
+    for uv in &mesh.uvs {
+        writeln!(out, "vt {} {}", uv[0], uv[1])?;
+    }
+    for n in &mesh.normals {
+        writeln!(out, "vn {} {} {}", n[0], n[1], n[2])?;
+    }
+
+    for (block, triangles) in triangles_by_block(mesh) {
+        writeln!(out, "g block_{block}")?;
+        writeln!(out, "usemtl {}", material_name(registry, block))?;
+        for tri in triangles {
+            // OBJ indices are 1-based
+            let [a, b, c] = tri.map(|i| i + 1);
+            writeln!(out, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Material library for `write_obj`, with a flat color per block
+pub fn write_mtl(mesh: &MeshData, registry: &VoxelRegistry, out: &mut impl Write) -> io::Result<()> {
+    for block in triangles_by_block(mesh).keys() {
+        let [r, g, b] = block_color(*block);
+        writeln!(out, "newmtl {}", material_name(registry, *block))?;
+        writeln!(out, "Kd {r} {g} {b}")?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Stable debug color per block ID
+fn block_color(id: u32) -> [f32; 3] {
+    let h = id.wrapping_mul(2_654_435_761);
+    [
+        0.3 + 0.6 * ((h & 0xff) as f32 / 255.0),
+        0.3 + 0.6 * (((h >> 8) & 0xff) as f32 / 255.0),
+        0.3 + 0.6 * (((h >> 16) & 0xff) as f32 / 255.0),
+    ]
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+const GLB_MAGIC: u32 = 0x4654_6C67; // "glTF"
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN: u32 = 0x004E_4942;
+
+// glTF enums
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// GLB chunks and buffer views are 4-byte aligned
+fn pad_to_4(bytes: &mut Vec<u8>, fill: u8) {
+    bytes.resize(bytes.len().next_multiple_of(4), fill);
+}
+
+/// Binary glTF 2.0: one mesh, one primitive and one material per block ID.
+/// Materials carry the block ID in `extras.block_id`.
+pub fn write_glb(mesh: &MeshData, registry: &VoxelRegistry, out: &mut impl Write) -> io::Result<()> {
+    let vertex_count = mesh.positions.len();
+    let groups = triangles_by_block(mesh);
+
+    let mut bin: Vec<u8> = Vec::new();
+    let mut buffer_views = Vec::new();
+    let mut accessors = Vec::new();
+
+    let mut push_view = |bin: &mut Vec<u8>, bytes: &[u8], target: u32| -> usize {
+        pad_to_4(bin, 0);
+        buffer_views.push(format!(
+            r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{target}}}"#,
+            bin.len(),
+            bytes.len()
+        ));
+        bin.extend_from_slice(bytes);
+        buffer_views.len() - 1
+    };
+
+    let floats = |data: &[f32]| data.iter().flat_map(|f| f.to_le_bytes()).collect::<Vec<u8>>();
+
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for p in &mesh.positions {
+        for k in 0..3 {
+            min[k] = min[k].min(p[k]);
+            max[k] = max[k].max(p[k]);
+        }
+    }
+    if vertex_count == 0 {
+        min = [0.0; 3];
+        max = [0.0; 3];
+    }
+
+    let view = push_view(&mut bin, &floats(mesh.positions.as_flattened()), ARRAY_BUFFER);
+    accessors.push(format!(
+        r#"{{"bufferView":{view},"componentType":{FLOAT},"count":{vertex_count},"type":"VEC3","min":[{},{},{}],"max":[{},{},{}]}}"#,
+        min[0], min[1], min[2], max[0], max[1], max[2]
+    ));
+    let view = push_view(&mut bin, &floats(mesh.normals.as_flattened()), ARRAY_BUFFER);
+    accessors.push(format!(
+        r#"{{"bufferView":{view},"componentType":{FLOAT},"count":{vertex_count},"type":"VEC3"}}"#
+    ));
+    let view = push_view(&mut bin, &floats(mesh.uvs.as_flattened()), ARRAY_BUFFER);
+    accessors.push(format!(
+        r#"{{"bufferView":{view},"componentType":{FLOAT},"count":{vertex_count},"type":"VEC2"}}"#
+    ));
+
+    let mut primitives = Vec::new();
+    let mut materials = Vec::new();
+    for (block, triangles) in &groups {
+        let indices: Vec<u8> = triangles
+            .iter()
+            .flatten()
+            .flat_map(|i| i.to_le_bytes())
+            .collect();
+        let view = push_view(&mut bin, &indices, ELEMENT_ARRAY_BUFFER);
+        accessors.push(format!(
+            r#"{{"bufferView":{view},"componentType":{UNSIGNED_INT},"count":{},"type":"SCALAR"}}"#,
+            triangles.len() * 3
+        ));
+
+        let [r, g, b] = block_color(*block);
+        materials.push(format!(
+            r#"{{"name":{},"pbrMetallicRoughness":{{"baseColorFactor":[{r},{g},{b},1.0],"metallicFactor":0.0}},"extras":{{"block_id":{block}}}}}"#,
+            json_string(&block_name(registry, *block))
+        ));
+        primitives.push(format!(
+            r#"{{"attributes":{{"POSITION":0,"NORMAL":1,"TEXCOORD_0":2}},"indices":{},"material":{}}}"#,
+            accessors.len() - 1,
+            materials.len() - 1
+        ));
+    }
+    pad_to_4(&mut bin, 0);
+
+    let mut json = format!(
+        concat!(
+            r#"{{"asset":{{"version":"2.0","generator":"voxel-engine"}},"scene":0,"scenes":[{{"nodes":[0]}}],"#,
+            r#""nodes":[{{"name":"terrain","mesh":0}}],"meshes":[{{"name":"terrain","primitives":[{}]}}],"#,
+            r#""materials":[{}],"accessors":[{}],"bufferViews":[{}],"buffers":[{{"byteLength":{}}}]}}"#
+        ),
+        primitives.join(","),
+        materials.join(","),
+        accessors.join(","),
+        buffer_views.join(","),
+        bin.len()
+    )
+    .into_bytes();
+    pad_to_4(&mut json, b' ');
+
+    let total = 12 + 8 + json.len() + 8 + bin.len();
+    out.write_all(&GLB_MAGIC.to_le_bytes())?;
+    out.write_all(&2u32.to_le_bytes())?;
+    out.write_all(&(total as u32).to_le_bytes())?;
+    out.write_all(&(json.len() as u32).to_le_bytes())?;
+    out.write_all(&GLB_CHUNK_JSON.to_le_bytes())?;
+    out.write_all(&json)?;
+    out.write_all(&(bin.len() as u32).to_le_bytes())?;
+    out.write_all(&GLB_CHUNK_BIN.to_le_bytes())?;
+    out.write_all(&bin)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::types::Voxel;
+
+    fn small_terrain() -> (MeshData, VoxelRegistry) {
+        let registry = VoxelRegistry::default();
+        let mesh = build_terrain_mesh(7, IVec2::new(-1, 0), IVec2::new(0, 0), MesherKind::BinaryGreedy, &registry);
+        (mesh, registry)
+    }
+
+    #[test]
+    fn chunks_are_offset_into_world_space() {
+        let (mesh, _) = small_terrain();
+        let min_x = mesh.positions.iter().map(|p| p[0]).fold(f32::MAX, f32::min);
+        let max_x = mesh.positions.iter().map(|p| p[0]).fold(f32::MIN, f32::max);
+        assert_eq!((min_x, max_x), (-CHUNK_WIDTH as f32, CHUNK_WIDTH as f32));
+    }
+
+    #[test]
+    fn obj_has_every_vertex_and_triangle() {
+        let (mesh, registry) = small_terrain();
+        let mut out = Vec::new();
+        write_obj(&mesh, &registry, Some("terrain.mtl"), &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        assert_eq!(text.lines().filter(|l| l.starts_with("v ")).count(), mesh.positions.len());
+        assert_eq!(text.lines().filter(|l| l.starts_with("f ")).count(), mesh.indices.len() / 3);
+        assert!(text.contains("usemtl block_1_Stone"));
+    }
+
+    #[test]
+    fn material_names_are_one_token() {
+        let (mesh, registry) = small_terrain();
+        let mut definitions = (*registry.definitions).clone();
+        definitions.get_mut(&Voxel::STONE).unwrap().name = "Dark Oak".into();
+        let registry = VoxelRegistry { definitions: definitions.into() };
+
+        let (mut obj, mut mtl) = (Vec::new(), Vec::new());
+        write_obj(&mesh, &registry, Some("terrain.mtl"), &mut obj).unwrap();
+        write_mtl(&mesh, &registry, &mut mtl).unwrap();
+        let (obj, mtl) = (String::from_utf8(obj).unwrap(), String::from_utf8(mtl).unwrap());
+        assert!(obj.lines().any(|l| l == "usemtl block_1_Dark_Oak"));
+        assert!(mtl.lines().any(|l| l == "newmtl block_1_Dark_Oak"));
+        let materials = obj.lines().chain(mtl.lines()).filter(|l| l.starts_with("usemtl") || l.starts_with("newmtl"));
+        assert!(materials.into_iter().all(|l| l.split_whitespace().count() == 2));
+    }
+
+    #[test]
+    fn glb_chunks_are_well_formed() {
+        let (mesh, registry) = small_terrain();
+        let mut out = Vec::new();
+        write_glb(&mesh, &registry, &mut out).unwrap();
+
+        let word = |at: usize| u32::from_le_bytes(out[at..at + 4].try_into().unwrap());
+        assert_eq!(word(0), GLB_MAGIC);
+        assert_eq!(word(8) as usize, out.len());
+
+        let json_len = word(12) as usize;
+        assert_eq!(word(16), GLB_CHUNK_JSON);
+        assert!(json_len.is_multiple_of(4));
+        let json = std::str::from_utf8(&out[20..20 + json_len]).unwrap();
+        assert!(json.contains(r#""extras":{"block_id":1}"#));
+
+        let bin_at = 20 + json_len;
+        assert_eq!(word(bin_at + 4), GLB_CHUNK_BIN);
+        assert_eq!(bin_at + 8 + word(bin_at) as usize, out.len());
+    }
+}
@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use assets::biomes::{Biome, BiomeTable};
+use assets::noise_graph::NoiseSampler;
+use bevy::math::IVec2;
+
+use crate::terrain::constants::{CHUNK_DEPTH, CHUNK_WIDTH};
+
+/// Climate of a world column, from the table's `temperature` and `humidity` noises.
+/// A noise the table does not define reads 0.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Climate {
+    pub temperature: f64,
+    pub humidity: f64,
+}
+
+/// A biome table with its climate noises built for the world seed
+#[derive(Clone)]
+pub struct BiomeMap {
+    table: Arc<BiomeTable>,
+    temperature: Option<NoiseSampler>,
+    humidity: Option<NoiseSampler>,
+}
+
+impl BiomeMap {
+    pub fn new(table: BiomeTable, seed: i32) -> Self {
+        let sampler = |name: &str| table.climate.get(name).map(|graph| graph.build(seed as u32));
+        Self {
+            temperature: sampler("temperature"),
+            humidity: sampler("humidity"),
+            table: Arc::new(table),
+        }
+    }
+
+    pub fn table(&self) -> &BiomeTable {
+        &self.table
+    }
+
+    pub fn climate(&self, x: i32, z: i32) -> Climate {
+        let sample = |noise: &Option<NoiseSampler>| noise.as_ref().map_or(0.0, |noise| noise.sample(x as f64, z as f64));
+        Climate {
+            temperature: sample(&self.temperature),
+            humidity: sample(&self.humidity),
+        }
+    }
+
+    pub fn biome(&self, x: i32, z: i32) -> Option<&Biome> {
+        let climate = self.climate(x, z);
+        self.table.select(climate.temperature, climate.humidity)
+    }
+
+    /// `Biome::height_scale` at the corners of a chunk, -x-z, +x-z, -x+z, +x+z
+    pub fn corner_scales(&self, coords: IVec2) -> [f32; 4] {
+        let scale = |dx: i32, dz: i32| {
+            let x = (coords.x + dx) * CHUNK_WIDTH;
+            let z = (coords.y + dz) * CHUNK_DEPTH;
+            self.biome(x, z).map_or(1.0, |biome| biome.height_scale)
+        };
+        [scale(0, 0), scale(1, 0), scale(0, 1), scale(1, 1)]
+    }
+
+    /// Height scale of a column, blended between the corners of its chunk so heights stay
+    /// continuous across biome borders
+    pub fn height_scale(&self, x: i32, z: i32) -> f32 {
+        let coords = IVec2::new(x.div_euclid(CHUNK_WIDTH), z.div_euclid(CHUNK_DEPTH));
+        blend_corners(self.corner_scales(coords), x, z)
+    }
+}
+
+/// Bilinear blend of `corner_scales` at a column of that chunk
+pub fn blend_corners(corners: [f32; 4], x: i32, z: i32) -> f32 {
+    let tx = x.rem_euclid(CHUNK_WIDTH) as f32 / CHUNK_WIDTH as f32;
+    let tz = z.rem_euclid(CHUNK_DEPTH) as f32 / CHUNK_DEPTH as f32;
+    let near = corners[0] + (corners[1] - corners[0]) * tx;
+    let far = corners[2] + (corners[3] - corners[2]) * tx;
+    near + (far - near) * tz
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use assets::noise_graph::{NoiseGraph, NoiseNode};
+
+    use super::*;
+    use crate::terrain::constants::SUBSURFACE_DEPTH;
+    use crate::terrain::ecs::components::chunk::ChunkCoords;
+    use crate::terrain::generator::TerrainManager;
+    use crate::terrain::types::Voxel;
+
+    fn biome(name: &str, surface: u8, temperature: (f64, f64), height_scale: f32) -> Biome {
+        Biome {
+            name: name.into(),
+            surface,
+            subsurface: surface,
+            temperature,
+            humidity: (f64::NEG_INFINITY, f64::INFINITY),
+            height_scale,
+        }
+    }
+
+    #[test]
+    fn selects_by_climate() {
+        let noise = NoiseNode::Fbm { seed_offset: 0, frequency: 0.02, octaves: 2, persistence: 0.5, lacunarity: 2.0 };
+        let table = BiomeTable {
+            climate: BTreeMap::from([("temperature".to_string(), NoiseGraph { root: noise })]),
+            biomes: vec![biome("Hot", 4, (0.0, 1.0), 2.0), biome("Cold", 5, (-1.0, 0.0), 0.5)],
+        };
+        let map = BiomeMap::new(table, 7);
+
+        let columns: Vec<(i32, i32)> = (0..64).flat_map(|z| (0..64).map(move |x| (x * 8, z * 8))).collect();
+        for &(x, z) in &columns {
+            let climate = map.climate(x, z);
+            assert_eq!(climate.humidity, 0.0);
+            let expected = if climate.temperature >= 0.0 { "Hot" } else { "Cold" };
+            assert_eq!(map.biome(x, z).unwrap().name, expected);
+        }
+        assert!(columns.iter().any(|&(x, z)| map.biome(x, z).unwrap().name == "Cold"));
+
+        // Chunk corners are shared by the neighbours, so the blend has no seams:
+        // a chunk's first column matches the far edge of the chunk before it
+        for &(x, z) in &columns {
+            let scale = map.height_scale(x, z);
+            assert!((0.5..=2.0).contains(&scale));
+            if x % CHUNK_WIDTH == 0 {
+                let before = map.corner_scales(IVec2::new(x / CHUNK_WIDTH - 1, z.div_euclid(CHUNK_DEPTH)));
+                let tz = z.rem_euclid(CHUNK_DEPTH) as f32 / CHUNK_DEPTH as f32;
+                assert!((before[1] + (before[3] - before[1]) * tz - scale).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn generates_biome_blocks() {
+        let table = BiomeTable {
+            climate: BTreeMap::new(),
+            biomes: vec![biome("Plains", 2, (f64::NEG_INFINITY, f64::INFINITY), 1.0)],
+        };
+        let plain = TerrainManager::new(1, 1, 3);
+        let manager = plain.clone().with_biomes(table);
+        let chunk = manager.run(ChunkCoords(IVec2::new(2, -1)));
+
+        let top = plain.surface_height(32, -16);
+        assert_eq!(manager.surface_height(32, -16), top);
+        assert_eq!(chunk.get(0, top - 1, 0), Voxel(2));
+        assert_eq!(chunk.get(0, top - 1 - SUBSURFACE_DEPTH as i32, 0), Voxel(2));
+        assert_eq!(chunk.get(0, top - 2 - SUBSURFACE_DEPTH as i32, 0), Voxel::STONE);
+        assert_eq!(chunk.get(0, top, 0), Voxel::AIR);
+    }
+}
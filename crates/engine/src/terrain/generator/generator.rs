@@ -1,15 +1,68 @@
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::Arc;
+
+use assets::biomes::{Biome, BiomeTable};
+use assets::structure::StructureTemplate;
+use serde::{Deserialize, Serialize};
+
 use crate::terrain::{
-    constants::{CHUNK_DEPTH, CHUNK_HEIGHT, CHUNK_WIDTH},
+    config::WorldConfig,
+    constants::{CHUNK_DEPTH, CHUNK_HEIGHT, CHUNK_WIDTH, STRUCTURE_CHANCE, SUBSURFACE_DEPTH},
     ecs::components::chunk::{ChunkCoords, ChunkData},
+    structures::{place_structure, structure_bounds, ChunkView, Placement, Rotation},
     types::Voxel,
 };
-use super::{heightmap::generate_height, noise::TerrainNoise};
-use bevy::{math::IVec2, platform::collections::HashSet, prelude::Resource};
+use super::{
+    biomes::{blend_corners, BiomeMap, Climate},
+    heightmap::generate_height_scaled,
+    noise::{GeneratorParams, TerrainNoise},
+};
+use bevy::{log::error, math::{IVec2, IVec3}, platform::collections::HashSet, prelude::Resource};
+
+/// How the terrain shape is produced
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GeneratorKind {
+    /// Fractal noise heightmap
+    Noise(GeneratorParams),
+    /// Solid up to `height` everywhere, for tests and building
+    Flat { height: i32 },
+}
+
+impl Default for GeneratorKind {
+    fn default() -> Self {
+        GeneratorKind::Noise(GeneratorParams::default())
+    }
+}
+
+/// Produces the voxels of a chunk. Implement it to plug a custom generator into
+/// `VoxelTerrainPlugin`. `generate` runs on a background thread.
+pub trait TerrainGenerator: Send + Sync + 'static {
+    fn generate(&self, coords: ChunkCoords) -> ChunkData;
+
+    /// Decoration passes on top of the shape, run once per chunk after `generate`
+    fn decorate(&self, _coords: ChunkCoords, _chunk: &mut ChunkData) {}
+}
+
+/// The generator used by the chunk pipeline
+#[derive(Resource, Clone)]
+pub struct ChunkGenerator(pub Arc<dyn TerrainGenerator>);
+
+/// Which chunks the pipeline spawns on its own
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChunkLoading {
+    /// A spiral out to the view radius around the origin
+    #[default]
+    Spiral,
+    /// Only chunks asked for with `TerrainManager::request`
+    Manual,
+}
 
 #[derive(Clone)]
 pub struct TerrainConfig {
     pub radius: i32,
     pub threads: usize,
+    pub loading: ChunkLoading,
 }
 
 #[derive(Clone)]
@@ -26,14 +79,29 @@ pub struct TerrainManager {
     pub config: TerrainConfig,
     pub spiral_state: TerrainSpiralState,
     pub spawned_chunks: HashSet<IVec2>,
+    /// Chunks requested by the game, spawned before the spiral continues
+    pub requested: VecDeque<IVec2>,
     pub active_permits: usize,
     pub noise_handle: TerrainNoise,
+    /// Fixed surface height, replaces the noise when set
+    pub flat_height: Option<i32>,
+    /// Columns below this height are topped up with water, no sea when `None`
+    pub sea_level: Option<i32>,
+    pub seed: i32,
+    /// Templates scattered by the decoration stage
+    pub structures: Arc<Vec<StructureTemplate>>,
+    /// Surface blocks and relief per column, all stone at scale 1 when `None`
+    pub biomes: Option<Arc<BiomeMap>>,
 }
 
 impl TerrainManager {
     pub fn new(radius: i32, threads: usize, seed: i32) -> Self {
+        Self::with_params(radius, threads, seed, GeneratorParams::default())
+    }
+
+    pub fn with_params(radius: i32, threads: usize, seed: i32, params: GeneratorParams) -> Self {
         Self {
-            config: TerrainConfig { radius, threads },
+            config: TerrainConfig { radius, threads, loading: ChunkLoading::Spiral },
             spiral_state: TerrainSpiralState {
                 center: IVec2::ZERO,
                 spiral_x: 0,
@@ -42,36 +110,200 @@ impl TerrainManager {
                 dy: -1,
             },
             spawned_chunks: HashSet::new(),
+            requested: VecDeque::new(),
             active_permits: 0,
-            noise_handle: TerrainNoise::new(seed),
+            noise_handle: TerrainNoise::with_params(seed, &params),
+            flat_height: None,
+            sea_level: None,
+            seed,
+            structures: Arc::default(),
+            biomes: None,
+        }
+    }
+
+    pub fn from_config(config: &WorldConfig) -> Self {
+        let threads = config.thread_budget();
+        let manager = match &config.generator {
+            GeneratorKind::Noise(params) => Self::with_params(config.view_radius, threads, config.seed, params.clone()),
+            GeneratorKind::Flat { height } => Self {
+                flat_height: Some(*height),
+                ..Self::new(config.view_radius, threads, config.seed)
+            },
+        };
+        Self { sea_level: Some(config.sea_level), ..manager }
+    }
+
+    /// `from_config` with the biome table and structure templates the config names, read from
+    /// the asset folder `assets`. Files that fail to load are logged and left out.
+    pub fn for_world(config: &WorldConfig, assets: &Path) -> Self {
+        let mut manager = Self::from_config(config);
+        if let Some(path) = &config.biomes {
+            match BiomeTable::load(&assets.join(path)) {
+                Ok(table) => manager.set_biomes(table),
+                Err(e) => error!("{e}, generating without biomes"),
+            }
         }
+        let structures = config.structures.iter().filter_map(|path| {
+            StructureTemplate::load(&assets.join(path))
+                .inspect_err(|e| error!("{e}, the structure is not scattered"))
+                .ok()
+        });
+        manager.with_structures(structures.collect())
+    }
+
+    /// Switches to a noise generator with new settings and forgets every spawned chunk,
+    /// so loading starts over from `center`. The caller despawns the chunk entities,
+    /// which drops their tasks, so the permits are given back here.
+    pub fn regenerate(&mut self, seed: i32, params: &GeneratorParams, center: IVec2) {
+        self.seed = seed;
+        self.noise_handle = TerrainNoise::with_params(seed, params);
+        if let Some(biomes) = self.biomes.take() {
+            self.set_biomes(biomes.table().clone());
+        }
+        self.flat_height = None;
+        self.spawned_chunks.clear();
+        self.requested.clear();
+        self.active_permits = 0;
+        self.spiral_state = TerrainSpiralState { center, spiral_x: 0, spiral_y: 0, dx: 0, dy: -1 };
+    }
+
+    /// Terrain height of a world column, the first air voxel above the ground
+    pub fn surface_height(&self, world_x: i32, world_z: i32) -> i32 {
+        match self.flat_height {
+            Some(height) => height,
+            None => {
+                let scale = self.biomes.as_ref().map_or(1.0, |biomes| biomes.height_scale(world_x, world_z));
+                generate_height_scaled(&self.noise_handle, world_x as f32, world_z as f32, scale)
+            }
+        }
+    }
+
+    pub fn with_biomes(mut self, table: BiomeTable) -> Self {
+        self.set_biomes(table);
+        self
+    }
+
+    /// Builds the table's climate noises for the current seed
+    pub fn set_biomes(&mut self, table: BiomeTable) {
+        self.biomes = Some(Arc::new(BiomeMap::new(table, self.seed)));
+    }
+
+    pub fn biome(&self, world_x: i32, world_z: i32) -> Option<&Biome> {
+        self.biomes.as_ref()?.biome(world_x, world_z)
+    }
+
+    pub fn climate(&self, world_x: i32, world_z: i32) -> Option<Climate> {
+        Some(self.biomes.as_ref()?.climate(world_x, world_z))
+    }
+
+    pub fn with_structures(mut self, structures: Vec<StructureTemplate>) -> Self {
+        self.structures = Arc::new(structures);
+        self
     }
 
     /// Pure generation logic - runs inside background threads
     pub fn run(&self, chunk_coord: ChunkCoords) -> ChunkData {
         let len = (CHUNK_WIDTH * CHUNK_DEPTH * CHUNK_HEIGHT) as usize;
-        let mut voxels = vec![Voxel::Air; len].into_boxed_slice();
+        let mut voxels = vec![Voxel::AIR; len].into_boxed_slice();
         let y_stride = (CHUNK_WIDTH * CHUNK_DEPTH) as usize;
+        let corners = self.biomes.as_ref().map(|biomes| biomes.corner_scales(chunk_coord.0));
 
         for lz in 0..CHUNK_DEPTH {
-            let world_z = (chunk_coord.y * CHUNK_DEPTH + lz) as f32;
+            let world_z = chunk_coord.y * CHUNK_DEPTH + lz;
             let z_offset = (lz * CHUNK_WIDTH) as usize;
 
             for lx in 0..CHUNK_WIDTH {
-                let world_x = (chunk_coord.x * CHUNK_WIDTH + lx) as f32;
-                let height = generate_height(&self.noise_handle, world_x, world_z);
+                let world_x = chunk_coord.x * CHUNK_WIDTH + lx;
+                let height = match (self.flat_height, corners) {
+                    (None, Some(corners)) => {
+                        let scale = blend_corners(corners, world_x, world_z);
+                        generate_height_scaled(&self.noise_handle, world_x as f32, world_z as f32, scale)
+                    }
+                    _ => self.surface_height(world_x, world_z),
+                };
                 let fill_to = height.clamp(0, CHUNK_HEIGHT) as usize;
 
                 let mut current_idx = lx as usize + z_offset;
                 for _ in 0..fill_to {
-                    voxels[current_idx] = Voxel::Solid;
+                    voxels[current_idx] = Voxel::STONE;
                     current_idx += y_stride;
                 }
+                let sea_to = self.sea_level.map_or(0, |level| level.clamp(0, CHUNK_HEIGHT) as usize);
+                for _ in fill_to..sea_to {
+                    voxels[current_idx] = Voxel::WATER;
+                    current_idx += y_stride;
+                }
+
+                // The top layers take the biome's blocks
+                if let Some(biome) = self.biome(world_x, world_z) {
+                    let column = lx as usize + z_offset;
+                    for depth in 0..fill_to.min(SUBSURFACE_DEPTH + 1) {
+                        let block = if depth == 0 { biome.surface } else { biome.subsurface };
+                        voxels[column + (fill_to - 1 - depth) * y_stride] = Voxel(block);
+                    }
+                }
             }
         }
         ChunkData { voxels }
     }
 
+    /// Decoration passes (trees, ores, structures) on top of the generated shape.
+    /// Runs once per chunk, after `run`.
+    ///
+    /// Every chunk rolls for one structure site. Sites of the chunks within
+    /// `structure_radius` are replayed too and clipped to this chunk, so structures
+    /// continue across borders.
+    pub fn decorate(&self, chunk_coord: ChunkCoords, chunk: &mut ChunkData) {
+        if self.structures.is_empty() {
+            return;
+        }
+        let radius = self.structure_radius();
+        let mut view = ChunkView { coords: chunk_coord.0, data: chunk };
+        for dz in -radius..=radius {
+            for dx in -radius..=radius {
+                let site = chunk_coord.0 + IVec2::new(dx, dz);
+                if let Some((template, origin, placement)) = self.structure_site(site) {
+                    place_structure(&mut view, template, origin, placement);
+                }
+            }
+        }
+    }
+
+    /// How many chunks away from its site chunk the largest template can reach,
+    /// in any rotation or mirroring
+    pub fn structure_radius(&self) -> i32 {
+        let mut reach = 0;
+        for template in self.structures.iter() {
+            for rotation in Rotation::ALL {
+                for mirror_x in [false, true] {
+                    let placement = Placement { rotation, mirror_x, mirror_z: false };
+                    let (min, max) = structure_bounds(template, IVec3::ZERO, placement);
+                    reach = reach.max(min.x.abs()).max(max.x).max(min.z.abs()).max(max.z);
+                }
+            }
+        }
+        // The origin can be anywhere in the site chunk
+        (reach + CHUNK_WIDTH.min(CHUNK_DEPTH) - 1) / CHUNK_WIDTH.min(CHUNK_DEPTH)
+    }
+
+    /// The structure rolled for a chunk, if any. Only depends on the seed and coordinates.
+    fn structure_site(&self, coords: IVec2) -> Option<(&StructureTemplate, IVec3, Placement)> {
+        let hash = site_hash(self.seed, coords);
+        if (hash % 1000) as f32 >= STRUCTURE_CHANCE * 1000.0 {
+            return None;
+        }
+        let template = &self.structures[(hash >> 10) as usize % self.structures.len()];
+        let x = coords.x * CHUNK_WIDTH + ((hash >> 20) % CHUNK_WIDTH as u64) as i32;
+        let z = coords.y * CHUNK_DEPTH + ((hash >> 28) % CHUNK_DEPTH as u64) as i32;
+        let y = self.surface_height(x, z);
+        let placement = Placement {
+            rotation: Rotation::ALL[((hash >> 36) % 4) as usize],
+            mirror_x: (hash >> 38) & 1 == 1,
+            mirror_z: false,
+        };
+        Some((template, IVec3::new(x, y, z), placement))
+    }
+
     /// Increments the spiral and returns the absolute world coordinate
     fn next_coord(&mut self) -> IVec2 {
         let coord = IVec2::new(self.spiral_state.spiral_x, self.spiral_state.spiral_y) + self.spiral_state.center;
@@ -89,8 +321,34 @@ impl TerrainManager {
         coord
     }
 
+    /// Queues a chunk for generation, does nothing if it was spawned already
+    pub fn request(&mut self, coords: IVec2) {
+        if !self.spawned_chunks.contains(&coords) && !self.requested.contains(&coords) {
+            self.requested.push_back(coords);
+        }
+    }
+
+    /// Requests every chunk within `radius` of `center`, nearest first
+    pub fn request_around(&mut self, center: IVec2, radius: i32) {
+        let mut around: Vec<IVec2> = (-radius..=radius)
+            .flat_map(|z| (-radius..=radius).map(move |x| center + IVec2::new(x, z)))
+            .collect();
+        around.sort_by_key(|c| (*c - center).length_squared());
+        for coords in around {
+            self.request(coords);
+        }
+    }
+
     /// Finds the next chunk that needs spawning
     pub fn try_get_next_chunk(&mut self) -> Option<IVec2> {
+        while let Some(coord) = self.requested.pop_front() {
+            if !self.spawned_chunks.contains(&coord) {
+                return Some(coord);
+            }
+        }
+        if self.config.loading == ChunkLoading::Manual {
+            return None;
+        }
         loop {
             if self.spiral_state.spiral_x.abs() > self.config.radius || 
                self.spiral_state.spiral_y.abs() > self.config.radius {
@@ -104,4 +362,103 @@ impl TerrainManager {
             }
         }
     }
-}
\ No newline at end of file
+}
+impl TerrainGenerator for TerrainManager {
+    fn generate(&self, coords: ChunkCoords) -> ChunkData {
+        self.run(coords)
+    }
+
+    fn decorate(&self, coords: ChunkCoords, chunk: &mut ChunkData) {
+        TerrainManager::decorate(self, coords, chunk)
+    }
+}
+
+/// SplitMix64 over the seed and chunk coordinates
+fn site_hash(seed: i32, coords: IVec2) -> u64 {
+    let mix = |x: u64| {
+        let x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        let x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        x ^ (x >> 31)
+    };
+    let hash = mix(seed as u32 as u64);
+    let hash = mix(hash ^ coords.x as u32 as u64);
+    mix(hash ^ coords.y as u32 as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(size: (u32, u32, u32), anchor: (i32, i32, i32)) -> StructureTemplate {
+        let len = (size.0 * size.1 * size.2) as usize;
+        StructureTemplate { size, anchor, palette: vec![Some(1)], voxels: vec![0; len], markers: Vec::new() }
+    }
+
+    #[test]
+    fn replays_sites_as_far_as_the_largest_template_reaches() {
+        let manager = TerrainManager::new(1, 1, 0);
+        assert_eq!(manager.clone().with_structures(vec![template((3, 5, 3), (1, 0, 1))]).structure_radius(), 1);
+        // 39 blocks out from the anchor, so up to 3 chunks from the site in every direction
+        let wide = manager.with_structures(vec![template((3, 5, 3), (1, 0, 1)), template((1, 1, 40), (0, 0, 0))]);
+        assert_eq!(wide.structure_radius(), 3);
+
+        // Find a chunk holding part of a structure whose site is more than one chunk away
+        let (coords, far) = (0..64)
+            .find_map(|x| {
+                let coords = IVec2::new(x, 0);
+                let mut far = ChunkData::new();
+                let mut view = ChunkView { coords, data: &mut far };
+                let mut written = 0;
+                for dz in -3..=3i32 {
+                    for dx in -3..=3i32 {
+                        let site = wide.structure_site(coords + IVec2::new(dx, dz));
+                        if let (true, Some((template, origin, placement))) = (dx.abs().max(dz.abs()) >= 2, site) {
+                            written += place_structure(&mut view, template, origin, placement);
+                        }
+                    }
+                }
+                (written > 0).then_some((coords, far))
+            })
+            .expect("no structure reaches past the neighbouring chunks");
+
+        let mut chunk = ChunkData::new();
+        wide.decorate(ChunkCoords(coords), &mut chunk);
+        for (decorated, far) in chunk.voxels.iter().zip(far.voxels.iter()) {
+            if *far != Voxel::AIR {
+                assert_eq!(decorated, far);
+            }
+        }
+    }
+
+    #[test]
+    fn fills_the_sea_up_to_sea_level() {
+        let config = WorldConfig { generator: GeneratorKind::Flat { height: 10 }, sea_level: 14, ..Default::default() };
+        let chunk = TerrainManager::from_config(&config).run(ChunkCoords(IVec2::new(3, -2)));
+        assert_eq!(chunk.get(5, 9, 5), Voxel::STONE);
+        assert!((10..14).all(|y| chunk.get(5, y, 5) == Voxel::WATER));
+        assert_eq!(chunk.get(5, 14, 5), Voxel::AIR);
+
+        // Land above the sea stays dry
+        let dry = WorldConfig { sea_level: 4, ..config };
+        let chunk = TerrainManager::from_config(&dry).run(ChunkCoords(IVec2::new(3, -2)));
+        assert!(!chunk.voxels.contains(&Voxel::WATER));
+    }
+
+    #[test]
+    fn for_world_loads_the_biome_table() {
+        let assets = std::env::temp_dir().join(format!("voxel-for-world-{}", std::process::id()));
+        std::fs::create_dir_all(&assets).unwrap();
+        std::fs::write(assets.join("test.biomes.ron"), r#"(biomes: [(name: "Desert", surface: 4, subsurface: 4)])"#).unwrap();
+
+        let config = WorldConfig {
+            biomes: Some("test.biomes.ron".into()),
+            structures: vec!["missing.structure.ron".into()],
+            ..Default::default()
+        };
+        let manager = TerrainManager::for_world(&config, &assets);
+        assert_eq!(manager.biome(12, -40).map(|biome| biome.name.as_str()), Some("Desert"));
+        assert!(TerrainManager::from_config(&config).biome(12, -40).is_none());
+        std::fs::remove_dir_all(&assets).unwrap();
+    }
+}
@@ -1,12 +1,11 @@
 use super::noise::TerrainNoise;
 
-const HEIGHT_MULTIPLIER: f32 = 20.0;
-const BASE_HEIGHT: f32 = 50.0;
-
 pub fn generate_height(noise: &TerrainNoise, world_x: f32, world_z: f32) -> i32 {
-    let value = noise.sample_2d(world_x, world_z);
-
-    println!("Noise value at ({}, {}): {}", world_x, world_z, value);
+    generate_height_scaled(noise, world_x, world_z, 1.0)
+}
 
-    ((value * HEIGHT_MULTIPLIER) + BASE_HEIGHT) as i32
+/// `generate_height` with the relief multiplied by `scale`, e.g. a biome's height scale
+pub fn generate_height_scaled(noise: &TerrainNoise, world_x: f32, world_z: f32, scale: f32) -> i32 {
+    let value = noise.sample_2d(world_x, world_z);
+    ((value.powf(noise.height_curve) * noise.height_scale * scale) + noise.base_height) as i32
 }
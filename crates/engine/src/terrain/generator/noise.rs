@@ -1,28 +1,75 @@
 use noise::{Fbm, MultiFractal, NoiseFn, Perlin};
+use serde::{Deserialize, Serialize};
 
 use crate::terrain::constants::noise::*;
 
+/// Shape of the height noise. Defaults are the values in `constants::noise`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneratorParams {
+    pub frequency: f64,
+    pub octaves: usize,
+    pub persistence: f64,
+    pub lacunarity: f64,
+    /// Scales the sample coordinates on top of `frequency`
+    pub strength: f64,
+    /// Height difference between noise 0.0 and 1.0
+    pub height_scale: f32,
+    pub base_height: f32,
+    /// Exponent on the noise before scaling, above 1 flattens valleys and sharpens peaks
+    pub height_curve: f32,
+}
+
+impl Default for GeneratorParams {
+    fn default() -> Self {
+        Self {
+            frequency: PERLIN_SCALE,
+            octaves: FBM_OCTAVES,
+            persistence: FBM_GAIN,
+            lacunarity: FBM_LACUNARITY,
+            strength: FBM_WEIGHTED_STRENGTH,
+            height_scale: HEIGHT_MULTIPLIER,
+            base_height: BASE_HEIGHT,
+            height_curve: HEIGHT_CURVE,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct TerrainNoise {
     noise: Fbm<Perlin>,
+    strength: f64,
+    pub height_scale: f32,
+    pub base_height: f32,
+    pub height_curve: f32,
 }
 
 impl TerrainNoise {
     pub fn new(seed: i32) -> Self {
+        Self::with_params(seed, &GeneratorParams::default())
+    }
+
+    pub fn with_params(seed: i32, params: &GeneratorParams) -> Self {
         let noise = Fbm::<Perlin>::new(seed as u32)
-            .set_frequency(PERLIN_SCALE)
-            .set_octaves(FBM_OCTAVES)
-            .set_persistence(FBM_GAIN)
-            .set_lacunarity(FBM_LACUNARITY);
+            .set_frequency(params.frequency)
+            .set_octaves(params.octaves)
+            .set_persistence(params.persistence)
+            .set_lacunarity(params.lacunarity);
 
-        Self { noise }
+        Self {
+            noise,
+            strength: params.strength,
+            height_scale: params.height_scale,
+            base_height: params.base_height,
+            height_curve: params.height_curve,
+        }
     }
 
     pub fn sample_2d(&self, x: f32, z: f32) -> f32 {
         // Perlin noise returns a value in the range [-1.0, 1.0]
         (self.noise.get([
-            x as f64 * FBM_WEIGHTED_STRENGTH as f64,
-            z as f64 * FBM_WEIGHTED_STRENGTH as f64,
+            x as f64 * self.strength,
+            z as f64 * self.strength,
         ]) as f32
             + 1.)
             / 2.
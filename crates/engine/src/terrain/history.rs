@@ -0,0 +1,346 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use bevy::prelude::*;
+
+use crate::terrain::{
+    ecs::components::chunk::{ChunkCoords, ChunkData},
+    storage::{read_varint, write_varint},
+    structures::{chunk_origin, world_to_chunk, VoxelAccess},
+    types::Voxel,
+};
+
+/// Default `EditHistory` budget, in compressed bytes
+pub const DEFAULT_HISTORY_BUDGET: usize = 16 * 1024 * 1024;
+
+/// Before and after values of the voxels an edit changed in one chunk.
+/// Stored as `count, (index gap, before id, after id)*` with varint counts and gaps,
+/// a brush stroke of neighbouring voxels takes about three bytes a voxel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChunkEdit {
+    bytes: Vec<u8>,
+}
+
+impl ChunkEdit {
+    /// `changes` maps `ChunkData` indices to their before and after values
+    fn encode(changes: &BTreeMap<u16, (Voxel, Voxel)>) -> Self {
+        let mut bytes = Vec::with_capacity(changes.len() * 3 + 2);
+        write_varint(&mut bytes, changes.len() as u32);
+        let mut previous = 0;
+        for (&index, &(before, after)) in changes {
+            write_varint(&mut bytes, (index - previous) as u32);
+            bytes.extend_from_slice(&[before.id(), after.id()]);
+            previous = index;
+        }
+        Self { bytes }
+    }
+
+    /// `(index, before, after)` for every changed voxel, by index
+    pub fn changes(&self) -> Vec<(u16, Voxel, Voxel)> {
+        // Only ever built by `encode`, it can't be malformed
+        let mut pos = 0;
+        let count = read_varint(&self.bytes, &mut pos).unwrap();
+        let mut index = 0u16;
+        let mut changes = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            index += read_varint(&self.bytes, &mut pos).unwrap() as u16;
+            changes.push((index, Voxel(self.bytes[pos]), Voxel(self.bytes[pos + 1])));
+            pos += 2;
+        }
+        changes
+    }
+
+    pub fn size_bytes(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+/// One undoable step: what it changed, per chunk
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EditBatch {
+    chunks: HashMap<ChunkCoords, ChunkEdit>,
+}
+
+impl EditBatch {
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Chunks the batch changed
+    pub fn chunks(&self) -> impl Iterator<Item = IVec2> + '_ {
+        self.chunks.keys().map(|coords| coords.0)
+    }
+
+    pub fn get(&self, coords: ChunkCoords) -> Option<&ChunkEdit> {
+        self.chunks.get(&coords)
+    }
+
+    pub fn size_bytes(&self) -> usize {
+        self.chunks.values().map(ChunkEdit::size_bytes).sum()
+    }
+
+    /// Folds a later batch into this one: a voxel changed by both keeps
+    /// this batch's before value and takes the later after value
+    pub fn merge(&mut self, later: EditBatch) {
+        for (coords, edit) in later.chunks {
+            let mut changes: BTreeMap<u16, (Voxel, Voxel)> = self
+                .chunks
+                .get(&coords)
+                .map(|earlier| earlier.changes().into_iter().map(|(i, before, after)| (i, (before, after))).collect())
+                .unwrap_or_default();
+            for (index, before, after) in edit.changes() {
+                changes.entry(index).and_modify(|change| change.1 = after).or_insert((before, after));
+            }
+            // Back to where it started, e.g. a block placed then removed in one stroke
+            changes.retain(|_, (before, after)| before != after);
+            if changes.is_empty() {
+                self.chunks.remove(&coords);
+            } else {
+                self.chunks.insert(coords, ChunkEdit::encode(&changes));
+            }
+        }
+    }
+
+    /// Writes the before (`undo`) or after values through `access`. With `WorldVoxels`
+    /// the chunks are marked dirty like any other edit, so they are re-lit and re-meshed.
+    fn apply(&self, access: &mut impl VoxelAccess, undo: bool) {
+        for (coords, edit) in &self.chunks {
+            let origin = chunk_origin(coords.0);
+            for (index, before, after) in edit.changes() {
+                let local = ChunkData::position(index as usize);
+                access.set_voxel(origin + local, if undo { before } else { after });
+            }
+        }
+    }
+}
+
+/// Records what goes through it, writes pass on to `access`.
+/// A voxel written several times keeps its first before and last after value.
+pub struct EditRecorder<'a, A: VoxelAccess> {
+    access: &'a mut A,
+    changes: HashMap<ChunkCoords, BTreeMap<u16, (Voxel, Voxel)>>,
+}
+
+impl<'a, A: VoxelAccess> EditRecorder<'a, A> {
+    pub fn new(access: &'a mut A) -> Self {
+        Self { access, changes: HashMap::new() }
+    }
+
+    /// The recorded batch, voxels that ended up unchanged are left out
+    pub fn finish(self) -> EditBatch {
+        let chunks = self
+            .changes
+            .into_iter()
+            .filter_map(|(coords, mut changes)| {
+                changes.retain(|_, (before, after)| before != after);
+                (!changes.is_empty()).then(|| (coords, ChunkEdit::encode(&changes)))
+            })
+            .collect();
+        EditBatch { chunks }
+    }
+}
+
+impl<A: VoxelAccess> VoxelAccess for EditRecorder<'_, A> {
+    fn get_voxel(&self, pos: IVec3) -> Option<Voxel> {
+        self.access.get_voxel(pos)
+    }
+
+    fn set_voxel(&mut self, pos: IVec3, voxel: Voxel) -> bool {
+        let Some(before) = self.access.get_voxel(pos) else {
+            return false;
+        };
+        if before == voxel {
+            return true;
+        }
+        if !self.access.set_voxel(pos, voxel) {
+            return false;
+        }
+        let (coords, local) = world_to_chunk(pos);
+        let index = ChunkData::index(local.x, local.y, local.z) as u16;
+        self.changes
+            .entry(ChunkCoords(coords))
+            .or_default()
+            .entry(index)
+            .and_modify(|change| change.1 = voxel)
+            .or_insert((before, voxel));
+        true
+    }
+}
+
+/// Undo and redo stacks of edit batches. The oldest steps are dropped once the
+/// batches take more than `budget` bytes.
+#[derive(Resource, Debug)]
+pub struct EditHistory {
+    undo: VecDeque<EditBatch>,
+    redo: Vec<EditBatch>,
+    budget: usize,
+    /// Set while a stroke is open, its batches merge into one step
+    stroke: Option<bool>,
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self::with_budget(DEFAULT_HISTORY_BUDGET)
+    }
+}
+
+impl EditHistory {
+    pub fn with_budget(budget: usize) -> Self {
+        Self { undo: VecDeque::new(), redo: Vec::new(), budget, stroke: None }
+    }
+
+    /// Records a step and drops the redo stack. Empty batches are ignored.
+    pub fn push(&mut self, batch: EditBatch) {
+        if batch.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        match (self.stroke, self.undo.back_mut()) {
+            (Some(true), Some(top)) => top.merge(batch),
+            _ => self.undo.push_back(batch),
+        }
+        if self.stroke.is_some() {
+            self.stroke = Some(true);
+        }
+        self.trim();
+    }
+
+    /// Batches pushed until `end_stroke` become a single step
+    pub fn begin_stroke(&mut self) {
+        self.stroke = Some(false);
+    }
+
+    pub fn end_stroke(&mut self) {
+        self.stroke = None;
+    }
+
+    /// Reverts the last step. Returns the chunks it touched, `None` with nothing to undo.
+    pub fn undo(&mut self, access: &mut impl VoxelAccess) -> Option<Vec<IVec2>> {
+        self.stroke = None;
+        let batch = self.undo.pop_back()?;
+        batch.apply(access, true);
+        let chunks = batch.chunks().collect();
+        self.redo.push(batch);
+        Some(chunks)
+    }
+
+    /// Replays the last undone step
+    pub fn redo(&mut self, access: &mut impl VoxelAccess) -> Option<Vec<IVec2>> {
+        self.stroke = None;
+        let batch = self.redo.pop()?;
+        batch.apply(access, false);
+        let chunks = batch.chunks().collect();
+        self.undo.push_back(batch);
+        Some(chunks)
+    }
+
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    /// Compressed size of both stacks
+    pub fn size_bytes(&self) -> usize {
+        self.undo.iter().chain(&self.redo).map(EditBatch::size_bytes).sum()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.stroke = None;
+    }
+
+    /// The latest step is always kept, even alone over the budget
+    fn trim(&mut self) {
+        while self.undo.len() > 1 && self.size_bytes() > self.budget {
+            self.undo.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::structures::test_utils::empty_chunks;
+
+    const CHUNKS: [IVec2; 2] = [IVec2::ZERO, IVec2::NEG_X];
+
+    fn edit(world: &mut HashMap<IVec2, ChunkData>, positions: &[IVec3], voxel: Voxel) -> EditBatch {
+        let mut recorder = EditRecorder::new(world);
+        for pos in positions {
+            recorder.set_voxel(*pos, voxel);
+        }
+        recorder.finish()
+    }
+
+    #[test]
+    fn undo_and_redo_restore_voxels() {
+        let mut world = empty_chunks(CHUNKS);
+        let mut history = EditHistory::default();
+        let positions = [IVec3::new(-1, 10, 3), IVec3::new(0, 10, 3), IVec3::new(40, 10, 3)];
+
+        let batch = edit(&mut world, &positions, Voxel::STONE);
+        // The third position is not loaded
+        assert_eq!(batch.chunks().count(), 2);
+        history.push(batch);
+        history.push(edit(&mut world, &positions[..1], Voxel(3)));
+
+        let mut undone = history.undo(&mut world).unwrap();
+        undone.sort_by_key(|c| c.x);
+        assert_eq!(undone, vec![IVec2::NEG_X]);
+        assert_eq!(world.get_voxel(positions[0]), Some(Voxel::STONE));
+        history.undo(&mut world).unwrap();
+        assert_eq!(world.get_voxel(positions[0]), Some(Voxel::AIR));
+        assert_eq!(world.get_voxel(positions[1]), Some(Voxel::AIR));
+        assert!(history.undo(&mut world).is_none());
+
+        history.redo(&mut world).unwrap();
+        assert_eq!(world.get_voxel(positions[1]), Some(Voxel::STONE));
+        // A new edit drops what is left to redo
+        history.push(edit(&mut world, &positions[1..2], Voxel::AIR));
+        assert_eq!((history.undo_len(), history.redo_len()), (2, 0));
+    }
+
+    #[test]
+    fn strokes_are_one_step() {
+        let mut world = empty_chunks(CHUNKS);
+        let mut history = EditHistory::default();
+        let a = IVec3::new(2, 20, 2);
+        let b = IVec3::new(3, 20, 2);
+
+        history.begin_stroke();
+        history.push(edit(&mut world, &[a], Voxel::STONE));
+        history.push(edit(&mut world, &[a, b], Voxel(3)));
+        history.end_stroke();
+        history.push(edit(&mut world, &[b], Voxel::STONE));
+        assert_eq!(history.undo_len(), 2);
+
+        history.undo(&mut world);
+        history.undo(&mut world);
+        // Back to before the stroke, not to its first batch
+        assert_eq!(world.get_voxel(a), Some(Voxel::AIR));
+        assert_eq!(world.get_voxel(b), Some(Voxel::AIR));
+    }
+
+    #[test]
+    fn budget_drops_the_oldest_steps() {
+        let mut world = empty_chunks(CHUNKS);
+        let layer: Vec<IVec3> = (0..16).flat_map(|z| (0..16).map(move |x| IVec3::new(x, 5, z))).collect();
+        let size = edit(&mut world.clone(), &layer, Voxel::STONE).size_bytes();
+        // Neighbouring indices: a byte of gap and two of values each
+        assert!(size <= layer.len() * 3 + 4, "{size} bytes");
+
+        let mut history = EditHistory::with_budget(size * 2);
+        for voxel in [Voxel::STONE, Voxel(3), Voxel(4)] {
+            history.push(edit(&mut world, &layer, voxel));
+        }
+        assert_eq!(history.undo_len(), 2);
+        assert!(history.size_bytes() <= size * 2);
+        history.undo(&mut world);
+        history.undo(&mut world);
+        // The first layer of stone could not be undone any more
+        assert_eq!(world.get_voxel(IVec3::new(7, 5, 7)), Some(Voxel::STONE));
+    }
+}
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+
+use assets::biomes::BiomeTable;
+use assets::blocks::BlockManifest;
+use assets::structure::StructureTemplate;
+
+use crate::terrain::ecs::{
+    components::chunk::{Chunk, ChunkDirty, ChunkStage},
+    resources::voxel::VoxelRegistry,
+};
+use crate::terrain::generator::{ChunkGenerator, TerrainManager};
+
+/// Keeps the compiled block manifest loaded, so the asset server watches it
+#[derive(Resource)]
+pub struct BlockManifestHandle(pub Handle<BlockManifest>);
+
+/// Keeps the world's biome table and its climate noises loaded
+#[derive(Resource)]
+pub struct BiomeTableHandle(pub Handle<BiomeTable>);
+
+/// Keeps the world's structure templates loaded, in `WorldConfig::structures` order
+#[derive(Resource)]
+pub struct StructureHandles(pub Vec<Handle<StructureTemplate>>);
+
+pub struct BlockReload;
+
+impl BlockReload {
+    /// Rebuilds `VoxelRegistry` when the manifest changes and re-meshes every chunk
+    /// that already went through decoration.
+    pub fn apply_manifest(
+        mut commands: Commands,
+        mut events: MessageReader<AssetEvent<BlockManifest>>,
+        handle: Option<Res<BlockManifestHandle>>,
+        manifests: Res<Assets<BlockManifest>>,
+        mut registry: ResMut<VoxelRegistry>,
+        chunks: Query<(Entity, &ChunkStage), With<Chunk>>,
+    ) {
+        let Some(handle) = handle else {
+            events.clear();
+            return;
+        };
+        let changed = events.read().any(|event| match event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } => *id == handle.0.id(),
+            _ => false,
+        });
+        if !changed {
+            return;
+        }
+        let Some(manifest) = manifests.get(&handle.0) else {
+            return;
+        };
+
+        *registry = VoxelRegistry::from_manifest(manifest);
+        let mut remeshed = 0;
+        for (entity, stage) in &chunks {
+            if *stage >= ChunkStage::Decorated {
+                commands.entity(entity).insert(ChunkDirty);
+                remeshed += 1;
+            }
+        }
+        info!("Block manifest reloaded, {} blocks, re-meshing {remeshed} chunks", manifest.blocks.len());
+    }
+}
+
+pub struct WorldgenReload;
+
+impl WorldgenReload {
+    /// Swaps the biome table of the built-in generator when it or one of its climate noises
+    /// changes. Chunks generated from then on use it, the loaded ones are left as they are.
+    pub fn apply_biomes(
+        mut events: MessageReader<AssetEvent<BiomeTable>>,
+        handle: Option<Res<BiomeTableHandle>>,
+        tables: Res<Assets<BiomeTable>>,
+        mut manager: ResMut<TerrainManager>,
+        mut generator: ResMut<ChunkGenerator>,
+    ) {
+        let Some(handle) = handle else {
+            events.clear();
+            return;
+        };
+        // The table was read from disk at startup, only later edits matter
+        let changed = events
+            .read()
+            .any(|event| matches!(event, AssetEvent::Modified { id } if *id == handle.0.id()));
+        let Some(table) = changed.then(|| tables.get(&handle.0)).flatten() else {
+            return;
+        };
+
+        manager.set_biomes(table.clone());
+        generator.0 = Arc::new(manager.clone());
+        info!("Biome table reloaded, {} biomes", table.biomes.len());
+    }
+
+    /// Swaps the structure templates of the built-in generator when one of them changes
+    pub fn apply_structures(
+        mut events: MessageReader<AssetEvent<StructureTemplate>>,
+        handles: Option<Res<StructureHandles>>,
+        templates: Res<Assets<StructureTemplate>>,
+        mut manager: ResMut<TerrainManager>,
+        mut generator: ResMut<ChunkGenerator>,
+    ) {
+        let Some(handles) = handles else {
+            events.clear();
+            return;
+        };
+        let changed = events.read().any(|event| match event {
+            AssetEvent::Modified { id } => handles.0.iter().any(|handle| handle.id() == *id),
+            _ => false,
+        });
+        if !changed {
+            return;
+        }
+
+        let structures: Vec<_> = handles.0.iter().filter_map(|handle| templates.get(handle)).cloned().collect();
+        info!("Structures reloaded, {} templates", structures.len());
+        manager.structures = Arc::new(structures);
+        generator.0 = Arc::new(manager.clone());
+    }
+}
@@ -0,0 +1,28 @@
+use crate::terrain::{
+    constants::{CHUNK_DEPTH, CHUNK_HEIGHT, CHUNK_WIDTH},
+    ecs::{
+        components::chunk::{ChunkData, ChunkLight},
+        resources::voxel::VoxelRegistry,
+    },
+};
+
+pub const MAX_LIGHT: u8 = 15;
+
+/// Column sky light: full light until the first solid voxel from the top, dark below it.
+pub fn compute_skylight(chunk: &ChunkData, registry: &VoxelRegistry) -> ChunkLight {
+    let mut sky = vec![0u8; chunk.voxels.len()].into_boxed_slice();
+
+    for z in 0..CHUNK_DEPTH {
+        for x in 0..CHUNK_WIDTH {
+            for y in (0..CHUNK_HEIGHT).rev() {
+                let i = ChunkData::index(x, y, z);
+                if registry.get(&chunk.voxels[i]).is_solid {
+                    break;
+                }
+                sky[i] = MAX_LIGHT;
+            }
+        }
+    }
+
+    ChunkLight { sky }
+}
@@ -1,4 +1,6 @@
 use crate::terrain::meshing::mesh_data::MeshData;
+use crate::terrain::meshing::packed::PackedMeshData;
+use crate::terrain::render::ATTRIBUTE_PACKED_VERTEX;
 use bevy::asset::RenderAssetUsages;
 use bevy::mesh::{Indices, PrimitiveTopology};
 use bevy::prelude::*;
@@ -16,3 +18,17 @@ pub fn meshdata_to_bevy_mesh(data: MeshData) -> Mesh {
 
     mesh
 }
+
+/// Mesh with the single `ATTRIBUTE_PACKED_VERTEX` attribute, for `TerrainMaterial`.
+/// Bevy can't compute bounds without positions, so the caller inserts an `Aabb`.
+pub fn packed_mesh_to_bevy_mesh(data: PackedMeshData) -> Mesh {
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+
+    mesh.insert_attribute(ATTRIBUTE_PACKED_VERTEX, data.vertices);
+    mesh.insert_indices(Indices::U32(data.indices));
+
+    mesh
+}
@@ -0,0 +1,227 @@
+use crate::terrain::constants::{CHUNK_DEPTH, CHUNK_HEIGHT, CHUNK_WIDTH};
+use crate::terrain::ecs::components::chunk::ChunkData;
+use crate::terrain::ecs::resources::voxel::VoxelRegistry;
+use crate::terrain::meshing::greedy::{emit_quad, FaceCell};
+use crate::terrain::meshing::mesh_data::MeshData;
+
+/// One bit per voxel along an axis. CHUNK_HEIGHT is 128, so Y columns don't fit in a u64.
+type Column = u128;
+
+const _: () = assert!(CHUNK_WIDTH <= Column::BITS as i32);
+const _: () = assert!(CHUNK_HEIGHT <= Column::BITS as i32);
+const _: () = assert!(CHUNK_DEPTH <= Column::BITS as i32);
+
+/// Dense `is_solid` lookup indexed by block ID, so the mesher never hashes
+pub struct SolidTable([bool; 256]);
+
+impl SolidTable {
+    pub fn new(registry: &VoxelRegistry) -> Self {
+        // Unregistered IDs resolve to Air in the registry, which is not solid
+        let mut table = [false; 256];
+        for (voxel, definition) in registry.definitions.iter() {
+            table[voxel.id() as usize] = definition.is_solid;
+        }
+        Self(table)
+    }
+
+    #[inline]
+    pub fn is_solid(&self, id: u8) -> bool {
+        self.0[id as usize]
+    }
+}
+
+/// Rows of one kind of face on a plane, one bit per cell along u
+type FaceRows = (FaceCell, Vec<Column>);
+
+/// Same output as `greedy_mesh` (same quads, same order), but face culling is done
+/// with bitwise ops on solid columns and the greedy pass merges on per-face bitmasks.
+pub fn binary_greedy_mesh(chunk: &ChunkData, registry: &VoxelRegistry) -> MeshData {
+    let solid = SolidTable::new(registry);
+    let dims = [CHUNK_WIDTH, CHUNK_HEIGHT, CHUNK_DEPTH];
+    let mut out = MeshData::new();
+
+    // 1. Solid columns along each axis, one per (u, v) cell, in a single pass over the chunk
+    let mut columns: [Vec<Column>; 3] = std::array::from_fn(|axis| {
+        vec![0; (dims[(axis + 1) % 3] * dims[(axis + 2) % 3]) as usize]
+    });
+    for y in 0..CHUNK_HEIGHT {
+        for z in 0..CHUNK_DEPTH {
+            for x in 0..CHUNK_WIDTH {
+                if solid.is_solid(chunk.voxels[ChunkData::index(x, y, z)].id()) {
+                    let p = [x, y, z];
+                    for (axis, columns) in columns.iter_mut().enumerate() {
+                        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+                        columns[(p[u] + p[v] * dims[u]) as usize] |= 1 << p[axis];
+                    }
+                }
+            }
+        }
+    }
+
+    for axis in 0..3 {
+        let u = (axis + 1) % 3;
+        let v = (axis + 2) % 3;
+
+        let plane_w = dims[u];
+        let plane_h = dims[v];
+        let axis_len = dims[axis];
+
+        // 2. Face culling. Plane `p` sits between voxels p - 1 and p, each face goes in the
+        // rows of its block and direction. Outside the chunk counts as empty, like in `greedy_mesh`.
+        let mut planes: Vec<Vec<FaceRows>> = vec![Vec::new(); (axis_len + 1) as usize];
+        let mut add_face = |plane: usize, i: i32, j: i32, voxel_layer: i32, normal_sign: i8| {
+            let mut x = [0i32; 3];
+            x[u] = i;
+            x[v] = j;
+            x[axis] = voxel_layer;
+            let cell = FaceCell { voxel: chunk.voxels[ChunkData::index(x[0], x[1], x[2])], normal_sign };
+            let faces = &mut planes[plane];
+            let rows = match faces.iter().position(|(kind, _)| *kind == cell) {
+                Some(kind) => &mut faces[kind].1,
+                None => {
+                    faces.push((cell, vec![0; plane_h as usize]));
+                    &mut faces.last_mut().unwrap().1
+                }
+            };
+            rows[j as usize] |= 1 << i;
+        };
+
+        for j in 0..plane_h {
+            for i in 0..plane_w {
+                let column = columns[axis][(i + j * plane_w) as usize];
+
+                // Solid at s, empty at s + 1: +axis face on plane s + 1
+                let mut pos = column & !(column >> 1);
+                while pos != 0 {
+                    let s = pos.trailing_zeros() as i32;
+                    pos &= pos - 1;
+                    add_face(s as usize + 1, i, j, s, 1);
+                }
+
+                // Solid at s, empty at s - 1: -axis face on plane s
+                let mut neg = column & !(column << 1);
+                while neg != 0 {
+                    let s = neg.trailing_zeros() as i32;
+                    neg &= neg - 1;
+                    add_face(s as usize, i, j, s, -1);
+                }
+            }
+        }
+
+        // 3. Greedy grouping. Quads start at the lowest set bit over all kinds of face,
+        // the scan order of `greedy_mesh`, then grow within the rows of their own kind.
+        for (plane, faces) in planes.iter_mut().enumerate() {
+            for j in 0..plane_h as usize {
+                loop {
+                    let row = faces.iter().fold(0, |row, (_, rows)| row | rows[j]);
+                    if row == 0 {
+                        break;
+                    }
+
+                    let i = row.trailing_zeros() as i32;
+                    let Some((cell, rows)) = faces.iter_mut().find(|(_, rows)| rows[j] & (1 << i) != 0) else {
+                        unreachable!("bit {i} is set in row {j}");
+                    };
+
+                    let w = (rows[j] >> i).trailing_ones() as i32;
+                    let span: Column = (Column::MAX >> (Column::BITS as i32 - w)) << i;
+                    let mut h = 1;
+                    while j + h < plane_h as usize && rows[j + h] & span == span {
+                        h += 1;
+                    }
+
+                    emit_quad(
+                        &mut out,
+                        axis, u, v,
+                        plane as i32, i, j as i32, w, h as i32,
+                        cell.normal_sign,
+                        cell.voxel,
+                    );
+
+                    for row in &mut rows[j..j + h] {
+                        *row &= !span;
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::terrain::{
+        defs::voxel::VoxelDefinition,
+        ecs::components::chunk::ChunkCoords, generator::TerrainManager,
+        meshing::greedy::greedy_mesh, types::Voxel,
+    };
+    use bevy::math::IVec2;
+
+    fn assert_same_mesh(a: &MeshData, b: &MeshData) {
+        assert_eq!(a.positions, b.positions);
+        assert_eq!(a.normals, b.normals);
+        assert_eq!(a.uvs, b.uvs);
+        assert_eq!(a.texture_indices, b.texture_indices);
+        assert_eq!(a.indices, b.indices);
+    }
+
+    #[test]
+    fn matches_greedy_on_generated_chunks() {
+        let registry = VoxelRegistry::default();
+        let manager = TerrainManager::new(1, 1, 42);
+
+        for coords in [IVec2::new(0, 0), IVec2::new(-1, 3), IVec2::new(7, -2)] {
+            let chunk = manager.run(ChunkCoords(coords));
+            assert_same_mesh(&binary_greedy_mesh(&chunk, &registry), &greedy_mesh(&chunk, &registry));
+        }
+    }
+
+    #[test]
+    fn matches_greedy_on_noisy_chunks() {
+        let registry = VoxelRegistry::default();
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+
+        for density in [0.0, 0.1, 0.5, 0.9, 1.0] {
+            let mut chunk = ChunkData::new();
+            for voxel in chunk.voxels.iter_mut() {
+                // xorshift, good enough for test patterns
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                if (state % 1000) as f64 / 1000.0 < density {
+                    *voxel = Voxel::STONE;
+                }
+            }
+            assert_same_mesh(&binary_greedy_mesh(&chunk, &registry), &greedy_mesh(&chunk, &registry));
+        }
+    }
+
+    #[test]
+    fn matches_greedy_with_mixed_blocks() {
+        let mut definitions = VoxelRegistry::default().definitions.as_ref().clone();
+        for (id, name) in [(2, "Dirt"), (3, "Sand")] {
+            let definition = VoxelDefinition { name: name.into(), ..definitions[&Voxel::STONE].clone() };
+            definitions.insert(Voxel(id), definition);
+        }
+        let registry = VoxelRegistry { definitions: Arc::new(definitions) };
+
+        // Layers of different blocks with holes, so planes hold several kinds of face
+        let mut chunk = ChunkData::new();
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        for (index, voxel) in chunk.voxels.iter_mut().enumerate() {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let layer = index / (CHUNK_WIDTH * CHUNK_DEPTH) as usize;
+            if layer < 40 && !state.is_multiple_of(8) {
+                // One block per band of 4 layers, with some of the next one mixed in
+                let block = (layer / 4) as u64 + state % 2;
+                *voxel = Voxel(1 + (block % 3) as u8);
+            }
+        }
+        assert_same_mesh(&binary_greedy_mesh(&chunk, &registry), &greedy_mesh(&chunk, &registry));
+    }
+}
@@ -5,9 +5,9 @@ use crate::terrain::meshing::mesh_data::MeshData;
 use crate::terrain::types::Voxel;
 
 #[derive(Clone, Copy, PartialEq, Eq)]
-struct FaceCell {
-    voxel: Voxel,
-    normal_sign: i8,
+pub(crate) struct FaceCell {
+    pub(crate) voxel: Voxel,
+    pub(crate) normal_sign: i8,
 }
 
 type MaskCell = Option<FaceCell>;
@@ -36,14 +36,14 @@ pub fn greedy_mesh(chunk: &ChunkData, registry: &VoxelRegistry) -> MeshData {
                     x[v] = j;
                     x[axis] = slice;
 
-                    let neg_side = if slice >= 0 { chunk.get(x[0], x[1], x[2]) } else { Voxel::Air };
+                    let neg_side = if slice >= 0 { chunk.get(x[0], x[1], x[2]) } else { Voxel::AIR };
                     let pos_side = if slice < axis_len - 1 {
                         chunk.get(
                             x[0] + (axis == 0) as i32,
                             x[1] + (axis == 1) as i32,
                             x[2] + (axis == 2) as i32,
                         )
-                    } else { Voxel::Air };
+                    } else { Voxel::AIR };
 
                     // DATA-DRIVEN CHECK: Use the registry to see if the voxel is solid
                     let neg_solid = registry.get(&neg_side).is_solid;
@@ -106,7 +106,8 @@ pub fn greedy_mesh(chunk: &ChunkData, registry: &VoxelRegistry) -> MeshData {
     out
 }
 
-fn emit_quad(
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn emit_quad(
     out: &mut MeshData,
     d: usize, u: usize, v: usize,
     plane: i32,
@@ -137,6 +138,7 @@ fn emit_quad(
         pos[v] = vv as f32;
         out.positions.push(pos);
         out.normals.push(normal);
+        out.texture_indices.push(voxel.id() as u32);
     }
 
     // Tiled UVs: Important for greedy quads to look like individual blocks
@@ -147,11 +149,192 @@ fn emit_quad(
         [0.0, h as f32],
     ]);
 
-    // OPTIONAL: If you add texture indices to your MeshData later:
-    // out.texture_indices.push(voxel as u32);
-
     out.indices.extend_from_slice(&[
         base, base + 1, base + 2, 
         base, base + 2, base + 3
     ]);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bevy::math::Vec3;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::terrain::defs::voxel::VoxelDefinition;
+
+    /// Not air, but not solid either: faces next to it are exposed
+    const GLASS: Voxel = Voxel(2);
+    const DIRT: Voxel = Voxel(3);
+    const BLOCKS: [Voxel; 4] = [Voxel::AIR, Voxel::STONE, GLASS, DIRT];
+
+    /// One unit square of surface: axis, normal sign, minimum corner and block
+    type Face = (usize, i8, [i32; 3], Voxel);
+
+    fn registry() -> VoxelRegistry {
+        let blocks = [(Voxel::AIR, "air", false), (Voxel::STONE, "stone", true), (GLASS, "glass", false), (DIRT, "dirt", true)];
+        let definitions = blocks
+            .into_iter()
+            .map(|(voxel, name, is_solid)| (voxel, VoxelDefinition { name: name.into(), is_solid, smooth: false, sound_group: None, texture_layers: None }))
+            .collect::<HashMap<_, _>>();
+        VoxelRegistry { definitions: definitions.into() }
+    }
+
+    fn solid(chunk: &ChunkData, registry: &VoxelRegistry, p: [i32; 3]) -> bool {
+        ChunkData::in_bounds(p[0], p[1], p[2]) && registry.get(&chunk.get(p[0], p[1], p[2])).is_solid
+    }
+
+    /// Reference mesher: every face of a solid voxel that is not against another solid one
+    fn naive_faces(chunk: &ChunkData, registry: &VoxelRegistry) -> HashMap<Face, usize> {
+        let mut faces = HashMap::new();
+        for index in 0..chunk.voxels.len() {
+            let p = ChunkData::position(index).to_array();
+            if !solid(chunk, registry, p) {
+                continue;
+            }
+            for axis in 0..3 {
+                for sign in [1i8, -1] {
+                    let mut neighbour = p;
+                    neighbour[axis] += sign as i32;
+                    if solid(chunk, registry, neighbour) {
+                        continue;
+                    }
+                    let mut corner = p;
+                    corner[axis] += (sign == 1) as i32;
+                    faces.insert((axis, sign, corner, chunk.voxels[index]), 1);
+                }
+            }
+        }
+        faces
+    }
+
+    /// Checks the quads are well formed and splits them into unit faces
+    fn quad_faces(mesh: &MeshData) -> Result<HashMap<Face, usize>, TestCaseError> {
+        let vertices = mesh.positions.len();
+        prop_assert_eq!(vertices % 4, 0);
+        prop_assert_eq!(mesh.indices.len(), vertices / 4 * 6);
+        prop_assert_eq!(mesh.normals.len(), vertices);
+        prop_assert_eq!(mesh.uvs.len(), vertices);
+        prop_assert_eq!(mesh.texture_indices.len(), vertices);
+
+        let mut faces = HashMap::new();
+        for quad in 0..vertices / 4 {
+            let base = quad * 4;
+            let corners = &mesh.positions[base..base + 4];
+            let normal = mesh.normals[base];
+            prop_assert!(mesh.normals[base..base + 4].iter().all(|n| *n == normal));
+            let axis = normal.iter().position(|c| *c != 0.0).unwrap();
+            let sign = normal[axis] as i8;
+            prop_assert_eq!(normal.iter().filter(|c| **c != 0.0).count(), 1);
+
+            // Both triangles use this quad's vertices, counter-clockwise seen from the normal
+            for triangle in mesh.indices[quad * 6..quad * 6 + 6].chunks(3) {
+                prop_assert!(triangle.iter().all(|i| (base..base + 4).contains(&(*i as usize))));
+                let [a, b, c] = [0, 1, 2].map(|k| Vec3::from(mesh.positions[triangle[k] as usize]));
+                let facing = (b - a).cross(c - a).dot(Vec3::from(normal));
+                prop_assert!(facing > 0.0, "quad {} winds against its normal", quad);
+            }
+
+            let min = [0, 1, 2].map(|k| corners.iter().map(|c| c[k] as i32).min().unwrap());
+            let max = [0, 1, 2].map(|k| corners.iter().map(|c| c[k] as i32).max().unwrap());
+            prop_assert_eq!(min[axis], max[axis], "quad {} is not flat", quad);
+            let voxel = Voxel(mesh.texture_indices[base] as u8);
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+            for a in min[u]..max[u] {
+                for b in min[v]..max[v] {
+                    let mut corner = min;
+                    corner[u] = a;
+                    corner[v] = b;
+                    *faces.entry((axis, sign, corner, voxel)).or_insert(0) += 1;
+                }
+            }
+        }
+        Ok(faces)
+    }
+
+    /// Every unit edge of the surface is shared by an even number of faces
+    fn assert_closed(faces: &HashMap<Face, usize>) -> Result<(), TestCaseError> {
+        let mut edges: HashMap<([i32; 3], usize), usize> = HashMap::new();
+        for (axis, _, corner, _) in faces.keys() {
+            for (along, across) in [((axis + 1) % 3, (axis + 2) % 3), ((axis + 2) % 3, (axis + 1) % 3)] {
+                for offset in [0, 1] {
+                    let mut start = *corner;
+                    start[across] += offset;
+                    *edges.entry((start, along)).or_insert(0) += 1;
+                }
+            }
+        }
+        for (edge, count) in edges {
+            prop_assert!(count % 2 == 0, "edge {:?} is used by {} faces", edge, count);
+        }
+        Ok(())
+    }
+
+    fn check_mesh(chunk: &ChunkData) -> Result<(), TestCaseError> {
+        let registry = registry();
+        let mesh = greedy_mesh(chunk, &registry);
+        let expected = naive_faces(chunk, &registry);
+        let covered = quad_faces(&mesh)?;
+
+        let area: usize = covered.values().sum();
+        prop_assert_eq!(area, expected.len(), "surface area differs from the naive mesh");
+        // Covered exactly once, and nothing the naive mesh does not have (interior faces)
+        prop_assert_eq!(&covered, &expected);
+        assert_closed(&covered)
+    }
+
+    /// Random blocks in a slab of 8 layers, anywhere from the floor to the ceiling
+    fn scattered() -> impl Strategy<Value = ChunkData> {
+        const LAYERS: i32 = 8;
+        let len = (CHUNK_WIDTH * CHUNK_DEPTH * LAYERS) as usize;
+        (0..=CHUNK_HEIGHT - LAYERS, prop::collection::vec(0..BLOCKS.len(), len)).prop_map(|(bottom, blocks)| {
+            let mut chunk = ChunkData::new();
+            let offset = ChunkData::index(0, bottom, 0);
+            for (i, block) in blocks.into_iter().enumerate() {
+                chunk.voxels[offset + i] = BLOCKS[block];
+            }
+            chunk
+        })
+    }
+
+    /// Columns of one block each, so large quads get merged
+    fn columns() -> impl Strategy<Value = ChunkData> {
+        let len = (CHUNK_WIDTH * CHUNK_DEPTH) as usize;
+        prop::collection::vec((0..BLOCKS.len(), 0..=CHUNK_HEIGHT), len).prop_map(|columns| {
+            let mut chunk = ChunkData::new();
+            for (i, (block, height)) in columns.into_iter().enumerate() {
+                let (x, z) = (i as i32 % CHUNK_WIDTH, i as i32 / CHUNK_WIDTH);
+                for y in 0..height {
+                    chunk.set(x, y, z, BLOCKS[block]);
+                }
+            }
+            chunk
+        })
+    }
+
+    #[test]
+    fn empty_and_full_chunks() {
+        check_mesh(&ChunkData::new()).unwrap();
+        let mut full = ChunkData::new();
+        full.fill(Voxel::STONE);
+        check_mesh(&full).unwrap();
+        // A full chunk is a single box
+        assert_eq!(greedy_mesh(&full, &registry()).positions.len(), 6 * 4);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn scattered_blocks_match_the_naive_mesh(chunk in scattered()) {
+            check_mesh(&chunk)?;
+        }
+
+        #[test]
+        fn columns_match_the_naive_mesh(chunk in columns()) {
+            check_mesh(&chunk)?;
+        }
+    }
+}
@@ -3,16 +3,40 @@ pub struct MeshData {
     pub positions: Vec<[f32; 3]>,
     pub normals: Vec<[f32; 3]>,
     pub uvs: Vec<[f32; 2]>,
+    /// Block ID per vertex, `pack_mesh` turns it into the texture layer of the face
+    pub texture_indices: Vec<u32>,
     pub indices: Vec<u32>,
 }
 
+impl Default for MeshData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl MeshData {
     pub fn new() -> Self {
         Self {
             positions: Vec::new(),
             normals: Vec::new(),
             uvs: Vec::new(),
+            texture_indices: Vec::new(),
             indices: Vec::new(),
         }
     }
+
+    /// Appends `other` with every position moved by `offset`
+    pub fn append(&mut self, other: &MeshData, offset: [f32; 3]) {
+        let base = self.positions.len() as u32;
+        self.positions.extend(
+            other
+                .positions
+                .iter()
+                .map(|p| [p[0] + offset[0], p[1] + offset[1], p[2] + offset[2]]),
+        );
+        self.normals.extend_from_slice(&other.normals);
+        self.uvs.extend_from_slice(&other.uvs);
+        self.texture_indices.extend_from_slice(&other.texture_indices);
+        self.indices.extend(other.indices.iter().map(|i| i + base));
+    }
 }
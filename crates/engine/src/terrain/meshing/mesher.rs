@@ -0,0 +1,99 @@
+use bevy::ecs::resource::Resource;
+
+use crate::terrain::ecs::resources::voxel::VoxelRegistry;
+use crate::terrain::meshing::{
+    binary_greedy::binary_greedy_mesh,
+    greedy::greedy_mesh,
+    mesh_data::MeshData,
+    surface_nets::{surface_nets_mesh, ChunkNeighborhood},
+};
+
+/// Which mesher builds the chunks of a world. Insert it as a resource to switch,
+/// or use `PerBlock` to pick by the blocks' `smooth` flag.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MesherKind {
+    /// Reference greedy mesher
+    Greedy,
+    /// Same quads as `Greedy`, faster
+    #[default]
+    BinaryGreedy,
+    /// Smooth terrain, reads one voxel into the neighbour chunks
+    SurfaceNets,
+    /// `SurfaceNets` for the blocks marked smooth, `BinaryGreedy` quads for the others
+    PerBlock,
+}
+
+impl MesherKind {
+    /// Axis-aligned quads on voxel corners, which the packed vertex format can hold
+    pub fn is_blocky(self) -> bool {
+        matches!(self, MesherKind::Greedy | MesherKind::BinaryGreedy)
+    }
+
+    /// The mesh depends on the neighbour chunks, so they must be generated first
+    pub fn needs_neighbors(self) -> bool {
+        !self.is_blocky()
+    }
+}
+
+pub fn mesh_chunk(kind: MesherKind, neighborhood: &ChunkNeighborhood, registry: &VoxelRegistry) -> MeshData {
+    match kind {
+        MesherKind::Greedy => greedy_mesh(neighborhood.center(), registry),
+        MesherKind::BinaryGreedy => binary_greedy_mesh(neighborhood.center(), registry),
+        MesherKind::SurfaceNets => surface_nets_mesh(neighborhood, registry),
+        MesherKind::PerBlock => {
+            let mut mesh = binary_greedy_mesh(neighborhood.center(), &registry.solid_where(|block| !block.smooth));
+            let smooth = surface_nets_mesh(neighborhood, &registry.solid_where(|block| block.smooth));
+            mesh.append(&smooth, [0.0; 3]);
+            mesh
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::terrain::defs::voxel::VoxelDefinition;
+    use crate::terrain::ecs::components::chunk::ChunkData;
+    use crate::terrain::types::Voxel;
+
+    const DIRT: Voxel = Voxel(2);
+
+    fn registry(smooth_dirt: bool) -> VoxelRegistry {
+        let mut definitions: HashMap<_, _> = VoxelRegistry::default().definitions.as_ref().clone();
+        let dirt = VoxelDefinition { name: "Dirt".into(), smooth: smooth_dirt, ..definitions[&Voxel::STONE].clone() };
+        definitions.insert(DIRT, dirt);
+        VoxelRegistry { definitions: Arc::new(definitions) }
+    }
+
+    #[test]
+    fn per_block_splits_smooth_and_blocky_voxels() {
+        let mut chunk = ChunkData::new();
+        chunk.fill_layer_below(10, Voxel::STONE);
+        for y in 10..14 {
+            for z in 4..9 {
+                for x in 4..9 {
+                    chunk.set(x, y, z, DIRT);
+                }
+            }
+        }
+        let neighborhood = ChunkNeighborhood::new(&chunk);
+        let positions = |kind, registry: &VoxelRegistry| mesh_chunk(kind, &neighborhood, registry).positions;
+
+        // Nothing smooth: the greedy quads, then an empty smooth mesh
+        let blocky = registry(false);
+        assert_eq!(positions(MesherKind::PerBlock, &blocky), positions(MesherKind::BinaryGreedy, &blocky));
+
+        // Smooth dirt on cube stone: stone stays on the voxel grid, the dirt box gets rounded edges
+        let mesh = mesh_chunk(MesherKind::PerBlock, &neighborhood, &registry(true));
+        let on_grid = |p: &[f32; 3]| p.iter().all(|c| c.fract() == 0.0);
+        let of = |voxel: Voxel| {
+            let vertices = mesh.positions.iter().zip(&mesh.texture_indices);
+            vertices.filter(move |(_, id)| **id == voxel.id() as u32).map(|(p, _)| p)
+        };
+        assert!(of(Voxel::STONE).count() > 0 && of(Voxel::STONE).all(on_grid));
+        assert!(of(DIRT).any(|p| !on_grid(p)));
+    }
+}
@@ -0,0 +1,336 @@
+use crate::terrain::ecs::components::chunk::{ChunkData, ChunkLight};
+use crate::terrain::ecs::resources::voxel::VoxelRegistry;
+use crate::terrain::meshing::binary_greedy::SolidTable;
+use crate::terrain::meshing::mesh_data::MeshData;
+
+// Word 0: position and shading
+const X_BITS: u32 = 6;
+const Y_BITS: u32 = 8;
+const Z_BITS: u32 = 6;
+const NORMAL_BITS: u32 = 3;
+const AO_BITS: u32 = 2;
+const TEXTURED_BITS: u32 = 1;
+
+const X_SHIFT: u32 = 0;
+const Y_SHIFT: u32 = X_SHIFT + X_BITS;
+const Z_SHIFT: u32 = Y_SHIFT + Y_BITS;
+const NORMAL_SHIFT: u32 = Z_SHIFT + Z_BITS;
+const AO_SHIFT: u32 = NORMAL_SHIFT + NORMAL_BITS;
+const TEXTURED_SHIFT: u32 = AO_SHIFT + AO_BITS;
+
+// Word 1: texturing and light
+const UV_BITS: u32 = 8;
+const LAYER_BITS: u32 = 8;
+const LIGHT_BITS: u32 = 4;
+
+const U_SHIFT: u32 = 0;
+const V_SHIFT: u32 = U_SHIFT + UV_BITS;
+const LAYER_SHIFT: u32 = V_SHIFT + UV_BITS;
+const SKY_SHIFT: u32 = LAYER_SHIFT + LAYER_BITS;
+const BLOCK_SHIFT: u32 = SKY_SHIFT + LIGHT_BITS;
+
+const _: () = assert!(TEXTURED_SHIFT + TEXTURED_BITS <= 32 && BLOCK_SHIFT + LIGHT_BITS <= 32);
+
+/// Face normals by index, as stored in the packed vertex (and decoded by terrain.wgsl)
+pub const NORMALS: [[f32; 3]; 6] = [
+    [1.0, 0.0, 0.0],
+    [-1.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0],
+    [0.0, -1.0, 0.0],
+    [0.0, 0.0, 1.0],
+    [0.0, 0.0, -1.0],
+];
+
+/// Index into `NORMALS` for an axis-aligned normal
+pub fn normal_index(normal: [f32; 3]) -> u8 {
+    let axis = (0..3)
+        .max_by(|&a, &b| normal[a].abs().total_cmp(&normal[b].abs()))
+        .unwrap_or(0);
+    (axis * 2 + (normal[axis] < 0.0) as usize) as u8
+}
+
+/// Vertex fields before packing. Positions are chunk-local corners (0..=CHUNK_*),
+/// uv is the quad-local tile coordinate (0..=quad size).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct VertexFields {
+    pub position: [u32; 3],
+    pub normal: u8,
+    /// 0 = fully occluded, 3 = open
+    pub ao: u8,
+    pub uv: [u32; 2],
+    /// Texture array layer when `textured`, otherwise the block ID the palette colors
+    pub layer: u8,
+    pub textured: bool,
+    pub sky_light: u8,
+    pub block_light: u8,
+}
+
+#[inline]
+fn field(value: u32, bits: u32, shift: u32) -> u32 {
+    debug_assert!(value < (1 << bits), "{value} does not fit in {bits} bits");
+    (value & ((1 << bits) - 1)) << shift
+}
+
+#[inline]
+fn read(word: u32, bits: u32, shift: u32) -> u32 {
+    (word >> shift) & ((1 << bits) - 1)
+}
+
+/// A terrain vertex in 8 bytes instead of the 32 of `MeshData`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PackedVertex(pub [u32; 2]);
+
+impl PackedVertex {
+    pub fn pack(v: &VertexFields) -> Self {
+        let lo = field(v.position[0], X_BITS, X_SHIFT)
+            | field(v.position[1], Y_BITS, Y_SHIFT)
+            | field(v.position[2], Z_BITS, Z_SHIFT)
+            | field(v.normal as u32, NORMAL_BITS, NORMAL_SHIFT)
+            | field(v.ao as u32, AO_BITS, AO_SHIFT)
+            | field(v.textured as u32, TEXTURED_BITS, TEXTURED_SHIFT);
+        let hi = field(v.uv[0], UV_BITS, U_SHIFT)
+            | field(v.uv[1], UV_BITS, V_SHIFT)
+            | field(v.layer as u32, LAYER_BITS, LAYER_SHIFT)
+            | field(v.sky_light as u32, LIGHT_BITS, SKY_SHIFT)
+            | field(v.block_light as u32, LIGHT_BITS, BLOCK_SHIFT);
+        Self([lo, hi])
+    }
+
+    pub fn unpack(self) -> VertexFields {
+        let [lo, hi] = self.0;
+        VertexFields {
+            position: [
+                read(lo, X_BITS, X_SHIFT),
+                read(lo, Y_BITS, Y_SHIFT),
+                read(lo, Z_BITS, Z_SHIFT),
+            ],
+            normal: read(lo, NORMAL_BITS, NORMAL_SHIFT) as u8,
+            ao: read(lo, AO_BITS, AO_SHIFT) as u8,
+            textured: read(lo, TEXTURED_BITS, TEXTURED_SHIFT) != 0,
+            uv: [read(hi, UV_BITS, U_SHIFT), read(hi, UV_BITS, V_SHIFT)],
+            layer: read(hi, LAYER_BITS, LAYER_SHIFT) as u8,
+            sky_light: read(hi, LIGHT_BITS, SKY_SHIFT) as u8,
+            block_light: read(hi, LIGHT_BITS, BLOCK_SHIFT) as u8,
+        }
+    }
+}
+
+/// Texture array layer of every face by block ID, so packing never hashes.
+/// Layers past the 8 bits of the packed vertex are left to the palette.
+pub struct FaceTextures([Option<[u8; 6]>; 256]);
+
+impl FaceTextures {
+    pub fn new(registry: &VoxelRegistry) -> Self {
+        let mut table = [None; 256];
+        for (voxel, definition) in registry.definitions.iter() {
+            let layers = definition.texture_layers.filter(|layers| layers.iter().all(|&l| l <= u8::MAX as u32));
+            table[voxel.id() as usize] = layers.map(|layers| layers.map(|layer| layer as u8));
+        }
+        Self(table)
+    }
+
+    /// Layer of the `face` (index into `NORMALS`) of block `id`, None when untextured
+    #[inline]
+    pub fn layer(&self, id: u8, face: u8) -> Option<u8> {
+        self.0[id as usize].map(|layers| layers[face as usize])
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct PackedMeshData {
+    pub vertices: Vec<[u32; 2]>,
+    pub indices: Vec<u32>,
+}
+
+/// Classic 3-neighbour corner occlusion
+#[inline]
+fn vertex_ao(side1: bool, side2: bool, corner: bool) -> u8 {
+    if side1 && side2 {
+        0
+    } else {
+        3 - (side1 as u8 + side2 as u8 + corner as u8)
+    }
+}
+
+/// Packs quad-based `MeshData` (4 vertices per quad, as emitted by the greedy meshers).
+/// Light and AO are sampled from the voxels in front of each corner, block IDs become
+/// the texture layer of the face when the block has one.
+pub fn pack_mesh(
+    mesh: &MeshData,
+    chunk: &ChunkData,
+    light: &ChunkLight,
+    solid: &SolidTable,
+    textures: &FaceTextures,
+) -> PackedMeshData {
+    let is_solid = |p: [i32; 3]| solid.is_solid(chunk.get(p[0], p[1], p[2]).id());
+    let mut vertices = Vec::with_capacity(mesh.positions.len());
+
+    for (quad, corners) in mesh.positions.chunks_exact(4).enumerate() {
+        let first = quad * 4;
+        let normal = mesh.normals[first];
+        let axis = normal_index(normal) as usize / 2;
+        let sign = if normal[axis] > 0.0 { 1.0 } else { -1.0 };
+        let block = mesh.texture_indices[first] as u8;
+        let texture = textures.layer(block, normal_index(normal));
+
+        let mut center = [0.0f32; 3];
+        for corner in corners {
+            for k in 0..3 {
+                center[k] += corner[k] / 4.0;
+            }
+        }
+
+        for (n, corner) in corners.iter().enumerate() {
+            // Voxel in front of the face, on the quad side of this corner
+            let mut front = [0i32; 3];
+            let mut toward = [0i32; 3];
+            for k in 0..3 {
+                if k == axis {
+                    front[k] = (corner[k] + 0.5 * sign).floor() as i32;
+                } else {
+                    toward[k] = if center[k] > corner[k] { 1 } else { -1 };
+                    front[k] = (corner[k] + 0.5 * toward[k] as f32).floor() as i32;
+                }
+            }
+
+            let (t1, t2) = ((axis + 1) % 3, (axis + 2) % 3);
+            let mut side1 = front;
+            side1[t1] -= toward[t1];
+            let mut side2 = front;
+            side2[t2] -= toward[t2];
+            let mut corner_voxel = front;
+            corner_voxel[t1] -= toward[t1];
+            corner_voxel[t2] -= toward[t2];
+
+            let uv = mesh.uvs[first + n];
+            vertices.push(
+                PackedVertex::pack(&VertexFields {
+                    position: corner.map(|c| c as u32),
+                    normal: normal_index(normal),
+                    ao: vertex_ao(is_solid(side1), is_solid(side2), is_solid(corner_voxel)),
+                    uv: uv.map(|c| c as u32),
+                    layer: texture.unwrap_or(block),
+                    textured: texture.is_some(),
+                    sky_light: light.get(front[0], front[1], front[2]),
+                    block_light: 0,
+                })
+                .0,
+            );
+        }
+    }
+
+    PackedMeshData {
+        vertices,
+        indices: mesh.indices.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::terrain::{
+        constants::{CHUNK_DEPTH, CHUNK_HEIGHT, CHUNK_WIDTH},
+        lighting::compute_skylight,
+        meshing::greedy::greedy_mesh,
+        types::Voxel,
+    };
+
+    #[test]
+    fn pack_unpack_round_trip() {
+        let fields = [
+            VertexFields::default(),
+            VertexFields {
+                position: [CHUNK_WIDTH as u32, CHUNK_HEIGHT as u32, CHUNK_DEPTH as u32],
+                normal: 5,
+                ao: 3,
+                uv: [128, 128],
+                layer: 255,
+                textured: true,
+                sky_light: 15,
+                block_light: 15,
+            },
+            VertexFields {
+                position: [3, 77, 9],
+                normal: 2,
+                ao: 1,
+                uv: [4, 1],
+                layer: 7,
+                textured: false,
+                sky_light: 9,
+                block_light: 2,
+            },
+        ];
+        for f in fields {
+            assert_eq!(PackedVertex::pack(&f).unpack(), f);
+        }
+    }
+
+    #[test]
+    fn normal_indices_round_trip() {
+        for (i, normal) in NORMALS.iter().enumerate() {
+            assert_eq!(normal_index(*normal) as usize, i);
+        }
+    }
+
+    #[test]
+    fn packed_mesh_matches_mesh_data() {
+        let registry = VoxelRegistry::default();
+        let mut chunk = ChunkData::new();
+        chunk.fill_layer_below(10, Voxel::STONE);
+        chunk.set(4, 10, 4, Voxel::STONE);
+
+        let mesh = greedy_mesh(&chunk, &registry);
+        let light = compute_skylight(&chunk, &registry);
+        let solid = SolidTable::new(&registry);
+        let packed = pack_mesh(&mesh, &chunk, &light, &solid, &FaceTextures::new(&registry));
+
+        assert_eq!(packed.vertices.len(), mesh.positions.len());
+        assert_eq!(packed.indices, mesh.indices);
+        for (i, v) in packed.vertices.iter().enumerate() {
+            let f = PackedVertex(*v).unpack();
+            assert_eq!(f.position.map(|c| c as f32), mesh.positions[i]);
+            assert_eq!(NORMALS[f.normal as usize], mesh.normals[i]);
+            assert_eq!(f.uv.map(|c| c as f32), mesh.uvs[i]);
+            assert_eq!(f.layer as u32, mesh.texture_indices[i]);
+            assert!(!f.textured);
+        }
+
+        // Top of the floor is open sky, next to the bump it is occluded
+        let top = |x: u32, z: u32| {
+            packed
+                .vertices
+                .iter()
+                .map(|v| PackedVertex(*v).unpack())
+                .filter(move |f| f.normal == 2 && f.position == [x, 10, z])
+        };
+        assert!(top(0, 0).all(|f| f.sky_light == 15 && f.ao == 3));
+        assert!(top(4, 4).any(|f| f.ao < 3));
+    }
+
+    #[test]
+    fn textured_faces_pack_their_layer() {
+        let mut definitions = VoxelRegistry::default().definitions.as_ref().clone();
+        definitions.get_mut(&Voxel::STONE).unwrap().texture_layers = Some([3, 3, 1, 2, 3, 3]);
+        let registry = VoxelRegistry { definitions: Arc::new(definitions) };
+        let mut chunk = ChunkData::new();
+        chunk.fill_layer_below(10, Voxel::STONE);
+        chunk.set(4, 10, 4, Voxel::STONE);
+
+        let mesh = greedy_mesh(&chunk, &registry);
+        let light = compute_skylight(&chunk, &registry);
+        let solid = SolidTable::new(&registry);
+        let packed = pack_mesh(&mesh, &chunk, &light, &solid, &FaceTextures::new(&registry));
+
+        for f in packed.vertices.iter().map(|v| PackedVertex(*v).unpack()) {
+            let expected = match f.normal {
+                2 => 1,
+                3 => 2,
+                _ => 3,
+            };
+            assert!(f.textured);
+            assert_eq!(f.layer, expected);
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use bevy::math::IVec2;
+
+use crate::terrain::constants::{CHUNK_DEPTH, CHUNK_HEIGHT, CHUNK_WIDTH};
+use crate::terrain::ecs::components::chunk::ChunkData;
+use crate::terrain::ecs::resources::voxel::VoxelRegistry;
+use crate::terrain::meshing::binary_greedy::SolidTable;
+use crate::terrain::meshing::mesh_data::MeshData;
+use crate::terrain::types::Voxel;
+
+/// A chunk plus its 8 horizontal neighbours. Smooth meshers sample one voxel
+/// past the chunk border, missing neighbours read as Air, which closes the
+/// surface along the edge of the loaded area.
+pub struct ChunkNeighborhood<'a> {
+    chunks: [Option<&'a ChunkData>; 9],
+}
+
+impl<'a> ChunkNeighborhood<'a> {
+    /// Offsets of the neighbours, in the order `from_fn` asks for them
+    pub const OFFSETS: [IVec2; 9] = [
+        IVec2::new(-1, -1),
+        IVec2::new(0, -1),
+        IVec2::new(1, -1),
+        IVec2::new(-1, 0),
+        IVec2::new(0, 0),
+        IVec2::new(1, 0),
+        IVec2::new(-1, 1),
+        IVec2::new(0, 1),
+        IVec2::new(1, 1),
+    ];
+
+    pub fn new(center: &'a ChunkData) -> Self {
+        let mut chunks = [None; 9];
+        chunks[4] = Some(center);
+        Self { chunks }
+    }
+
+    /// `neighbour(offset)` is called for every entry of `OFFSETS`, (0, 0) being the center
+    pub fn from_fn(mut neighbour: impl FnMut(IVec2) -> Option<&'a ChunkData>) -> Self {
+        Self {
+            chunks: Self::OFFSETS.map(&mut neighbour),
+        }
+    }
+
+    pub fn center(&self) -> &'a ChunkData {
+        self.chunks[4].expect("ChunkNeighborhood without a center chunk")
+    }
+
+    /// Voxel at chunk-local coordinates, x and z may be one chunk off in either direction
+    #[inline]
+    pub fn get(&self, x: i32, y: i32, z: i32) -> Voxel {
+        let cx = x.div_euclid(CHUNK_WIDTH);
+        let cz = z.div_euclid(CHUNK_DEPTH);
+        if !(-1..=1).contains(&cx) || !(-1..=1).contains(&cz) {
+            return Voxel::AIR;
+        }
+        match self.chunks[((cx + 1) + (cz + 1) * 3) as usize] {
+            Some(chunk) => chunk.get(x.rem_euclid(CHUNK_WIDTH), y, z.rem_euclid(CHUNK_DEPTH)),
+            None => Voxel::AIR,
+        }
+    }
+}
+
+/// Cells span 2x2x2 voxel centers. Cell `c` has its min corner on voxel `c`,
+/// cells run from -1 to the chunk size (inclusive of -1) on every axis.
+const CELLS: [i32; 3] = [CHUNK_WIDTH + 1, CHUNK_HEIGHT + 1, CHUNK_DEPTH + 1];
+
+#[inline]
+fn cell_index(c: [i32; 3]) -> usize {
+    ((c[0] + 1) + (c[2] + 1) * CELLS[0] + (c[1] + 1) * CELLS[0] * CELLS[2]) as usize
+}
+
+/// Naive surface nets over the solid/empty field, with smooth gradient normals.
+///
+/// A chunk owns the edges starting at its own voxels, so two chunks never emit the same
+/// quad, and the vertices along a border are computed from the same samples on both sides.
+/// Produces `MeshData` for `meshdata_to_bevy_mesh`, like the greedy meshers.
+pub fn surface_nets_mesh(neighborhood: &ChunkNeighborhood, registry: &VoxelRegistry) -> MeshData {
+    let solid = SolidTable::new(registry);
+    let sample = |p: [i32; 3]| -> f32 {
+        if solid.is_solid(neighborhood.get(p[0], p[1], p[2]).id()) {
+            1.0
+        } else {
+            -1.0
+        }
+    };
+
+    let mut out = MeshData::new();
+    let mut cell_vertex = vec![u32::MAX; (CELLS[0] * CELLS[1] * CELLS[2]) as usize];
+
+    // 1. One vertex per cell crossed by the surface
+    for cy in -1..CHUNK_HEIGHT {
+        for cz in -1..CHUNK_DEPTH {
+            for cx in -1..CHUNK_WIDTH {
+                let mut corners = [0.0f32; 8];
+                for (n, corner) in corners.iter_mut().enumerate() {
+                    *corner = sample([
+                        cx + (n & 1) as i32,
+                        cy + ((n >> 1) & 1) as i32,
+                        cz + ((n >> 2) & 1) as i32,
+                    ]);
+                }
+
+                let inside = corners.iter().filter(|d| **d > 0.0).count();
+                if inside == 0 || inside == 8 {
+                    continue;
+                }
+
+                // Average of the edge crossings
+                let mut sum = [0.0f32; 3];
+                let mut crossings = 0.0;
+                for a in 0..8usize {
+                    for axis in 0..3 {
+                        let b = a | (1 << axis);
+                        if b == a || (corners[a] > 0.0) == (corners[b] > 0.0) {
+                            continue;
+                        }
+                        let t = corners[a] / (corners[a] - corners[b]);
+                        for (k, component) in sum.iter_mut().enumerate() {
+                            let from = ((a >> k) & 1) as f32;
+                            let to = ((b >> k) & 1) as f32;
+                            *component += from + (to - from) * t;
+                        }
+                        crossings += 1.0;
+                    }
+                }
+
+                // Gradient of the trilinear field, negated so it points out of the solid
+                let mut normal = [0.0f32; 3];
+                for (n, d) in corners.iter().enumerate() {
+                    for (k, component) in normal.iter_mut().enumerate() {
+                        let sign = if (n >> k) & 1 == 1 { -1.0 } else { 1.0 };
+                        *component += sign * d;
+                    }
+                }
+                let len = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+                if len > f32::EPSILON {
+                    normal = normal.map(|c| c / len);
+                }
+
+                // Samples sit at voxel centers
+                let position = [
+                    cx as f32 + 0.5 + sum[0] / crossings,
+                    cy as f32 + 0.5 + sum[1] / crossings,
+                    cz as f32 + 0.5 + sum[2] / crossings,
+                ];
+
+                cell_vertex[cell_index([cx, cy, cz])] = out.positions.len() as u32;
+                out.positions.push(position);
+                out.normals.push(normal);
+                out.uvs.push(planar_uv(position, normal));
+                out.texture_indices.push(0);
+            }
+        }
+    }
+
+    // 2. One quad per owned edge with a sign change, joining the 4 cells around it
+    for y in -1..CHUNK_HEIGHT {
+        for z in 0..CHUNK_DEPTH {
+            for x in 0..CHUNK_WIDTH {
+                let p = [x, y, z];
+                let inside = sample(p) > 0.0;
+
+                for axis in 0..3 {
+                    let mut q = p;
+                    q[axis] += 1;
+                    if inside == (sample(q) > 0.0) {
+                        continue;
+                    }
+
+                    let b = (axis + 1) % 3;
+                    let c = (axis + 2) % 3;
+                    let mut quad = [u32::MAX; 4];
+                    for (n, (db, dc)) in [(1, 1), (0, 1), (0, 0), (1, 0)].into_iter().enumerate() {
+                        let mut cell = p;
+                        cell[b] -= db;
+                        cell[c] -= dc;
+                        if cell.iter().enumerate().any(|(k, v)| *v < -1 || *v >= CELLS[k] - 1) {
+                            break;
+                        }
+                        quad[n] = cell_vertex[cell_index(cell)];
+                    }
+                    if quad.contains(&u32::MAX) {
+                        continue;
+                    }
+
+                    let voxel = if inside {
+                        neighborhood.get(p[0], p[1], p[2])
+                    } else {
+                        neighborhood.get(q[0], q[1], q[2])
+                    };
+                    for &vertex in &quad {
+                        out.texture_indices[vertex as usize] = voxel.id() as u32;
+                    }
+
+                    // Counter-clockwise seen from the empty side
+                    if inside {
+                        out.indices.extend_from_slice(&[quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]]);
+                    } else {
+                        out.indices.extend_from_slice(&[quad[0], quad[2], quad[1], quad[0], quad[3], quad[2]]);
+                    }
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// Triplanar-style projection on the dominant normal axis
+fn planar_uv(position: [f32; 3], normal: [f32; 3]) -> [f32; 2] {
+    let [nx, ny, nz] = normal.map(f32::abs);
+    if ny >= nx && ny >= nz {
+        [position[0], position[2]]
+    } else if nx >= nz {
+        [position[2], position[1]]
+    } else {
+        [position[0], position[1]]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// Chunk with the voxels of a world-space sphere, for the chunk at `coords`
+    fn sphere_chunk(coords: IVec2, center: [f32; 3], radius: f32) -> ChunkData {
+        let mut chunk = ChunkData::new();
+        for y in 0..CHUNK_HEIGHT {
+            for z in 0..CHUNK_DEPTH {
+                for x in 0..CHUNK_WIDTH {
+                    let world = [
+                        (x + coords.x * CHUNK_WIDTH) as f32 + 0.5,
+                        y as f32 + 0.5,
+                        (z + coords.y * CHUNK_DEPTH) as f32 + 0.5,
+                    ];
+                    let d2: f32 = (0..3).map(|k| (world[k] - center[k]).powi(2)).sum();
+                    if d2 < radius * radius {
+                        chunk.set(x, y, z, Voxel::STONE);
+                    }
+                }
+            }
+        }
+        chunk
+    }
+
+    /// Merges vertices by position and counts how many triangles use each edge.
+    /// A closed surface uses every edge exactly twice, in opposite directions.
+    fn assert_watertight(meshes: &[(MeshData, [f32; 3])]) {
+        let mut welded: HashMap<[i64; 3], usize> = HashMap::new();
+        let mut edges: HashMap<(usize, usize), i32> = HashMap::new();
+
+        for (mesh, offset) in meshes {
+            let ids: Vec<usize> = mesh
+                .positions
+                .iter()
+                .map(|p| {
+                    let key = [0, 1, 2].map(|k| ((p[k] + offset[k]) * 1000.0).round() as i64);
+                    let next = welded.len();
+                    *welded.entry(key).or_insert(next)
+                })
+                .collect();
+
+            for tri in mesh.indices.chunks_exact(3) {
+                for n in 0..3 {
+                    let a = ids[tri[n] as usize];
+                    let b = ids[tri[(n + 1) % 3] as usize];
+                    assert_ne!(a, b, "degenerate triangle");
+                    // +1 for a -> b, -1 for b -> a
+                    *edges.entry((a.min(b), a.max(b))).or_default() += if a < b { 1 } else { -1 };
+                }
+            }
+        }
+
+        assert!(!edges.is_empty());
+        for (edge, balance) in edges {
+            assert_eq!(balance, 0, "edge {edge:?} is not shared by two opposite triangles");
+        }
+    }
+
+    #[test]
+    fn blob_inside_a_chunk_is_closed() {
+        let registry = VoxelRegistry::default();
+        let chunk = sphere_chunk(IVec2::ZERO, [8.0, 40.0, 8.0], 5.0);
+        let mesh = surface_nets_mesh(&ChunkNeighborhood::new(&chunk), &registry);
+
+        assert_eq!(mesh.positions.len(), mesh.normals.len());
+        assert_eq!(mesh.positions.len(), mesh.texture_indices.len());
+        assert_watertight(&[(mesh, [0.0; 3])]);
+    }
+
+    #[test]
+    fn blob_across_chunk_border_has_no_seam() {
+        let registry = VoxelRegistry::default();
+        let coords = [IVec2::new(0, 0), IVec2::new(1, 0), IVec2::new(0, 1), IVec2::new(1, 1)];
+        let chunks: HashMap<IVec2, ChunkData> = coords
+            .iter()
+            .map(|c| (*c, sphere_chunk(*c, [16.0, 40.0, 16.0], 6.5)))
+            .collect();
+
+        let meshes: Vec<(MeshData, [f32; 3])> = coords
+            .iter()
+            .map(|c| {
+                let neighborhood = ChunkNeighborhood::from_fn(|offset| chunks.get(&(*c + offset)));
+                let offset = [(c.x * CHUNK_WIDTH) as f32, 0.0, (c.y * CHUNK_DEPTH) as f32];
+                (surface_nets_mesh(&neighborhood, &registry), offset)
+            })
+            .collect();
+
+        assert!(meshes.iter().all(|(mesh, _)| !mesh.indices.is_empty()));
+        assert_watertight(&meshes);
+    }
+}
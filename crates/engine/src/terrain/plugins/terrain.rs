@@ -1,50 +1,261 @@
 use bevy::app::{App, Plugin};
-use std::collections::HashMap;
-use std::thread;
+use bevy::asset::io::file::FileAssetReader;
+use bevy::asset::AssetPlugin;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use bevy::prelude::*;
 
-use crate::terrain::ecs::resources::voxel::VoxelRegistry;
+use assets::blocks::BlockManifest;
+use assets::AssetsPlugin;
 
+use crate::terrain::config::WorldConfig;
+use crate::terrain::ecs::resources::{chunk::ChunkMap, voxel::VoxelRegistry};
+use crate::terrain::generator::{ChunkGenerator, ChunkLoading, TerrainGenerator, TerrainManager};
+use crate::terrain::hot_reload::{BiomeTableHandle, BlockManifestHandle, BlockReload, StructureHandles, WorldgenReload};
+use crate::terrain::meshing::mesher::MesherKind;
+use crate::terrain::render::{TerrainMaterialPlugin, TerrainTextures};
+use crate::terrain::stats::TerrainStats;
 use crate::terrain::tasks::TerrainTask;
-use crate::terrain::{defs::voxel::VoxelDefinition, types::Voxel};
-use crate::terrain::generator::TerrainManager;
 
-pub struct TerrainPlugin;
+/// Output of `asset_compiler`, relative to the asset folder
+pub const BLOCK_MANIFEST_PATH: &str = "compiled/blocks.manifest.ron";
 
-impl Plugin for TerrainPlugin {
-    fn build(&self, app: &mut App) {
-        let mut definitions = HashMap::new();
+/// Where the block palette comes from
+#[derive(Clone)]
+pub enum BlockSource {
+    /// Air and Stone
+    BuiltIn,
+    /// A compiled block manifest relative to the asset folder, hot reloaded when the asset
+    /// server is watching. Falls back to the built-in blocks when it is missing or invalid.
+    Manifest(PathBuf),
+    /// A registry built by the game
+    Registry(VoxelRegistry),
+}
+
+impl Default for BlockSource {
+    fn default() -> Self {
+        BlockSource::Manifest(BLOCK_MANIFEST_PATH.into())
+    }
+}
+
+impl BlockSource {
+    fn registry(&self, asset_root: &Path) -> VoxelRegistry {
+        match self {
+            BlockSource::BuiltIn => VoxelRegistry::default(),
+            BlockSource::Manifest(path) if asset_root.join(path).exists() => {
+                VoxelRegistry::load_manifest(&asset_root.join(path)).unwrap_or_else(|e| {
+                    error!("{e}, falling back to the built-in blocks");
+                    VoxelRegistry::default()
+                })
+            }
+            BlockSource::Manifest(_) => VoxelRegistry::default(),
+            BlockSource::Registry(registry) => registry.clone(),
+        }
+    }
+
+    /// Texture array next to the manifest, when its blocks have textures
+    fn texture_array(&self, asset_root: &Path) -> Option<PathBuf> {
+        let BlockSource::Manifest(path) = self else {
+            return None;
+        };
+        let path = asset_root.join(path);
+        let manifest = BlockManifest::load(&path).ok().filter(|manifest| manifest.layers > 0)?;
+        Some(path.with_file_name(manifest.texture_array))
+    }
+}
+
+/// The folder the asset server reads from: `AssetPlugin::file_path` under Bevy's base path.
+/// Files the plugin reads at startup are resolved here too, so they are the ones it watches.
+pub fn asset_root(app: &App) -> PathBuf {
+    match app.get_added_plugins::<AssetPlugin>().first() {
+        Some(plugin) => FileAssetReader::get_base_path().join(&plugin.file_path),
+        None => default_asset_root(),
+    }
+}
+
+/// `asset_root` with the default `AssetPlugin`, for generators built before the app
+pub fn default_asset_root() -> PathBuf {
+    FileAssetReader::get_base_path().join(AssetPlugin::default().file_path)
+}
 
-        // Register your "Palette" here
-        definitions.insert(Voxel::Air, VoxelDefinition {
-            name: "Air",
-            is_solid: false,
-        });
+/// How chunk meshes are drawn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TerrainMaterialKind {
+    /// Packed vertices with `TerrainMaterial`
+    #[default]
+    Packed,
+    /// Full vertices, unlit red `StandardMaterial`
+    Debug,
+}
 
-        definitions.insert(Voxel::Solid, VoxelDefinition {
-            name: "Stone",
-            is_solid: true,
-        });
+/// Voxel terrain: block registry, chunk pipeline and rendering, configured with a builder.
+///
+/// ```ignore
+/// app.add_plugins(
+///     VoxelTerrainPlugin::new(WorldConfig { seed: 7, ..default() })
+///         .with_mesher(MesherKind::SurfaceNets)
+///         .without_rendering(),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct VoxelTerrainPlugin {
+    config: Option<WorldConfig>,
+    blocks: BlockSource,
+    generator: Option<Arc<dyn TerrainGenerator>>,
+    mesher: MesherKind,
+    material: TerrainMaterialKind,
+    loading: ChunkLoading,
+    rendering: bool,
+}
 
-        app.insert_resource(VoxelRegistry { definitions: definitions.into() });
+impl Default for VoxelTerrainPlugin {
+    /// Uses the `WorldConfig` resource inserted by the app, or the default one
+    fn default() -> Self {
+        Self {
+            config: None,
+            blocks: BlockSource::default(),
+            generator: None,
+            mesher: MesherKind::default(),
+            material: TerrainMaterialKind::default(),
+            loading: ChunkLoading::default(),
+            rendering: true,
+        }
     }
 }
 
-pub struct TerrainTaskPlugin;
+impl VoxelTerrainPlugin {
+    pub fn new(config: WorldConfig) -> Self {
+        Self {
+            config: Some(config),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_blocks(mut self, blocks: BlockSource) -> Self {
+        self.blocks = blocks;
+        self
+    }
+
+    /// Replaces the generator built from `WorldConfig::generator`
+    pub fn with_generator(mut self, generator: impl TerrainGenerator) -> Self {
+        self.generator = Some(Arc::new(generator));
+        self
+    }
 
-impl Plugin for TerrainTaskPlugin {
+    pub fn with_mesher(mut self, mesher: MesherKind) -> Self {
+        self.mesher = mesher;
+        self
+    }
+
+    pub fn with_material(mut self, material: TerrainMaterialKind) -> Self {
+        self.material = material;
+        self
+    }
+
+    pub fn with_loading(mut self, loading: ChunkLoading) -> Self {
+        self.loading = loading;
+        self
+    }
+
+    /// Generates and lights chunks without meshing or drawing them, for servers and tests.
+    /// Runs under `MinimalPlugins`.
+    pub fn without_rendering(mut self) -> Self {
+        self.rendering = false;
+        self
+    }
+
+    pub fn rendering(&self) -> bool {
+        self.rendering
+    }
+}
+
+impl Plugin for VoxelTerrainPlugin {
     fn build(&self, app: &mut App) {
-        // 1. Create the generator with a seed
-        let manager = TerrainManager::new(64, (thread::available_parallelism().unwrap().get() / 2) as usize, 42); 
+        // 1. World settings, the app may have inserted its own already
+        match &self.config {
+            Some(config) => app.insert_resource(config.clone()),
+            None => app.init_resource::<WorldConfig>(),
+        };
+        let config = app.world().resource::<WorldConfig>().clone();
 
-        // 2. Insert it as a resource so systems can find it
+        // 2. Blocks, hot reloaded from the manifest when assets are available
+        let root = asset_root(app);
+        app.insert_resource(self.blocks.registry(&root));
+        if let Some(path) = self.blocks.texture_array(&root) {
+            app.insert_resource(TerrainTextures(path));
+        }
+        if app.is_plugin_added::<AssetPlugin>() {
+            app.add_plugins(AssetsPlugin);
+            app.add_systems(Update, BlockReload::apply_manifest);
+            if self.generator.is_none() {
+                app.add_systems(Update, (WorldgenReload::apply_biomes, WorldgenReload::apply_structures));
+            }
+        }
+
+        // 3. Generator and scheduling
+        let mut manager = TerrainManager::for_world(&config, &root);
+        manager.config.loading = self.loading;
+        let generator = self
+            .generator
+            .clone()
+            .unwrap_or_else(|| Arc::new(manager.clone()));
         app.insert_resource(manager);
-        // 3. Register your systems
+        app.insert_resource(ChunkGenerator(generator));
+        app.insert_resource(self.mesher);
+        app.init_resource::<ChunkMap>();
+        app.init_resource::<TerrainStats>();
+
+        // 4. One system per chunk stage, up to lighting. Meshing lives in TerrainRenderPlugin.
         app.add_systems(Update, (
             TerrainTask::queue,
-            TerrainTask::process,
-        ));
+            TerrainTask::poll_generation,
+            TerrainTask::decorate,
+            TerrainTask::rewind_dirty,
+            TerrainTask::light,
+        ).chain());
+        if self.rendering {
+            app.add_plugins(TerrainRenderPlugin { material: self.material });
+        }
     }
+
+    fn finish(&self, app: &mut App) {
+        // Keeps the worldgen assets loaded so edits reach the reload systems
+        let Some(asset_server) = app.world().get_resource::<AssetServer>().cloned() else {
+            return;
+        };
+        let root = asset_root(app);
+        if let BlockSource::Manifest(path) = &self.blocks {
+            if root.join(path).exists() {
+                app.insert_resource(BlockManifestHandle(asset_server.load(path.clone())));
+            }
+        }
+        let biomes = app.world().resource::<WorldConfig>().biomes.clone();
+        if let Some(path) = biomes.filter(|path| root.join(path).exists()) {
+            app.insert_resource(BiomeTableHandle(asset_server.load(path)));
+        }
+        let structures = app.world().resource::<WorldConfig>().structures.clone();
+        let handles = structures.into_iter().filter(|path| root.join(path).exists());
+        app.insert_resource(StructureHandles(handles.map(|path| asset_server.load(path)).collect()));
+    }
+}
+
+/// Meshing and upload of lit chunks. Needs the render stack (`Assets<Mesh>`, materials).
+/// Added by `VoxelTerrainPlugin` unless rendering is turned off.
+pub struct TerrainRenderPlugin {
+    pub material: TerrainMaterialKind,
 }
 
+impl Plugin for TerrainRenderPlugin {
+    fn build(&self, app: &mut App) {
+        if self.material == TerrainMaterialKind::Packed {
+            app.add_plugins(TerrainMaterialPlugin);
+        }
+        app.add_systems(Update, (
+            TerrainTask::fill_missing_neighbours,
+            TerrainTask::mesh,
+            TerrainTask::poll_meshing,
+            TerrainTask::upload,
+        ).chain().after(TerrainTask::light));
+    }
+}
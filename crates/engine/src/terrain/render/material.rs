@@ -0,0 +1,213 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use assets::blocks::BlockManifest;
+use assets::texture_array::TextureArray;
+use assets::AssetsPlugin;
+use bevy::{
+    asset::{embedded_asset, RenderAssetUsages},
+    image::{ImageAddressMode, ImageFilterMode, ImageSampler, ImageSamplerDescriptor},
+    math::Vec4,
+    mesh::{MeshVertexAttribute, MeshVertexBufferLayoutRef},
+    pbr::{MaterialPipeline, MaterialPipelineKey},
+    prelude::*,
+    reflect::TypePath,
+    render::render_resource::{
+        AsBindGroup, Extent3d, RenderPipelineDescriptor, ShaderType, SpecializedMeshPipelineError,
+        TextureDimension, TextureFormat, TextureViewDescriptor, TextureViewDimension, VertexFormat,
+    },
+    shader::ShaderRef,
+};
+
+const SHADER_PATH: &str = "embedded://engine/terrain/render/terrain.wgsl";
+
+/// The only vertex attribute of packed terrain meshes, see `meshing::packed`
+pub const ATTRIBUTE_PACKED_VERTEX: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_TerrainPacked", 2_091_504_771, VertexFormat::Uint32x2);
+
+pub const PALETTE_SIZE: usize = 16;
+
+#[derive(Clone, Copy, Debug, ShaderType)]
+pub struct TerrainParams {
+    /// Base color of untextured blocks (block ID % PALETTE_SIZE)
+    pub palette: [Vec4; PALETTE_SIZE],
+    /// Scales the sky light baked in the vertices (1.0 = noon)
+    pub sky_intensity: f32,
+    /// Minimum light, so caves are not pitch black
+    pub ambient: f32,
+}
+
+impl Default for TerrainParams {
+    fn default() -> Self {
+        let mut palette = [Vec4::new(0.6, 0.6, 0.6, 1.0); PALETTE_SIZE];
+        palette[1] = Vec4::new(0.5, 0.5, 0.52, 1.0); // Stone
+        Self {
+            palette,
+            sky_intensity: 1.0,
+            ambient: 0.15,
+        }
+    }
+}
+
+/// Unlit voxel material reading `ATTRIBUTE_PACKED_VERTEX`.
+/// Does not take part in the prepass nor in shadow maps.
+#[derive(Asset, TypePath, AsBindGroup, Clone, Debug, Default)]
+pub struct TerrainMaterial {
+    #[uniform(0)]
+    pub params: TerrainParams,
+    /// Block texture array, sampled by faces packed as textured
+    #[texture(1, dimension = "2d_array")]
+    #[sampler(2)]
+    pub textures: Option<Handle<Image>>,
+}
+
+impl Material for TerrainMaterial {
+    fn vertex_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+
+    fn enable_prepass() -> bool {
+        false
+    }
+
+    fn enable_shadows() -> bool {
+        false
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayoutRef,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let vertex_layout = layout
+            .0
+            .get_layout(&[ATTRIBUTE_PACKED_VERTEX.at_shader_location(0)])?;
+        descriptor.vertex.buffers = vec![vertex_layout];
+        Ok(())
+    }
+}
+
+/// Shared material of all packed terrain chunks
+#[derive(Resource, Clone)]
+pub struct TerrainMaterialHandle(pub Handle<TerrainMaterial>);
+
+/// Texture array file written by the asset compiler next to the block manifest
+#[derive(Resource, Clone, Debug)]
+pub struct TerrainTextures(pub PathBuf);
+
+/// GPU image of a compiled texture array: every layer with its mips, tiled, nearest filtered
+pub fn texture_array_image(array: &TextureArray) -> Image {
+    let size = Extent3d {
+        width: array.size,
+        height: array.size,
+        depth_or_array_layers: array.layers.len() as u32,
+    };
+    let mut image = Image::new_uninit(
+        size,
+        TextureDimension::D2,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.data = Some(array.layers.concat());
+    image.texture_descriptor.mip_level_count = array.mip_levels;
+    // A single layer would otherwise get a plain 2D view
+    image.texture_view_descriptor = Some(TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::D2Array),
+        ..default()
+    });
+    image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+        address_mode_u: ImageAddressMode::Repeat,
+        address_mode_v: ImageAddressMode::Repeat,
+        mipmap_filter: ImageFilterMode::Linear,
+        ..ImageSamplerDescriptor::nearest()
+    });
+    image
+}
+
+pub fn load_texture_array(path: &Path) -> Result<Image, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let array = TextureArray::read(&mut BufReader::new(file)).map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok(texture_array_image(&array))
+}
+
+impl TerrainMaterial {
+    /// Reloads the texture array after the block manifest changed, the compiler writes
+    /// the array first so it is complete by then
+    pub fn reload_textures(
+        mut events: MessageReader<AssetEvent<BlockManifest>>,
+        textures: Option<Res<TerrainTextures>>,
+        material: Res<TerrainMaterialHandle>,
+        mut materials: ResMut<Assets<TerrainMaterial>>,
+        mut images: ResMut<Assets<Image>>,
+    ) {
+        let modified = events.read().any(|event| matches!(event, AssetEvent::Modified { .. }));
+        let Some(textures) = textures.filter(|_| modified) else {
+            return;
+        };
+        match load_texture_array(&textures.0) {
+            Ok(image) => {
+                let handle = images.add(image);
+                if let Some(material) = materials.get_mut(&material.0) {
+                    material.textures = Some(handle);
+                }
+            }
+            Err(e) => error!("{e}, keeping the previous block textures"),
+        }
+    }
+}
+
+/// Renders chunks with the packed vertex format and `TerrainMaterial`
+/// instead of full-size vertices and a `StandardMaterial`.
+pub struct TerrainMaterialPlugin;
+
+impl Plugin for TerrainMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        embedded_asset!(app, "terrain.wgsl");
+        app.add_plugins(MaterialPlugin::<TerrainMaterial>::default());
+
+        let path = app.world().get_resource::<TerrainTextures>().map(|textures| textures.0.clone());
+        let textures = path
+            .and_then(|path| {
+                load_texture_array(&path)
+                    .inspect_err(|e| error!("{e}, drawing blocks with palette colors"))
+                    .ok()
+            })
+            .map(|image| app.world_mut().resource_mut::<Assets<Image>>().add(image));
+        let handle = app
+            .world_mut()
+            .resource_mut::<Assets<TerrainMaterial>>()
+            .add(TerrainMaterial { textures, ..default() });
+        app.insert_resource(TerrainMaterialHandle(handle));
+        if app.is_plugin_added::<AssetsPlugin>() {
+            app.add_systems(Update, TerrainMaterial::reload_textures);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assets::texture_array::{mip_chain, mip_chain_len, mip_count};
+
+    use super::*;
+
+    #[test]
+    fn texture_array_image_keeps_layers_and_mips() {
+        let array = TextureArray {
+            size: 4,
+            mip_levels: mip_count(4),
+            layers: vec![mip_chain(&[255; 64], 4), mip_chain(&[0; 64], 4)],
+        };
+        let image = texture_array_image(&array);
+        assert_eq!(image.texture_descriptor.size.depth_or_array_layers, 2);
+        assert_eq!(image.texture_descriptor.mip_level_count, 3);
+        assert_eq!(image.data.as_ref().map(Vec::len), Some(2 * mip_chain_len(4)));
+        let view = image.texture_view_descriptor.as_ref().and_then(|view| view.dimension);
+        assert_eq!(view, Some(TextureViewDimension::D2Array));
+    }
+}
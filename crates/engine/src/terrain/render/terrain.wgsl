@@ -0,0 +1,117 @@
+// Terrain material: decodes the packed vertex format from terrain::meshing::packed.
+// Keep the bit layout in sync with the constants in packed.rs.
+#import bevy_pbr::{
+    fog::{atmospheric_fog, exponential_fog, exponential_squared_fog, linear_fog},
+    mesh_functions::{get_world_from_local, mesh_position_local_to_world},
+    mesh_view_bindings::{fog, view},
+    mesh_view_types::{FOG_MODE_ATMOSPHERIC, FOG_MODE_EXPONENTIAL, FOG_MODE_EXPONENTIAL_SQUARED, FOG_MODE_LINEAR},
+    view_transformations::position_world_to_clip,
+}
+
+struct TerrainParams {
+    palette: array<vec4<f32>, 16>,
+    sky_intensity: f32,
+    ambient: f32,
+};
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(0) var<uniform> params: TerrainParams;
+@group(#{MATERIAL_BIND_GROUP}) @binding(1) var textures: texture_2d_array<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(2) var textures_sampler: sampler;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) packed: vec2<u32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) @interpolate(flat) layer: u32,
+    @location(3) shade: f32,
+    @location(4) world_position: vec3<f32>,
+    @location(5) @interpolate(flat) textured: u32,
+};
+
+fn bits(word: u32, shift: u32, count: u32) -> u32 {
+    return (word >> shift) & ((1u << count) - 1u);
+}
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    let lo = vertex.packed.x;
+    let hi = vertex.packed.y;
+
+    // Word 0: x 6 | y 8 | z 6 | normal 3 | ao 2 | textured 1
+    let position = vec3<f32>(
+        f32(bits(lo, 0u, 6u)),
+        f32(bits(lo, 6u, 8u)),
+        f32(bits(lo, 14u, 6u)),
+    );
+    let normal_index = bits(lo, 20u, 3u);
+    let ao = f32(bits(lo, 23u, 2u)) / 3.0;
+    let textured = bits(lo, 25u, 1u);
+
+    // Word 1: u 8 | v 8 | layer 8 | sky light 4 | block light 4
+    var uv = vec2<f32>(f32(bits(hi, 0u, 8u)), f32(bits(hi, 8u, 8u)));
+    let layer = bits(hi, 16u, 8u);
+    let sky = f32(bits(hi, 24u, 4u)) / 15.0;
+    let block = f32(bits(hi, 28u, 4u)) / 15.0;
+
+    // Normals are ordered +X, -X, +Y, -Y, +Z, -Z
+    var normal = vec3<f32>(0.0);
+    normal[normal_index / 2u] = 1.0 - 2.0 * f32(normal_index % 2u);
+
+    // The meshers lay u, v along the next two axes. Side faces get the texture upright:
+    // X faces run (y, z), Z faces (x, y), and texture v grows downwards.
+    if normal_index < 2u {
+        uv = vec2<f32>(uv.y, -uv.x);
+    } else if normal_index >= 4u {
+        uv = vec2<f32>(uv.x, -uv.y);
+    }
+
+    let world_position = mesh_position_local_to_world(
+        get_world_from_local(vertex.instance_index),
+        vec4<f32>(position, 1.0),
+    );
+
+    var out: VertexOutput;
+    out.clip_position = position_world_to_clip(world_position.xyz);
+    out.world_position = world_position.xyz;
+    out.normal = normal;
+    out.uv = uv;
+    out.layer = layer;
+    out.textured = textured;
+    out.shade = mix(0.5, 1.0, ao) * max(max(sky * params.sky_intensity, block), params.ambient);
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Sampled on every path, derivatives need uniform control flow
+    let texel = textureSample(textures, textures_sampler, in.uv, in.layer);
+    let base = select(params.palette[in.layer % 16u], texel, in.textured != 0u);
+    // Top faces full, sides 3/4, bottoms half, so block edges read without real lights
+    let facing = 0.75 + 0.25 * in.normal.y;
+    var color = vec4<f32>(base.rgb * in.shade * facing, base.a);
+#ifdef DISTANCE_FOG
+    color = apply_distance_fog(color, in.world_position);
+#endif
+    return color;
+}
+
+// `DistanceFog` on the camera, without the directional light scattering of StandardMaterial
+fn apply_distance_fog(color: vec4<f32>, world_position: vec3<f32>) -> vec4<f32> {
+    let distance = length(world_position - view.world_position);
+    let scattering = vec3<f32>(0.0);
+    if fog.mode == FOG_MODE_LINEAR {
+        return linear_fog(fog, color, distance, scattering);
+    } else if fog.mode == FOG_MODE_EXPONENTIAL {
+        return exponential_fog(fog, color, distance, scattering);
+    } else if fog.mode == FOG_MODE_EXPONENTIAL_SQUARED {
+        return exponential_squared_fog(fog, color, distance, scattering);
+    } else if fog.mode == FOG_MODE_ATMOSPHERIC {
+        return atmospheric_fog(fog, color, distance, scattering);
+    }
+    return color;
+}
@@ -0,0 +1,216 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+/// Samples kept per timing window
+pub const STATS_WINDOW: usize = 256;
+/// Histogram buckets, powers of two microseconds from 1 µs to about 17 s
+pub const HISTOGRAM_BUCKETS: usize = 25;
+
+/// Pipeline work that is timed, each in its own tracing span
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PipelineStage {
+    Generation,
+    Decoration,
+    Lighting,
+    Meshing,
+    Upload,
+}
+
+impl PipelineStage {
+    pub const ALL: [PipelineStage; 5] = [
+        PipelineStage::Generation,
+        PipelineStage::Decoration,
+        PipelineStage::Lighting,
+        PipelineStage::Meshing,
+        PipelineStage::Upload,
+    ];
+}
+
+/// The latest task durations, oldest dropped first
+#[derive(Clone, Debug)]
+pub struct TimingWindow {
+    samples: VecDeque<Duration>,
+    capacity: usize,
+}
+
+impl Default for TimingWindow {
+    fn default() -> Self {
+        Self::new(STATS_WINDOW)
+    }
+}
+
+impl TimingWindow {
+    pub fn new(capacity: usize) -> Self {
+        Self { samples: VecDeque::with_capacity(capacity), capacity: capacity.max(1) }
+    }
+
+    pub fn push(&mut self, sample: Duration) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Nearest-rank percentile, `p` in 0..=1. `None` before the first sample.
+    pub fn percentile(&self, p: f32) -> Option<Duration> {
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = ((sorted.len() as f32 * p.clamp(0.0, 1.0)).ceil() as usize).max(1);
+        sorted.get(rank - 1).copied()
+    }
+}
+
+/// Every duration since startup, in power of two buckets
+#[derive(Clone, Debug, Default)]
+pub struct TimingHistogram {
+    buckets: [u64; HISTOGRAM_BUCKETS],
+    count: u64,
+    total: Duration,
+    max: Duration,
+}
+
+impl TimingHistogram {
+    /// Bucket `i` holds durations below `2^i` µs, the last one everything longer
+    fn bucket(sample: Duration) -> usize {
+        let micros = sample.as_micros().min(u64::MAX as u128) as u64;
+        ((u64::BITS - micros.leading_zeros()) as usize).min(HISTOGRAM_BUCKETS - 1)
+    }
+
+    /// Upper bound of a bucket
+    pub fn bucket_limit(index: usize) -> Duration {
+        Duration::from_micros(1 << index)
+    }
+
+    pub fn record(&mut self, sample: Duration) {
+        self.buckets[Self::bucket(sample)] += 1;
+        self.count += 1;
+        self.total += sample;
+        self.max = self.max.max(sample);
+    }
+
+    pub fn buckets(&self) -> &[u64; HISTOGRAM_BUCKETS] {
+        &self.buckets
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0).then(|| self.total / self.count as u32)
+    }
+
+    /// Upper bound of the bucket holding the `p` percentile, capped at the slowest sample.
+    /// The last bucket has no bound and gives the slowest sample.
+    pub fn percentile(&self, p: f32) -> Option<Duration> {
+        let rank = ((self.count as f32 * p.clamp(0.0, 1.0)).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let limit = if index + 1 < HISTOGRAM_BUCKETS { Self::bucket_limit(index) } else { self.max };
+                return Some(limit.min(self.max));
+            }
+        }
+        None
+    }
+}
+
+/// Work done by the chunk pipeline, for the debug overlay and benchmarks
+#[derive(Resource, Default, Debug)]
+pub struct TerrainStats {
+    /// Time a worker spent generating one chunk
+    pub generation: TimingWindow,
+    /// Time a worker spent meshing one chunk, packing included
+    pub meshing: TimingWindow,
+    /// Chunks generated since startup
+    pub generated: u64,
+    /// Meshes built since startup, remeshes included
+    pub meshed: u64,
+    histograms: [TimingHistogram; PipelineStage::ALL.len()],
+}
+
+impl TerrainStats {
+    pub fn record_generation(&mut self, elapsed: Duration) {
+        self.generation.push(elapsed);
+        self.generated += 1;
+        self.record(PipelineStage::Generation, elapsed);
+    }
+
+    pub fn record_meshing(&mut self, elapsed: Duration) {
+        self.meshing.push(elapsed);
+        self.meshed += 1;
+        self.record(PipelineStage::Meshing, elapsed);
+    }
+
+    /// Adds a sample to the histogram of a stage
+    pub fn record(&mut self, stage: PipelineStage, elapsed: Duration) {
+        self.histograms[stage as usize].record(elapsed);
+    }
+
+    pub fn histogram(&self, stage: PipelineStage) -> &TimingHistogram {
+        &self.histograms[stage as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_over_the_latest_samples() {
+        let mut window = TimingWindow::new(100);
+        assert_eq!(window.percentile(0.5), None);
+        // 1..=200 ms, only 101..=200 are kept
+        for ms in 1..=200 {
+            window.push(Duration::from_millis(ms));
+        }
+        assert_eq!(window.len(), 100);
+        assert_eq!(window.percentile(0.0), Some(Duration::from_millis(101)));
+        assert_eq!(window.percentile(0.5), Some(Duration::from_millis(150)));
+        assert_eq!(window.percentile(0.99), Some(Duration::from_millis(199)));
+        assert_eq!(window.percentile(1.0), Some(Duration::from_millis(200)));
+    }
+
+    #[test]
+    fn histogram_buckets() {
+        let mut histogram = TimingHistogram::default();
+        assert_eq!(histogram.percentile(0.5), None);
+        for micros in [0, 1, 3, 4, 1000] {
+            histogram.record(Duration::from_micros(micros));
+        }
+        histogram.record(Duration::from_secs(100));
+        assert_eq!(histogram.buckets()[..4], [1, 1, 1, 1]);
+        assert_eq!(histogram.buckets()[10], 1);
+        assert_eq!(histogram.buckets()[HISTOGRAM_BUCKETS - 1], 1);
+        assert_eq!(histogram.count(), 6);
+        assert_eq!(histogram.percentile(0.5), Some(Duration::from_micros(4)));
+        assert_eq!(histogram.percentile(0.8), Some(Duration::from_micros(1024)));
+        assert_eq!(histogram.percentile(1.0), Some(Duration::from_secs(100)));
+
+        let mut stats = TerrainStats::default();
+        stats.record_generation(Duration::from_millis(2));
+        stats.record(PipelineStage::Lighting, Duration::from_millis(1));
+        assert_eq!(stats.histogram(PipelineStage::Generation).count(), 1);
+        assert_eq!(stats.histogram(PipelineStage::Lighting).mean(), Some(Duration::from_millis(1)));
+        assert_eq!(stats.histogram(PipelineStage::Meshing).count(), 0);
+    }
+}
@@ -0,0 +1,439 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use bevy::log::error;
+use bevy::math::IVec2;
+
+use crate::terrain::{
+    config::{WorldConfig, WORLD_CONFIG_FILE},
+    ecs::components::chunk::{ChunkCoords, ChunkData},
+    generator::{TerrainGenerator, TerrainManager},
+};
+
+use super::codec::{decode_chunk, encode_chunk};
+
+pub const ARCHIVE_VERSION: u16 = 1;
+
+const MAGIC: [u8; 4] = *b"VXLV";
+const FLAG_SEED: u16 = 1;
+/// magic, version, flags, seed, reserved, index offset, index length, index crc
+const HEADER_LEN: u64 = 4 + 2 + 2 + 4 + 4 + 8 + 4 + 4;
+
+const KIND_CHUNK: u8 = 1;
+const KIND_FILE: u8 = 2;
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ArchiveEntry {
+    /// Encoded chunk, see `encode_chunk`
+    Chunk(IVec2),
+    /// Any other level data (entities, scenes), by path relative to the world directory
+    File(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexEntry {
+    pub entry: ArchiveEntry,
+    pub offset: u64,
+    pub len: u32,
+    pub crc: u32,
+}
+
+/// Writes a level archive: a header, the blobs, then an index of every blob with its CRC32.
+/// Chunks missing from an archive that has a seed are regenerated from that seed.
+pub struct ArchiveWriter<W: Write + Seek> {
+    out: W,
+    seed: Option<i32>,
+    offset: u64,
+    index: Vec<IndexEntry>,
+}
+
+impl<W: Write + Seek> ArchiveWriter<W> {
+    pub fn new(mut out: W, seed: Option<i32>) -> io::Result<Self> {
+        // Patched by `finish` once the index location is known
+        out.write_all(&[0; HEADER_LEN as usize])?;
+        Ok(Self {
+            out,
+            seed,
+            offset: HEADER_LEN,
+            index: Vec::new(),
+        })
+    }
+
+    pub fn add_chunk(&mut self, coords: IVec2, chunk: &ChunkData) -> io::Result<()> {
+        self.add(ArchiveEntry::Chunk(coords), &encode_chunk(chunk))
+    }
+
+    pub fn add_file(&mut self, name: &str, bytes: &[u8]) -> io::Result<()> {
+        if name.len() > u16::MAX as usize {
+            return Err(invalid(format!("file name too long: {name}")));
+        }
+        self.add(ArchiveEntry::File(name.to_string()), bytes)
+    }
+
+    fn add(&mut self, entry: ArchiveEntry, bytes: &[u8]) -> io::Result<()> {
+        let len = u32::try_from(bytes.len()).map_err(|_| invalid("entry larger than 4 GiB"))?;
+        self.out.write_all(bytes)?;
+        self.index.push(IndexEntry {
+            entry,
+            offset: self.offset,
+            len,
+            crc: crc32fast::hash(bytes),
+        });
+        self.offset += len as u64;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        let mut index = Vec::new();
+        index.extend_from_slice(&(self.index.len() as u32).to_le_bytes());
+        for item in &self.index {
+            match &item.entry {
+                ArchiveEntry::Chunk(coords) => {
+                    index.push(KIND_CHUNK);
+                    index.extend_from_slice(&coords.x.to_le_bytes());
+                    index.extend_from_slice(&coords.y.to_le_bytes());
+                }
+                ArchiveEntry::File(name) => {
+                    index.push(KIND_FILE);
+                    index.extend_from_slice(&(name.len() as u16).to_le_bytes());
+                    index.extend_from_slice(name.as_bytes());
+                }
+            }
+            index.extend_from_slice(&item.offset.to_le_bytes());
+            index.extend_from_slice(&item.len.to_le_bytes());
+            index.extend_from_slice(&item.crc.to_le_bytes());
+        }
+        self.out.write_all(&index)?;
+
+        let mut header = Vec::with_capacity(HEADER_LEN as usize);
+        header.extend_from_slice(&MAGIC);
+        header.extend_from_slice(&ARCHIVE_VERSION.to_le_bytes());
+        let flags = if self.seed.is_some() { FLAG_SEED } else { 0 };
+        header.extend_from_slice(&flags.to_le_bytes());
+        header.extend_from_slice(&self.seed.unwrap_or(0).to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&self.offset.to_le_bytes());
+        header.extend_from_slice(&(index.len() as u32).to_le_bytes());
+        header.extend_from_slice(&crc32fast::hash(&index).to_le_bytes());
+
+        self.out.seek(SeekFrom::Start(0))?;
+        self.out.write_all(&header)?;
+        self.out.seek(SeekFrom::End(0))?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Reads a level archive. Only the index is loaded up front, blobs are read on demand
+/// and checked against their CRC32.
+pub struct LevelArchive<R: Read + Seek> {
+    reader: R,
+    seed: Option<i32>,
+    entries: Vec<IndexEntry>,
+    chunks: HashMap<IVec2, usize>,
+}
+
+struct IndexReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl IndexReader<'_> {
+    fn take<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + N)
+            .ok_or_else(|| invalid("archive index is truncated"))?;
+        self.pos += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn take_slice(&mut self, len: usize) -> io::Result<&[u8]> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| invalid("archive index is truncated"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+}
+
+impl<R: Read + Seek> LevelArchive<R> {
+    pub fn open(mut reader: R) -> io::Result<Self> {
+        let stream_len = reader.seek(SeekFrom::End(0))?;
+        // Lengths are checked against the stream before anything is allocated for them
+        let within_stream =
+            |offset: u64, len: usize| offset.checked_add(len as u64).is_some_and(|end| end <= stream_len);
+        let mut header = [0u8; HEADER_LEN as usize];
+        reader.seek(SeekFrom::Start(0))?;
+        reader
+            .read_exact(&mut header)
+            .map_err(|_| invalid("not a level archive"))?;
+
+        let mut cursor = IndexReader { bytes: &header, pos: 0 };
+        if cursor.take::<4>()? != MAGIC {
+            return Err(invalid("not a level archive"));
+        }
+        let version = u16::from_le_bytes(cursor.take()?);
+        if version != ARCHIVE_VERSION {
+            return Err(invalid(format!("unsupported archive version {version}")));
+        }
+        let flags = u16::from_le_bytes(cursor.take()?);
+        let seed = i32::from_le_bytes(cursor.take()?);
+        let _reserved = cursor.take::<4>()?;
+        let index_offset = u64::from_le_bytes(cursor.take()?);
+        let index_len = u32::from_le_bytes(cursor.take()?) as usize;
+        let index_crc = u32::from_le_bytes(cursor.take()?);
+        if !within_stream(index_offset, index_len) {
+            return Err(invalid("archive index is truncated"));
+        }
+
+        let mut index = vec![0u8; index_len];
+        reader.seek(SeekFrom::Start(index_offset))?;
+        reader
+            .read_exact(&mut index)
+            .map_err(|_| invalid("archive index is truncated"))?;
+        if crc32fast::hash(&index) != index_crc {
+            return Err(invalid("archive index checksum mismatch"));
+        }
+
+        let mut cursor = IndexReader { bytes: &index, pos: 0 };
+        let count = u32::from_le_bytes(cursor.take()?) as usize;
+        let mut entries = Vec::with_capacity(count.min(index.len()));
+        let mut chunks = HashMap::new();
+        for _ in 0..count {
+            let entry = match cursor.take::<1>()?[0] {
+                KIND_CHUNK => {
+                    let x = i32::from_le_bytes(cursor.take()?);
+                    let z = i32::from_le_bytes(cursor.take()?);
+                    chunks.insert(IVec2::new(x, z), entries.len());
+                    ArchiveEntry::Chunk(IVec2::new(x, z))
+                }
+                KIND_FILE => {
+                    let len = u16::from_le_bytes(cursor.take()?) as usize;
+                    let name = std::str::from_utf8(cursor.take_slice(len)?)
+                        .map_err(|_| invalid("file name is not UTF-8"))?;
+                    ArchiveEntry::File(name.to_string())
+                }
+                kind => return Err(invalid(format!("unknown entry kind {kind}"))),
+            };
+            let item = IndexEntry {
+                entry,
+                offset: u64::from_le_bytes(cursor.take()?),
+                len: u32::from_le_bytes(cursor.take()?),
+                crc: u32::from_le_bytes(cursor.take()?),
+            };
+            if !within_stream(item.offset, item.len as usize) {
+                return Err(invalid(format!("{:?} is truncated", item.entry)));
+            }
+            entries.push(item);
+        }
+
+        Ok(Self {
+            reader,
+            seed: (flags & FLAG_SEED != 0).then_some(seed),
+            entries,
+            chunks,
+        })
+    }
+
+    /// Seed the dropped chunks are regenerated from
+    pub fn seed(&self) -> Option<i32> {
+        self.seed
+    }
+
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    /// Blob of an entry, after checking its CRC32
+    pub fn read(&mut self, index: usize) -> io::Result<Vec<u8>> {
+        let item = &self.entries[index];
+        let mut bytes = vec![0u8; item.len as usize];
+        self.reader.seek(SeekFrom::Start(item.offset))?;
+        self.reader.read_exact(&mut bytes)?;
+        if crc32fast::hash(&bytes) != item.crc {
+            return Err(invalid(format!("checksum mismatch for {:?}", item.entry)));
+        }
+        Ok(bytes)
+    }
+
+    /// `None` when the chunk was not packed (regenerate it from `seed`)
+    pub fn load_chunk(&mut self, coords: IVec2) -> io::Result<Option<ChunkData>> {
+        let Some(&index) = self.chunks.get(&coords) else {
+            return Ok(None);
+        };
+        let bytes = self.read(index)?;
+        decode_chunk(&bytes)
+            .map(Some)
+            .map_err(|e| invalid(format!("chunk {coords}: {e}")))
+    }
+
+    /// The `world.ron` packed with the level, if any
+    pub fn config(&mut self) -> io::Result<Option<WorldConfig>> {
+        let name = ArchiveEntry::File(WORLD_CONFIG_FILE.to_string());
+        let Some(index) = self.entries.iter().position(|item| item.entry == name) else {
+            return Ok(None);
+        };
+        let text = String::from_utf8(self.read(index)?).map_err(|_| invalid("world.ron is not UTF-8"))?;
+        WorldConfig::from_ron(&text).map(Some).map_err(invalid)
+    }
+
+    /// Reads every blob and decodes every chunk
+    pub fn verify(&mut self) -> io::Result<()> {
+        for index in 0..self.entries.len() {
+            let bytes = self.read(index)?;
+            if let ArchiveEntry::Chunk(coords) = self.entries[index].entry {
+                decode_chunk(&bytes).map_err(|e| invalid(format!("chunk {coords}: {e}")))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Generator of a packed level: packed chunks come back as they were packed, without
+/// decoration. The ones the packer left out go through the level's own generator, from
+/// its `world.ron` and the archive's seed, with biomes and structures from the asset folder.
+pub struct ArchiveGenerator<R: Read + Seek> {
+    archive: Mutex<LevelArchive<R>>,
+    packed: HashSet<IVec2>,
+    config: WorldConfig,
+    fallback: TerrainManager,
+}
+
+impl ArchiveGenerator<BufReader<File>> {
+    pub fn open(path: &Path, assets: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("cannot open {}: {e}", path.display()))?;
+        let archive = LevelArchive::open(BufReader::new(file)).map_err(|e| format!("{}: {e}", path.display()))?;
+        Self::new(archive, assets).map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
+
+impl<R: Read + Seek> ArchiveGenerator<R> {
+    pub fn new(mut archive: LevelArchive<R>, assets: &Path) -> io::Result<Self> {
+        let config = archive.config()?.unwrap_or_default();
+        let config = WorldConfig { seed: archive.seed().unwrap_or(config.seed), ..config };
+        Ok(Self {
+            packed: archive.chunks.keys().copied().collect(),
+            fallback: TerrainManager::for_world(&config, assets),
+            config,
+            archive: Mutex::new(archive),
+        })
+    }
+
+    /// Settings of the level, to start the game with
+    pub fn config(&self) -> &WorldConfig {
+        &self.config
+    }
+}
+
+impl<R: Read + Seek + Send + 'static> TerrainGenerator for ArchiveGenerator<R> {
+    fn generate(&self, coords: ChunkCoords) -> ChunkData {
+        if self.packed.contains(&coords.0) {
+            match self.archive.lock().unwrap().load_chunk(coords.0) {
+                Ok(Some(data)) => return data,
+                Ok(None) => unreachable!("chunk {} is in the index", coords.0),
+                Err(e) => error!("cannot load chunk {}: {e}, generating it", coords.0),
+            }
+        }
+        self.fallback.generate(coords)
+    }
+
+    fn decorate(&self, coords: ChunkCoords, chunk: &mut ChunkData) {
+        if !self.packed.contains(&coords.0) {
+            self.fallback.decorate(coords, chunk);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::terrain::{generator::GeneratorKind, types::Voxel};
+
+    fn sample_archive() -> Vec<u8> {
+        let manager = TerrainManager::new(0, 1, 3);
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()), Some(3)).unwrap();
+        for coords in [IVec2::new(0, 0), IVec2::new(-2, 5)] {
+            writer.add_chunk(coords, &manager.run(ChunkCoords(coords))).unwrap();
+        }
+        writer.add_file("entities/spawn.ron", b"(x: 1, y: 2)").unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut archive = LevelArchive::open(Cursor::new(sample_archive())).unwrap();
+        archive.verify().unwrap();
+
+        assert_eq!(archive.seed(), Some(3));
+        assert_eq!(archive.entries().len(), 3);
+        let expected = TerrainManager::new(0, 1, 3).run(ChunkCoords(IVec2::new(-2, 5)));
+        assert!(archive.load_chunk(IVec2::new(-2, 5)).unwrap().unwrap().voxels == expected.voxels);
+        assert!(archive.load_chunk(IVec2::new(1, 1)).unwrap().is_none());
+        assert_eq!(archive.entries()[2].entry, ArchiveEntry::File("entities/spawn.ron".into()));
+        assert_eq!(archive.read(2).unwrap(), b"(x: 1, y: 2)");
+    }
+
+    #[test]
+    fn detects_corruption() {
+        let mut bytes = sample_archive();
+        // First byte of the first chunk blob
+        bytes[HEADER_LEN as usize] ^= 0xff;
+        let mut archive = LevelArchive::open(Cursor::new(bytes.clone())).unwrap();
+        assert!(archive.verify().is_err());
+
+        // Last byte of the index
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        assert!(LevelArchive::open(Cursor::new(bytes)).is_err());
+    }
+
+    #[test]
+    fn rejects_lengths_past_the_end() {
+        let bytes = sample_archive();
+        let index_offset = u64::from_le_bytes(bytes[16..24].try_into().unwrap()) as usize;
+
+        // An index of 4 GiB is refused before it is read
+        let mut huge_index = bytes.clone();
+        huge_index[24..28].copy_from_slice(&u32::MAX.to_le_bytes());
+        let error = LevelArchive::open(Cursor::new(huge_index)).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // So is an entry reaching past the end, even with a valid index checksum
+        let mut huge_entry = bytes;
+        let len_at = index_offset + 4 + 9 + 8;
+        huge_entry[len_at..len_at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let crc = crc32fast::hash(&huge_entry[index_offset..]);
+        huge_entry[28..32].copy_from_slice(&crc.to_le_bytes());
+        let error = LevelArchive::open(Cursor::new(huge_entry)).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn generates_levels_from_packed_chunks_and_config() {
+        let config = WorldConfig { seed: 1, generator: GeneratorKind::Flat { height: 10 }, ..Default::default() };
+        let mut edited = ChunkData::new();
+        edited.set(1, 50, 2, Voxel::STONE);
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()), Some(9)).unwrap();
+        writer.add_chunk(IVec2::new(-3, 4), &edited).unwrap();
+        writer.add_file(WORLD_CONFIG_FILE, config.to_ron().as_bytes()).unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+
+        let generator = ArchiveGenerator::new(LevelArchive::open(Cursor::new(bytes)).unwrap(), Path::new("assets")).unwrap();
+        assert_eq!(generator.config().seed, 9);
+        assert_eq!(generator.config().generator, GeneratorKind::Flat { height: 10 });
+        assert!(generator.generate(ChunkCoords(IVec2::new(-3, 4))) == edited);
+        // Not packed: generated from the level's flat config
+        assert_eq!(generator.generate(ChunkCoords(IVec2::new(5, 5))).get(0, 9, 0), Voxel::STONE);
+    }
+}
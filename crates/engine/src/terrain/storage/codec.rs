@@ -0,0 +1,172 @@
+use std::fmt;
+
+use crate::terrain::{
+    ecs::{components::chunk::ChunkData, resources::voxel::VoxelRegistry},
+    types::Voxel,
+};
+
+/// Bumped whenever the encoded layout changes
+pub const CODEC_VERSION: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodecError {
+    Empty,
+    UnsupportedVersion(u8),
+    Truncated,
+    /// An id the block registry does not define, see `decode_chunk_checked`
+    UnknownVoxel(u8),
+    /// Runs don't add up to one chunk
+    WrongLength { expected: usize, got: usize },
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::Empty => write!(f, "empty chunk payload"),
+            CodecError::UnsupportedVersion(v) => write!(f, "unsupported chunk codec version {v}"),
+            CodecError::Truncated => write!(f, "chunk payload is truncated"),
+            CodecError::UnknownVoxel(id) => write!(f, "unknown voxel id {id}"),
+            CodecError::WrongLength { expected, got } => {
+                write!(f, "chunk payload has {got} voxels, expected {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+pub(crate) fn write_varint(out: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+pub(crate) fn read_varint(bytes: &[u8], pos: &mut usize) -> Result<u32, CodecError> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = *bytes.get(*pos).ok_or(CodecError::Truncated)?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(CodecError::Truncated)
+}
+
+/// Run-length encodes the voxels in storage order: `version, (run varint, voxel id)*`.
+/// Generated terrain is mostly long runs of the same block along x/z layers.
+pub fn encode_chunk(chunk: &ChunkData) -> Vec<u8> {
+    let mut out = vec![CODEC_VERSION];
+    let mut voxels = chunk.voxels.iter();
+
+    let Some(&first) = voxels.next() else {
+        return out;
+    };
+    let (mut current, mut run) = (first, 1u32);
+    for &voxel in voxels {
+        if voxel == current {
+            run += 1;
+        } else {
+            write_varint(&mut out, run);
+            out.push(current.id());
+            (current, run) = (voxel, 1);
+        }
+    }
+    write_varint(&mut out, run);
+    out.push(current.id());
+    out
+}
+
+pub fn decode_chunk(bytes: &[u8]) -> Result<ChunkData, CodecError> {
+    decode(bytes, None)
+}
+
+/// `decode_chunk`, rejecting the ids `registry` does not define, for chunks written
+/// with another block palette
+pub fn decode_chunk_checked(bytes: &[u8], registry: &VoxelRegistry) -> Result<ChunkData, CodecError> {
+    let mut known = [false; 256];
+    for voxel in registry.definitions.keys() {
+        known[voxel.id() as usize] = true;
+    }
+    decode(bytes, Some(&known))
+}
+
+fn decode(bytes: &[u8], known: Option<&[bool; 256]>) -> Result<ChunkData, CodecError> {
+    let (&version, _) = bytes.split_first().ok_or(CodecError::Empty)?;
+    if version != CODEC_VERSION {
+        return Err(CodecError::UnsupportedVersion(version));
+    }
+
+    let mut chunk = ChunkData::new();
+    let expected = chunk.voxels.len();
+    let mut filled = 0usize;
+    let mut pos = 1;
+
+    while pos < bytes.len() {
+        let run = read_varint(bytes, &mut pos)? as usize;
+        let id = *bytes.get(pos).ok_or(CodecError::Truncated)?;
+        pos += 1;
+        if known.is_some_and(|known| !known[id as usize]) {
+            return Err(CodecError::UnknownVoxel(id));
+        }
+        let voxel = Voxel(id);
+
+        let end = filled + run;
+        if end > expected {
+            return Err(CodecError::WrongLength { expected, got: end });
+        }
+        chunk.voxels[filled..end].fill(voxel);
+        filled = end;
+    }
+
+    if filled != expected {
+        return Err(CodecError::WrongLength { expected, got: filled });
+    }
+    Ok(chunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::{ecs::components::chunk::ChunkCoords, generator::TerrainManager};
+    use bevy::math::IVec2;
+
+    #[test]
+    fn round_trips_generated_chunks() {
+        let manager = TerrainManager::new(0, 1, 42);
+        for coords in [IVec2::new(0, 0), IVec2::new(-5, 9)] {
+            let chunk = manager.run(ChunkCoords(coords));
+            let bytes = encode_chunk(&chunk);
+            // Height columns compress to a few runs per layer
+            assert!(bytes.len() < chunk.voxels.len() / 8);
+            assert!(decode_chunk(&bytes).unwrap().voxels == chunk.voxels);
+        }
+    }
+
+    #[test]
+    fn round_trips_empty_and_full_chunks() {
+        let mut chunk = ChunkData::new();
+        assert!(decode_chunk(&encode_chunk(&chunk)).unwrap().voxels == chunk.voxels);
+        chunk.fill(Voxel::STONE);
+        assert!(decode_chunk(&encode_chunk(&chunk)).unwrap().voxels == chunk.voxels);
+    }
+
+    #[test]
+    fn rejects_corrupt_payloads() {
+        let bytes = encode_chunk(&ChunkData::new());
+
+        assert_eq!(decode_chunk(&[]).err(), Some(CodecError::Empty));
+        assert_eq!(decode_chunk(&[9]).err(), Some(CodecError::UnsupportedVersion(9)));
+        assert_eq!(decode_chunk(&bytes[..bytes.len() - 1]).err(), Some(CodecError::Truncated));
+        let registry = VoxelRegistry::default();
+        assert_eq!(decode_chunk_checked(&[CODEC_VERSION, 1, 200], &registry).err(), Some(CodecError::UnknownVoxel(200)));
+        assert!(decode_chunk_checked(&bytes, &registry).is_ok());
+        assert!(matches!(
+            decode_chunk(&[CODEC_VERSION, 1, 0]),
+            Err(CodecError::WrongLength { got: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,257 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use bevy::math::IVec2;
+
+use crate::terrain::ecs::components::chunk::ChunkData;
+
+use super::codec::{decode_chunk, encode_chunk};
+
+/// Chunks per region side, a region file holds up to REGION_SIZE² chunks
+pub const REGION_SIZE: i32 = 32;
+pub const REGION_VERSION: u16 = 1;
+
+const MAGIC: [u8; 4] = *b"VXRG";
+const SLOTS: usize = (REGION_SIZE * REGION_SIZE) as usize;
+/// magic, version, reserved, then (offset, length) per slot
+const HEADER_LEN: usize = 4 + 2 + 2 + SLOTS * 8;
+
+/// Region containing a chunk
+pub fn region_of(chunk: IVec2) -> IVec2 {
+    chunk.div_euclid(IVec2::splat(REGION_SIZE))
+}
+
+#[inline]
+fn slot(chunk: IVec2) -> usize {
+    let local = chunk.rem_euclid(IVec2::splat(REGION_SIZE));
+    (local.x + local.y * REGION_SIZE) as usize
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Encoded chunks of one region. On disk:
+/// `"VXRG", version u16, 0u16, [offset u32, length u32; REGION_SIZE²], payloads`,
+/// little endian, offset 0 meaning the chunk was never saved.
+pub struct Region {
+    slots: Vec<Option<Vec<u8>>>,
+}
+
+impl Default for Region {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Region {
+    pub fn new() -> Self {
+        Self {
+            slots: vec![None; SLOTS],
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(Option::is_none)
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.iter().filter(|s| s.is_some()).count()
+    }
+
+    /// `chunk` is in world chunk coordinates, it must belong to this region
+    pub fn insert(&mut self, chunk: IVec2, data: &ChunkData) {
+        self.insert_encoded(chunk, encode_chunk(data));
+    }
+
+    pub fn insert_encoded(&mut self, chunk: IVec2, payload: Vec<u8>) {
+        self.slots[slot(chunk)] = Some(payload);
+    }
+
+    pub fn remove(&mut self, chunk: IVec2) {
+        self.slots[slot(chunk)] = None;
+    }
+
+    pub fn get_encoded(&self, chunk: IVec2) -> Option<&[u8]> {
+        self.slots[slot(chunk)].as_deref()
+    }
+
+    pub fn get(&self, chunk: IVec2) -> io::Result<Option<ChunkData>> {
+        self.get_encoded(chunk)
+            .map(|payload| decode_chunk(payload).map_err(|e| invalid(format!("chunk {chunk}: {e}"))))
+            .transpose()
+    }
+
+    /// World coordinates of the saved chunks, `region` being this region's coordinates
+    pub fn chunks(&self, region: IVec2) -> impl Iterator<Item = IVec2> + '_ {
+        let origin = region * REGION_SIZE;
+        self.slots.iter().enumerate().filter(|(_, s)| s.is_some()).map(move |(i, _)| {
+            origin + IVec2::new(i as i32 % REGION_SIZE, i as i32 / REGION_SIZE)
+        })
+    }
+
+    pub fn read(reader: &mut impl Read) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        if bytes.len() < HEADER_LEN || bytes[..4] != MAGIC {
+            return Err(invalid("not a region file"));
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != REGION_VERSION {
+            return Err(invalid(format!("unsupported region version {version}")));
+        }
+
+        let word = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize;
+        let mut region = Self::new();
+        for (i, slot) in region.slots.iter_mut().enumerate() {
+            let (offset, len) = (word(8 + i * 8), word(12 + i * 8));
+            if offset == 0 {
+                continue;
+            }
+            let payload = bytes
+                .get(offset..offset + len)
+                .ok_or_else(|| invalid(format!("slot {i} points past the end of the file")))?;
+            *slot = Some(payload.to_vec());
+        }
+        Ok(region)
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(&MAGIC);
+        header.extend_from_slice(&REGION_VERSION.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+
+        let mut offset = HEADER_LEN;
+        for slot in &self.slots {
+            let (at, len) = match slot {
+                Some(payload) => (offset, payload.len()),
+                None => (0, 0),
+            };
+            header.extend_from_slice(&(at as u32).to_le_bytes());
+            header.extend_from_slice(&(len as u32).to_le_bytes());
+            offset += len;
+        }
+
+        writer.write_all(&header)?;
+        for payload in self.slots.iter().flatten() {
+            writer.write_all(payload)?;
+        }
+        Ok(())
+    }
+}
+
+/// A world save: one region file per REGION_SIZE² chunks, in a single directory
+#[derive(Clone, Debug)]
+pub struct RegionStore {
+    root: PathBuf,
+}
+
+impl RegionStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn region_path(&self, region: IVec2) -> PathBuf {
+        self.root.join(format!("r.{}.{}.vxr", region.x, region.y))
+    }
+
+    /// Coordinates of every region file in the store
+    pub fn regions(&self) -> io::Result<Vec<IVec2>> {
+        let mut regions = Vec::new();
+        for entry in fs::read_dir(&self.root)? {
+            let name = entry?.file_name();
+            let Some(name) = name.to_str() else { continue };
+            let Some(coords) = name.strip_prefix("r.").and_then(|n| n.strip_suffix(".vxr")) else {
+                continue;
+            };
+            if let Some((x, z)) = coords.split_once('.') {
+                if let (Ok(x), Ok(z)) = (x.parse(), z.parse()) {
+                    regions.push(IVec2::new(x, z));
+                }
+            }
+        }
+        regions.sort_by_key(|r| (r.y, r.x));
+        Ok(regions)
+    }
+
+    /// A region that was never saved is empty
+    pub fn load_region(&self, region: IVec2) -> io::Result<Region> {
+        match fs::File::open(self.region_path(region)) {
+            Ok(mut file) => Region::read(&mut file),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Region::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes next to the target and renames, so a crash never leaves half a region
+    pub fn save_region(&self, region: IVec2, data: &Region) -> io::Result<()> {
+        fs::create_dir_all(&self.root)?;
+        let path = self.region_path(region);
+        let tmp = path.with_extension("vxr.tmp");
+        {
+            let mut file = io::BufWriter::new(fs::File::create(&tmp)?);
+            data.write(&mut file)?;
+            file.flush()?;
+        }
+        fs::rename(tmp, path)
+    }
+
+    pub fn load_chunk(&self, chunk: IVec2) -> io::Result<Option<ChunkData>> {
+        self.load_region(region_of(chunk))?.get(chunk)
+    }
+
+    /// Rewrites the whole region file, batch with `save_region` when saving many chunks
+    pub fn save_chunk(&self, chunk: IVec2, data: &ChunkData) -> io::Result<()> {
+        let region = region_of(chunk);
+        let mut contents = self.load_region(region)?;
+        contents.insert(chunk, data);
+        self.save_region(region, &contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::{ecs::components::chunk::ChunkCoords, generator::TerrainManager};
+
+    #[test]
+    fn region_math_handles_negative_chunks() {
+        assert_eq!(region_of(IVec2::new(0, 31)), IVec2::new(0, 0));
+        assert_eq!(region_of(IVec2::new(-1, 32)), IVec2::new(-1, 1));
+        assert_eq!(slot(IVec2::new(-1, -32)), (REGION_SIZE - 1) as usize);
+    }
+
+    #[test]
+    fn store_round_trip() {
+        let root = std::env::temp_dir().join(format!("voxel-region-test-{}", std::process::id()));
+        let store = RegionStore::new(&root);
+        let manager = TerrainManager::new(0, 1, 7);
+
+        let chunks = [IVec2::new(0, 0), IVec2::new(31, 5), IVec2::new(-1, -1), IVec2::new(40, -70)];
+        for coords in chunks {
+            store.save_chunk(coords, &manager.run(ChunkCoords(coords))).unwrap();
+        }
+
+        for coords in chunks {
+            let loaded = store.load_chunk(coords).unwrap().expect("chunk was saved");
+            assert!(loaded.voxels == manager.run(ChunkCoords(coords)).voxels);
+        }
+        assert!(store.load_chunk(IVec2::new(1, 0)).unwrap().is_none());
+        assert!(store.load_chunk(IVec2::new(500, 500)).unwrap().is_none());
+
+        let region = store.load_region(IVec2::ZERO).unwrap();
+        let mut saved: Vec<IVec2> = region.chunks(IVec2::ZERO).collect();
+        saved.sort_by_key(|c| (c.y, c.x));
+        assert_eq!(saved, vec![IVec2::new(0, 0), IVec2::new(31, 5)]);
+        assert_eq!(store.regions().unwrap().len(), 3);
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
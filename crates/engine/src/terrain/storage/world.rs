@@ -0,0 +1,135 @@
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy::log::error;
+use bevy::math::IVec2;
+
+use crate::terrain::{
+    config::WorldConfig,
+    ecs::components::chunk::{ChunkCoords, ChunkData},
+    generator::{TerrainGenerator, TerrainManager},
+};
+
+use super::region::{region_of, RegionStore};
+
+/// A world directory: `world.ron` and the region files of the chunks that were saved.
+/// Chunks that were never saved are generated from the config.
+#[derive(Clone, Debug)]
+pub struct WorldSave {
+    config: WorldConfig,
+    store: RegionStore,
+}
+
+impl WorldSave {
+    /// Opens the world in `dir`, or creates it with `config`, see `WorldConfig::open_world`
+    pub fn open(dir: impl Into<PathBuf>, config: &WorldConfig) -> Result<Self, String> {
+        let store = RegionStore::new(dir);
+        let config = config.open_world(store.root())?;
+        Ok(Self { config, store })
+    }
+
+    pub fn config(&self) -> &WorldConfig {
+        &self.config
+    }
+
+    pub fn store(&self) -> &RegionStore {
+        &self.store
+    }
+
+    /// Coordinates of every chunk in the region files
+    pub fn saved_chunks(&self) -> io::Result<HashSet<IVec2>> {
+        let mut saved = HashSet::new();
+        for region in self.store.regions()? {
+            saved.extend(self.store.load_region(region)?.chunks(region));
+        }
+        Ok(saved)
+    }
+
+    /// Loads saved chunks and generates the others, with biomes and structures from the
+    /// asset folder `assets`
+    pub fn generator(&self, assets: &Path) -> io::Result<SavedWorldGenerator> {
+        Ok(SavedWorldGenerator {
+            store: self.store.clone(),
+            saved: self.saved_chunks()?,
+            fallback: TerrainManager::for_world(&self.config, assets),
+        })
+    }
+
+    /// Writes chunks into their regions, each region file once. Returns how many were saved.
+    pub fn save_chunks<'a>(&self, chunks: impl IntoIterator<Item = (IVec2, &'a ChunkData)>) -> io::Result<usize> {
+        let mut by_region: BTreeMap<(i32, i32), Vec<(IVec2, &ChunkData)>> = BTreeMap::new();
+        for (coords, data) in chunks {
+            let region = region_of(coords);
+            by_region.entry((region.x, region.y)).or_default().push((coords, data));
+        }
+
+        let mut saved = 0;
+        for ((x, z), chunks) in by_region {
+            let region = IVec2::new(x, z);
+            let mut contents = self.store.load_region(region)?;
+            for (coords, data) in chunks {
+                contents.insert(coords, data);
+                saved += 1;
+            }
+            self.store.save_region(region, &contents)?;
+        }
+        Ok(saved)
+    }
+}
+
+/// Generator of a `WorldSave`: saved chunks come back as they were saved, without
+/// decoration, the rest go through the world's own generator.
+pub struct SavedWorldGenerator {
+    store: RegionStore,
+    saved: HashSet<IVec2>,
+    fallback: TerrainManager,
+}
+
+impl TerrainGenerator for SavedWorldGenerator {
+    fn generate(&self, coords: ChunkCoords) -> ChunkData {
+        if self.saved.contains(&coords.0) {
+            match self.store.load_chunk(coords.0) {
+                Ok(Some(data)) => return data,
+                Ok(None) => error!("chunk {} is missing from {}", coords.0, self.store.root().display()),
+                Err(e) => error!("cannot load chunk {}: {e}, generating it", coords.0),
+            }
+        }
+        self.fallback.generate(coords)
+    }
+
+    fn decorate(&self, coords: ChunkCoords, chunk: &mut ChunkData) {
+        if !self.saved.contains(&coords.0) {
+            self.fallback.decorate(coords, chunk);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::{generator::GeneratorKind, types::Voxel};
+
+    #[test]
+    fn saved_chunks_replace_generated_ones() {
+        let root = std::env::temp_dir().join(format!("voxel-world-test-{}", std::process::id()));
+        let config = WorldConfig { generator: GeneratorKind::Flat { height: 10 }, ..Default::default() };
+        let world = WorldSave::open(&root, &config).unwrap();
+
+        let mut edited = world.generator(Path::new("assets")).unwrap().generate(ChunkCoords(IVec2::new(-3, 40)));
+        edited.set(1, 50, 2, Voxel::STONE);
+        let untouched = ChunkData::new();
+        let chunks = [(IVec2::new(-3, 40), &edited), (IVec2::new(-2, 40), &untouched)];
+        assert_eq!(world.save_chunks(chunks).unwrap(), 2);
+
+        let reopened = WorldSave::open(&root, &WorldConfig::default()).unwrap();
+        assert_eq!(reopened.config().generator, GeneratorKind::Flat { height: 10 });
+        let generator = reopened.generator(Path::new("assets")).unwrap();
+        assert!(generator.generate(ChunkCoords(IVec2::new(-3, 40))) == edited);
+        assert!(generator.generate(ChunkCoords(IVec2::new(-2, 40))) == untouched);
+        // Not saved: generated from the flat config
+        assert_eq!(generator.generate(ChunkCoords(IVec2::new(5, 5))).get(0, 9, 0), Voxel::STONE);
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
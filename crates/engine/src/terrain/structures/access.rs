@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::terrain::{
+    constants::{CHUNK_DEPTH, CHUNK_HEIGHT, CHUNK_WIDTH},
+    ecs::{
+        components::chunk::{ChunkData, ChunkDirty, ChunkHeightmap},
+        resources::chunk::ChunkMap,
+    },
+    types::Voxel,
+};
+
+/// Splits a world voxel position into chunk coordinates and the position inside that chunk
+pub fn world_to_chunk(pos: IVec3) -> (IVec2, IVec3) {
+    let coords = IVec2::new(pos.x.div_euclid(CHUNK_WIDTH), pos.z.div_euclid(CHUNK_DEPTH));
+    let local = IVec3::new(pos.x.rem_euclid(CHUNK_WIDTH), pos.y, pos.z.rem_euclid(CHUNK_DEPTH));
+    (coords, local)
+}
+
+/// World position of the chunk's (0, 0, 0) voxel
+pub fn chunk_origin(coords: IVec2) -> IVec3 {
+    IVec3::new(coords.x * CHUNK_WIDTH, 0, coords.y * CHUNK_DEPTH)
+}
+
+/// Chunks whose meshes read the voxel at `local` of chunk `coords`: the side and
+/// diagonal neighbours when it lies on the chunk's border
+pub fn border_neighbours(coords: IVec2, local: IVec3) -> impl Iterator<Item = IVec2> {
+    let side = |v: i32, size: i32| match v {
+        0 => -1..=0,
+        v if v == size - 1 => 0..=1,
+        _ => 0..=0,
+    };
+    let xs = side(local.x, CHUNK_WIDTH);
+    side(local.z, CHUNK_DEPTH)
+        .flat_map(move |dz| xs.clone().map(move |dx| IVec2::new(dx, dz)))
+        .filter(|offset| *offset != IVec2::ZERO)
+        .map(move |offset| coords + offset)
+}
+
+/// Voxel reads and writes in world coordinates, across chunk boundaries
+pub trait VoxelAccess {
+    /// `None` when the position is not backed by a chunk (unloaded, or above/below the world)
+    fn get_voxel(&self, pos: IVec3) -> Option<Voxel>;
+
+    /// Returns false when the position is not backed by a chunk, the write is dropped
+    fn set_voxel(&mut self, pos: IVec3, voxel: Voxel) -> bool;
+}
+
+fn in_height(pos: IVec3) -> bool {
+    (0..CHUNK_HEIGHT).contains(&pos.y)
+}
+
+/// A single chunk seen in world coordinates, writes outside of it are dropped.
+/// The decoration stage places structures through this, one chunk at a time.
+pub struct ChunkView<'a> {
+    pub coords: IVec2,
+    pub data: &'a mut ChunkData,
+}
+
+impl VoxelAccess for ChunkView<'_> {
+    fn get_voxel(&self, pos: IVec3) -> Option<Voxel> {
+        let (coords, local) = world_to_chunk(pos);
+        (coords == self.coords && in_height(pos)).then(|| self.data.get(local.x, local.y, local.z))
+    }
+
+    fn set_voxel(&mut self, pos: IVec3, voxel: Voxel) -> bool {
+        let (coords, local) = world_to_chunk(pos);
+        if coords != self.coords || !in_height(pos) {
+            return false;
+        }
+        self.data.set(local.x, local.y, local.z, voxel);
+        true
+    }
+}
+
+/// Chunks held outside the ECS, for tools and tests
+impl VoxelAccess for HashMap<IVec2, ChunkData> {
+    fn get_voxel(&self, pos: IVec3) -> Option<Voxel> {
+        let (coords, local) = world_to_chunk(pos);
+        let chunk = self.get(&coords).filter(|_| in_height(pos))?;
+        Some(chunk.get(local.x, local.y, local.z))
+    }
+
+    fn set_voxel(&mut self, pos: IVec3, voxel: Voxel) -> bool {
+        let (coords, local) = world_to_chunk(pos);
+        match self.get_mut(&coords).filter(|_| in_height(pos)) {
+            Some(chunk) => {
+                chunk.set(local.x, local.y, local.z, voxel);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Voxels of the loaded chunk entities. Every write marks its chunk `ChunkDirty`,
+/// so it gets re-lit and re-meshed, along with the neighbours sharing a border voxel.
+#[derive(SystemParam)]
+pub struct WorldVoxels<'w, 's> {
+    commands: Commands<'w, 's>,
+    chunk_map: Res<'w, ChunkMap>,
+    chunks: Query<'w, 's, &'static mut ChunkData>,
+}
+
+impl VoxelAccess for WorldVoxels<'_, '_> {
+    fn get_voxel(&self, pos: IVec3) -> Option<Voxel> {
+        let (coords, local) = world_to_chunk(pos);
+        let chunk = self.chunks.get(self.chunk_map.get(coords)?).ok()?;
+        in_height(pos).then(|| chunk.get(local.x, local.y, local.z))
+    }
+
+    fn set_voxel(&mut self, pos: IVec3, voxel: Voxel) -> bool {
+        let (coords, local) = world_to_chunk(pos);
+        let Some(entity) = self.chunk_map.get(coords).filter(|_| in_height(pos)) else {
+            return false;
+        };
+        let Ok(mut chunk) = self.chunks.get_mut(entity) else {
+            return false;
+        };
+        if chunk.get(local.x, local.y, local.z) != voxel {
+            chunk.set(local.x, local.y, local.z, voxel);
+            self.commands.entity(entity).insert(ChunkDirty);
+            for neighbour in border_neighbours(coords, local) {
+                if let Some(entity) = self.chunk_map.get(neighbour) {
+                    self.commands.entity(entity).insert(ChunkDirty);
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Top-most solid voxel of world columns, read from the chunk heightmaps.
+/// Columns of chunks that are not lit yet are `None`, like unloaded ones.
+#[derive(SystemParam)]
+pub struct SurfaceHeights<'w, 's> {
+    chunk_map: Res<'w, ChunkMap>,
+    heightmaps: Query<'w, 's, &'static ChunkHeightmap>,
+}
+
+impl SurfaceHeights<'_, '_> {
+    pub fn top_solid(&self, x: i32, z: i32) -> Option<i32> {
+        let (coords, local) = world_to_chunk(IVec3::new(x, 0, z));
+        let heightmap = self.heightmaps.get(self.chunk_map.get(coords)?).ok()?;
+        heightmap.top_solid(local.x, local.z)
+    }
+}
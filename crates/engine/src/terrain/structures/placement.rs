@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use bevy::math::{IVec2, IVec3};
+
+use assets::structure::{StructureMarker, StructureTemplate};
+
+use crate::terrain::{
+    constants::{CHUNK_DEPTH, CHUNK_WIDTH},
+    types::Voxel,
+};
+
+use super::access::VoxelAccess;
+
+/// Quarter turns around +Y, clockwise seen from above
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Rotation {
+    #[default]
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 4] = [Rotation::None, Rotation::Cw90, Rotation::Cw180, Rotation::Cw270];
+}
+
+/// How a template is oriented when pasted. Mirroring is applied before the rotation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Placement {
+    pub rotation: Rotation,
+    pub mirror_x: bool,
+    pub mirror_z: bool,
+}
+
+impl Placement {
+    /// Maps an offset from the template anchor to an offset from the placement origin
+    pub fn apply(&self, offset: IVec3) -> IVec3 {
+        let x = if self.mirror_x { -offset.x } else { offset.x };
+        let z = if self.mirror_z { -offset.z } else { offset.z };
+        let (x, z) = match self.rotation {
+            Rotation::None => (x, z),
+            Rotation::Cw90 => (-z, x),
+            Rotation::Cw180 => (-x, -z),
+            Rotation::Cw270 => (z, -x),
+        };
+        IVec3::new(x, offset.y, z)
+    }
+}
+
+fn anchor(template: &StructureTemplate) -> IVec3 {
+    let (x, y, z) = template.anchor;
+    IVec3::new(x, y, z)
+}
+
+/// World-space box covered by the placed template, min and max inclusive
+pub fn structure_bounds(template: &StructureTemplate, origin: IVec3, placement: Placement) -> (IVec3, IVec3) {
+    let (sx, sy, sz) = template.size;
+    let far = IVec3::new(sx as i32 - 1, sy as i32 - 1, sz as i32 - 1);
+    let a = origin + placement.apply(-anchor(template));
+    let b = origin + placement.apply(far - anchor(template));
+    (a.min(b), a.max(b))
+}
+
+/// Chunks touched by the placed template
+pub fn structure_chunks(template: &StructureTemplate, origin: IVec3, placement: Placement) -> Vec<IVec2> {
+    let (min, max) = structure_bounds(template, origin, placement);
+    let (min_x, max_x) = (min.x.div_euclid(CHUNK_WIDTH), max.x.div_euclid(CHUNK_WIDTH));
+    let (min_z, max_z) = (min.z.div_euclid(CHUNK_DEPTH), max.z.div_euclid(CHUNK_DEPTH));
+    (min_z..=max_z)
+        .flat_map(|z| (min_x..=max_x).map(move |x| IVec2::new(x, z)))
+        .collect()
+}
+
+/// Pastes a template with its anchor on `origin`. Returns how many voxels were written,
+/// voxels outside the chunks `access` can reach are skipped.
+pub fn place_structure(
+    access: &mut impl VoxelAccess,
+    template: &StructureTemplate,
+    origin: IVec3,
+    placement: Placement,
+) -> usize {
+    let (sx, sy, sz) = template.size;
+    let anchor = anchor(template);
+    let mut written = 0;
+    for y in 0..sy {
+        for z in 0..sz {
+            for x in 0..sx {
+                let Some(id) = template.get(x, y, z) else {
+                    continue;
+                };
+                let offset = IVec3::new(x as i32, y as i32, z as i32) - anchor;
+                if access.set_voxel(origin + placement.apply(offset), Voxel(id)) {
+                    written += 1;
+                }
+            }
+        }
+    }
+    written
+}
+
+/// Template markers moved to world space, in template order
+pub fn structure_markers(template: &StructureTemplate, origin: IVec3, placement: Placement) -> Vec<StructureMarker> {
+    template
+        .markers
+        .iter()
+        .map(|marker| {
+            let (x, y, z) = marker.position;
+            let pos = origin + placement.apply(IVec3::new(x, y, z) - anchor(template));
+            StructureMarker {
+                name: marker.name.clone(),
+                position: (pos.x, pos.y, pos.z),
+            }
+        })
+        .collect()
+}
+
+/// Saves the box `min..=max` as a template anchored on `anchor`.
+/// Unloaded voxels are left out of the template, so is air unless `keep_air` is set.
+pub fn capture_structure(
+    access: &impl VoxelAccess,
+    min: IVec3,
+    max: IVec3,
+    anchor: IVec3,
+    keep_air: bool,
+) -> StructureTemplate {
+    let (min, max) = (min.min(max), min.max(max));
+    let size = max - min + IVec3::ONE;
+
+    // Palette index 0 is always "leave alone"
+    let mut palette = vec![None];
+    let mut index_of: HashMap<Voxel, u8> = HashMap::new();
+    let mut voxels = Vec::with_capacity((size.x * size.y * size.z) as usize);
+    for y in min.y..=max.y {
+        for z in min.z..=max.z {
+            for x in min.x..=max.x {
+                let index = match access.get_voxel(IVec3::new(x, y, z)) {
+                    Some(voxel) if keep_air || voxel != Voxel::AIR => *index_of.entry(voxel).or_insert_with(|| {
+                        palette.push(Some(voxel.id()));
+                        (palette.len() - 1) as u8
+                    }),
+                    _ => 0,
+                };
+                voxels.push(index);
+            }
+        }
+    }
+
+    let anchor = anchor - min;
+    StructureTemplate {
+        size: (size.x as u32, size.y as u32, size.z as u32),
+        anchor: (anchor.x, anchor.y, anchor.z),
+        palette,
+        voxels,
+        markers: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::structures::test_utils::empty_chunks_around;
+
+    /// An L: two voxels along +X and one along +Z from the anchor
+    fn template() -> StructureTemplate {
+        StructureTemplate {
+            size: (3, 1, 2),
+            anchor: (0, 0, 0),
+            palette: vec![None, Some(1), Some(2)],
+            voxels: vec![1, 1, 1, 2, 0, 0],
+            markers: vec![StructureMarker { name: "door".into(), position: (2, 0, 0) }],
+        }
+    }
+
+    #[test]
+    fn places_across_chunk_borders() {
+        let mut world = empty_chunks_around(1);
+        let origin = IVec3::new(15, 10, 15);
+        let written = place_structure(&mut world, &template(), origin, Placement::default());
+        assert_eq!(written, 4);
+        assert_eq!(world.get_voxel(IVec3::new(15, 10, 15)), Some(Voxel(1)));
+        assert_eq!(world.get_voxel(IVec3::new(17, 10, 15)), Some(Voxel(1)));
+        assert_eq!(world.get_voxel(IVec3::new(15, 10, 16)), Some(Voxel(2)));
+        assert_eq!(world[&IVec2::new(1, 0)].get(1, 10, 15), Voxel(1));
+        assert_eq!(structure_chunks(&template(), origin, Placement::default()).len(), 4);
+    }
+
+    #[test]
+    fn rotates_and_mirrors() {
+        let origin = IVec3::new(0, 10, 0);
+        let rotated = Placement { rotation: Rotation::Cw90, ..Default::default() };
+        let mut world = empty_chunks_around(1);
+        place_structure(&mut world, &template(), origin, rotated);
+        // +X turns into +Z, +Z into -X
+        assert_eq!(world.get_voxel(IVec3::new(0, 10, 2)), Some(Voxel(1)));
+        assert_eq!(world.get_voxel(IVec3::new(-1, 10, 0)), Some(Voxel(2)));
+        assert_eq!(structure_markers(&template(), origin, rotated)[0].position, (0, 10, 2));
+
+        let mirrored = Placement { mirror_x: true, ..Default::default() };
+        assert_eq!(structure_bounds(&template(), origin, mirrored), (IVec3::new(-2, 10, 0), IVec3::new(0, 10, 1)));
+
+        let turn = |rotation| Placement { rotation, ..Default::default() };
+        let offset = IVec3::new(3, 1, -2);
+        let quarter = |o| turn(Rotation::Cw90).apply(o);
+        assert_eq!(quarter(quarter(offset)), turn(Rotation::Cw180).apply(offset));
+        assert_eq!(quarter(quarter(quarter(offset))), turn(Rotation::Cw270).apply(offset));
+        assert_eq!(quarter(quarter(quarter(quarter(offset)))), offset);
+    }
+
+    #[test]
+    fn capture_round_trips() {
+        let mut world = empty_chunks_around(1);
+        place_structure(&mut world, &template(), IVec3::new(-2, 5, -1), Placement::default());
+        let captured = capture_structure(&world, IVec3::new(-2, 5, -1), IVec3::new(0, 5, 0), IVec3::new(-2, 5, -1), false);
+        assert_eq!(captured.size, (3, 1, 2));
+        assert_eq!(captured.voxels, template().voxels);
+        assert!(captured.validate().is_ok());
+
+        let mut copy = empty_chunks_around(1);
+        place_structure(&mut copy, &captured, IVec3::new(4, 20, 4), Placement::default());
+        assert_eq!(copy.get_voxel(IVec3::new(4, 20, 5)), Some(Voxel(2)));
+        assert_eq!(copy.get_voxel(IVec3::new(5, 20, 5)), Some(Voxel::AIR));
+    }
+}
@@ -0,0 +1,26 @@
+//! Worlds held in a `HashMap`, for tests of code written against `VoxelAccess`
+
+use std::collections::HashMap;
+
+use bevy::math::IVec2;
+
+use crate::terrain::{ecs::components::chunk::ChunkData, types::Voxel};
+
+/// Empty chunks at `coords`
+pub fn empty_chunks(coords: impl IntoIterator<Item = IVec2>) -> HashMap<IVec2, ChunkData> {
+    coords.into_iter().map(|coords| (coords, ChunkData::new())).collect()
+}
+
+/// Empty chunks in the square reaching `radius` chunks from the origin
+pub fn empty_chunks_around(radius: i32) -> HashMap<IVec2, ChunkData> {
+    empty_chunks((-radius..=radius).flat_map(|z| (-radius..=radius).map(move |x| IVec2::new(x, z))))
+}
+
+/// Chunks at `coords`, stone below `height`
+pub fn stone_ground(coords: impl IntoIterator<Item = IVec2>, height: i32) -> HashMap<IVec2, ChunkData> {
+    let mut world = empty_chunks(coords);
+    for chunk in world.values_mut() {
+        chunk.fill_layer_below(height, Voxel::STONE);
+    }
+    world
+}
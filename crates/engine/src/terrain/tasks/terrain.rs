@@ -1,12 +1,16 @@
-use bevy::log::{info, warn};
-use bevy::pbr::wireframe::Wireframe;
+use std::time::Instant;
+
+use bevy::log::{debug, info_span};
 use bevy::tasks::futures_lite::future;
 
 use bevy::{
     asset::Assets,
+    camera::primitives::Aabb,
     color::Color,
     ecs::{
+        bundle::Bundle,
         entity::Entity,
+        query::{With, Without},
         system::{Commands, Query, Res, ResMut},
     },
     math::{IVec2, Vec3},
@@ -17,29 +21,60 @@ use bevy::{
     transform::components::Transform,
     utils::default,
 };
-// Add the following import or define TerrainGenerator if it's in another module
 use crate::terrain::{
-    constants::{CHUNK_DEPTH, CHUNK_WIDTH},
+    constants::{CHUNK_DEPTH, CHUNK_HEIGHT, CHUNK_WIDTH},
     ecs::{
-        components::chunk::{Chunk, ChunkCompute, ChunkCoords},
-        resources::voxel::VoxelRegistry,
+        components::chunk::{
+            Chunk, ChunkCoords, ChunkData, ChunkDirty, ChunkGenTask, ChunkHeightmap, ChunkLight, ChunkMesh,
+            ChunkMeshData, ChunkMeshInfo, ChunkMeshTask, ChunkStage, MissingNeighbours,
+        },
+        resources::{chunk::ChunkMap, voxel::VoxelRegistry},
+    },
+    generator::{ChunkGenerator, TerrainManager},
+    lighting::compute_skylight,
+    meshing::{
+        bevy_meshing::{meshdata_to_bevy_mesh, packed_mesh_to_bevy_mesh},
+        binary_greedy::SolidTable,
+        mesher::{mesh_chunk, MesherKind},
+        packed::{pack_mesh, FaceTextures},
+        surface_nets::ChunkNeighborhood,
     },
-    generator::TerrainManager,
-    meshing::{bevy_meshing::meshdata_to_bevy_mesh, greedy::greedy_mesh},
+    render::{TerrainMaterial, TerrainMaterialHandle},
+    stats::{PipelineStage, TerrainStats},
 };
 
+/// Components of a chunk entity, placed at its world offset
+pub fn chunk_bundle(coords: ChunkCoords, stage: ChunkStage) -> impl Bundle {
+    let world_offset = Vec3::new(
+        (coords.x * CHUNK_WIDTH) as f32,
+        0.0,
+        (coords.y * CHUNK_DEPTH) as f32,
+    );
+    (
+        Chunk,
+        coords,
+        stage,
+        Transform::from_translation(world_offset),
+        GlobalTransform::default(),
+        Visibility::default(),
+        InheritedVisibility::default(),
+        ViewVisibility::default(),
+    )
+}
+
 pub struct TerrainTask;
 
 impl TerrainTask {
-    /// System that spawns the background work
+    /// System that spawns the background generation work
     pub fn queue(
         mut commands: Commands,
         mut manager: ResMut<TerrainManager>,
-        registry: Res<VoxelRegistry>,
+        generator: Res<ChunkGenerator>,
+        mut chunk_map: ResMut<ChunkMap>,
     ) {
         // We use a while loop to fill the thread budget (e.g., up to 4)
         while manager.active_permits < manager.config.threads {
-            
+
             // try_get_next_chunk handles the spiral logic and HashSet check
             if let Some(coord) = manager.try_get_next_chunk() {
                 // Take the permit and mark as spawned
@@ -48,17 +83,19 @@ impl TerrainTask {
 
                 let chunk_coords = ChunkCoords(coord);
                 let thread_pool = AsyncComputeTaskPool::get();
-                
-                let manager_clone = manager.clone();
-                let registry_clone = registry.clone();
+
+                let generator = generator.0.clone();
 
                 let task = thread_pool.spawn(async move {
-                    let data = manager_clone.run(chunk_coords);
-                    let mesh_data = greedy_mesh(&data, &registry_clone);
-                    (chunk_coords, data, mesh_data)
+                    let _span = info_span!("generate_chunk", coords = %chunk_coords.0).entered();
+                    let started = Instant::now();
+                    (generator.generate(chunk_coords), started.elapsed())
                 });
 
-                commands.spawn(ChunkCompute(task));
+                let entity = commands
+                    .spawn((chunk_bundle(chunk_coords, ChunkStage::Queued), ChunkGenTask(task)))
+                    .id();
+                chunk_map.entities.insert(coord, entity);
             } else {
                 // If None, we've hit the max radius (1024)
                 break;
@@ -66,63 +103,285 @@ impl TerrainTask {
         }
     }
 
-    /// System that collects finished background work
-    pub fn process(
+    /// Queued -> Generated: collects finished generation tasks
+    pub fn poll_generation(
         mut commands: Commands,
         mut manager: ResMut<TerrainManager>,
-        mut tasks: Query<(Entity, &mut ChunkCompute)>,
-        mut meshes: ResMut<Assets<Mesh>>,
-        mut materials: ResMut<Assets<StandardMaterial>>,
+        mut stats: ResMut<TerrainStats>,
+        mut tasks: Query<(Entity, &mut ChunkStage, &mut ChunkGenTask)>,
     ) {
-        for (entity, mut task) in &mut tasks {
-            if let Some((coords, data, mesh_data)) =
-                future::block_on(future::poll_once(&mut task.0))
-            {
+        for (entity, mut stage, mut task) in &mut tasks {
+            if let Some((data, elapsed)) = future::block_on(future::poll_once(&mut task.0)) {
                 if manager.active_permits > 0 {
                     manager.active_permits -= 1;
                 }
-                let vert_count = mesh_data.positions.len();
-                info!("{:?} finished. Vertices: {}", coords, vert_count);
+                stats.record_generation(elapsed);
+                *stage = ChunkStage::Generated;
+                commands
+                    .entity(entity)
+                    .insert(data)
+                    .remove::<ChunkGenTask>();
+            }
+        }
+    }
+
+    /// Generated -> Decorated
+    pub fn decorate(
+        generator: Res<ChunkGenerator>,
+        mut stats: ResMut<TerrainStats>,
+        mut chunks: Query<(&ChunkCoords, &mut ChunkStage, &mut ChunkData)>,
+    ) {
+        for (coords, mut stage, mut data) in &mut chunks {
+            if *stage != ChunkStage::Generated {
+                continue;
+            }
+            let _span = info_span!("decorate_chunk", coords = %coords.0).entered();
+            let started = Instant::now();
+            generator.0.decorate(*coords, &mut data);
+            stats.record(PipelineStage::Decoration, started.elapsed());
+            *stage = ChunkStage::Decorated;
+        }
+    }
+
+    /// Sends edited chunks back to `Decorated`, so they are re-lit and re-meshed
+    /// without being regenerated. A mesh task in flight is stale and gets dropped.
+    pub fn rewind_dirty(
+        mut commands: Commands,
+        mut manager: ResMut<TerrainManager>,
+        mut chunks: Query<(Entity, &mut ChunkStage, Option<&ChunkMeshTask>), With<ChunkDirty>>,
+    ) {
+        for (entity, mut stage, mesh_task) in &mut chunks {
+            if *stage < ChunkStage::Decorated {
+                // Not lit yet, the normal pipeline will pick the edits up
+                commands.entity(entity).remove::<ChunkDirty>();
+                continue;
+            }
+            if mesh_task.is_some() && manager.active_permits > 0 {
+                manager.active_permits -= 1;
+            }
+            *stage = ChunkStage::Decorated;
+            commands
+                .entity(entity)
+                .remove::<(ChunkDirty, ChunkMeshTask, ChunkMeshData)>();
+        }
+    }
+
+    /// Decorated -> Lit, the column heightmap is rebuilt along with the sky light
+    pub fn light(
+        mut commands: Commands,
+        registry: Res<VoxelRegistry>,
+        mut stats: ResMut<TerrainStats>,
+        mut chunks: Query<(Entity, &ChunkCoords, &mut ChunkStage, &ChunkData)>,
+    ) {
+        let mut solid = None;
+        for (entity, coords, mut stage, data) in &mut chunks {
+            if *stage != ChunkStage::Decorated {
+                continue;
+            }
+            let _span = info_span!("light_chunk", coords = %coords.0).entered();
+            let started = Instant::now();
+            let light = compute_skylight(data, &registry);
+            let heightmap = ChunkHeightmap::new(data, solid.get_or_insert_with(|| SolidTable::new(&registry)));
+            stats.record(PipelineStage::Lighting, started.elapsed());
+            commands.entity(entity).insert((light, heightmap));
+            *stage = ChunkStage::Lit;
+        }
+    }
+
+    /// Spawns meshing work for lit chunks, sharing the thread budget with generation.
+    /// Blocky meshes are packed on the worker too when `TerrainMaterial` is in use.
+    /// Smooth meshers wait until the loaded neighbours among the 8 around have their final
+    /// voxels. Neighbours that are not loaded read as Air, see `MissingNeighbours`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn mesh(
+        mut commands: Commands,
+        mut manager: ResMut<TerrainManager>,
+        registry: Res<VoxelRegistry>,
+        mesher: Res<MesherKind>,
+        chunk_map: Res<ChunkMap>,
+        terrain_material: Option<Res<TerrainMaterialHandle>>,
+        chunks: Query<(Entity, &ChunkCoords, &ChunkStage, &ChunkData, &ChunkLight), Without<ChunkMeshTask>>,
+        neighbours: Query<(&ChunkStage, &ChunkData)>,
+    ) {
+        let thread_pool = AsyncComputeTaskPool::get();
+        let mesher = *mesher;
+        let packed = terrain_material.is_some() && mesher.is_blocky();
+
+        for (entity, coords, stage, data, light) in &chunks {
+            if *stage != ChunkStage::Lit {
+                continue;
+            }
 
-                if vert_count == 0 {
-                    warn!("Chunk {:?} generated an empty mesh!", coords);
-                    commands.entity(entity).despawn();
+            // Copies of the neighbour chunks, index matches ChunkNeighborhood::OFFSETS
+            let mut around: [Option<ChunkData>; 9] = Default::default();
+            let mut missing = 0u16;
+            if mesher.needs_neighbors() {
+                let mut ready = true;
+                for (slot, (bit, offset)) in around.iter_mut().zip(ChunkNeighborhood::OFFSETS.into_iter().enumerate()) {
+                    if offset == IVec2::ZERO {
+                        continue;
+                    }
+                    let neighbour = chunk_map.get(coords.0 + offset).and_then(|e| neighbours.get(e).ok());
+                    match neighbour {
+                        Some((stage, _)) if *stage < ChunkStage::Decorated => {
+                            ready = false;
+                            break;
+                        }
+                        Some((_, neighbour_data)) => *slot = Some(neighbour_data.clone()),
+                        None => missing |= 1 << bit,
+                    }
+                }
+                if !ready {
                     continue;
                 }
+            }
 
-                let bevy_mesh = meshdata_to_bevy_mesh(mesh_data);
-                let mesh_handle = meshes.add(bevy_mesh);
+            if manager.active_permits >= manager.config.threads {
+                break;
+            }
+            manager.active_permits += 1;
 
-                // Debug material: Bright red and unlit (no lights needed)
-                let material_handle = materials.add(StandardMaterial {
-                    base_color: Color::srgb(1.0, 0.0, 0.0),
-                    unlit: true,
-                    ..default()
+            around[4] = Some(data.clone());
+            let light = light.clone();
+            let registry_clone = registry.clone();
+            let coords = coords.0;
+            let task = thread_pool.spawn(async move {
+                let _span = info_span!("mesh_chunk", coords = %coords, ?mesher).entered();
+                let started = Instant::now();
+                let neighborhood = ChunkNeighborhood::from_fn(|offset| {
+                    around[((offset.x + 1) + (offset.y + 1) * 3) as usize].as_ref()
                 });
+                let mesh = mesh_chunk(mesher, &neighborhood, &registry_clone);
+                let mesh = if packed {
+                    let solid = SolidTable::new(&registry_clone);
+                    let textures = FaceTextures::new(&registry_clone);
+                    ChunkMesh::Packed(pack_mesh(&mesh, neighborhood.center(), &light, &solid, &textures))
+                } else {
+                    ChunkMesh::Full(mesh)
+                };
+                (mesh, started.elapsed())
+            });
+
+            commands.entity(entity).insert(ChunkMeshTask(task));
+            if missing == 0 {
+                commands.entity(entity).remove::<MissingNeighbours>();
+            } else {
+                commands.entity(entity).insert(MissingNeighbours(missing));
+            }
+        }
+    }
+
+    /// Re-meshes chunks built without a neighbour once that neighbour is decorated,
+    /// so the Air read in its place does not leave a wall along the border
+    pub fn fill_missing_neighbours(
+        mut commands: Commands,
+        chunk_map: Res<ChunkMap>,
+        chunks: Query<(Entity, &ChunkCoords, &MissingNeighbours), Without<ChunkDirty>>,
+        stages: Query<&ChunkStage>,
+    ) {
+        for (entity, coords, missing) in &chunks {
+            let arrived = ChunkNeighborhood::OFFSETS.iter().enumerate().any(|(bit, offset)| {
+                missing.0 & (1 << bit) != 0
+                    && chunk_map
+                        .get(coords.0 + *offset)
+                        .and_then(|e| stages.get(e).ok())
+                        .is_some_and(|stage| *stage >= ChunkStage::Decorated)
+            });
+            if arrived {
+                commands.entity(entity).insert(ChunkDirty).remove::<MissingNeighbours>();
+            }
+        }
+    }
+
+    /// Lit -> Meshed: collects finished meshing tasks
+    pub fn poll_meshing(
+        mut commands: Commands,
+        mut manager: ResMut<TerrainManager>,
+        mut stats: ResMut<TerrainStats>,
+        mut tasks: Query<(Entity, &mut ChunkStage, &mut ChunkMeshTask)>,
+    ) {
+        for (entity, mut stage, mut task) in &mut tasks {
+            if let Some((mesh_data, elapsed)) = future::block_on(future::poll_once(&mut task.0)) {
+                if manager.active_permits > 0 {
+                    manager.active_permits -= 1;
+                }
+                stats.record_meshing(elapsed);
+                *stage = ChunkStage::Meshed;
+                commands
+                    .entity(entity)
+                    .insert(ChunkMeshData(mesh_data))
+                    .remove::<ChunkMeshTask>();
+            }
+        }
+    }
+
+    /// Meshed -> Uploaded: turns mesh data into a Bevy mesh asset.
+    /// Remeshed chunks reuse their existing mesh handle.
+    pub fn upload(
+        mut commands: Commands,
+        mut stats: ResMut<TerrainStats>,
+        mut chunks: Query<(Entity, &ChunkCoords, &mut ChunkStage, &mut ChunkMeshData, Option<&Mesh3d>)>,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+        terrain_material: Option<Res<TerrainMaterialHandle>>,
+    ) {
+        for (entity, coords, mut stage, mut mesh_data, mesh3d) in &mut chunks {
+            let _span = info_span!("upload_chunk", coords = %coords.0).entered();
+            *stage = ChunkStage::Uploaded;
+            commands.entity(entity).remove::<ChunkMeshData>();
 
-                let world_offset = Vec3::new(
-                    (coords.x * CHUNK_WIDTH) as f32,
-                    0.0,
-                    (coords.y * CHUNK_DEPTH) as f32,
-                );
+            let mesh_data = std::mem::take(&mut mesh_data.0);
+            let info = ChunkMeshInfo {
+                vertices: mesh_data.vertex_count(),
+                triangles: mesh_data.triangle_count(),
+            };
+            debug!("{:?} uploaded, {} vertices", coords, info.vertices);
+            commands.entity(entity).insert(info);
 
+            if info.vertices == 0 {
                 commands
                     .entity(entity)
-                    .insert((
-                        Chunk,
-                        coords,
-                        data,
-                        Mesh3d(mesh_handle),
-                        MeshMaterial3d(material_handle),
-                        Transform::from_translation(world_offset),
-                        GlobalTransform::default(),
-                        Visibility::default(),
-                        InheritedVisibility::default(),
-                        ViewVisibility::default(),
-                        Wireframe::default(),
-                    ))
-                    .remove::<ChunkCompute>();
+                    .remove::<(
+                        Mesh3d,
+                        MeshMaterial3d<StandardMaterial>,
+                        MeshMaterial3d<TerrainMaterial>,
+                    )>();
+                continue;
+            }
+
+            let started = Instant::now();
+            let is_packed = matches!(mesh_data, ChunkMesh::Packed(_));
+            let bevy_mesh = match mesh_data {
+                ChunkMesh::Full(mesh) => meshdata_to_bevy_mesh(mesh),
+                ChunkMesh::Packed(mesh) => packed_mesh_to_bevy_mesh(mesh),
+            };
+            stats.record(PipelineStage::Upload, started.elapsed());
+
+            if let Some(Mesh3d(handle)) = mesh3d {
+                let _ = meshes.insert(handle.id(), bevy_mesh);
+                continue;
             }
+
+            let mesh_handle = meshes.add(bevy_mesh);
+
+            if let Some(terrain_material) = terrain_material.as_ref().filter(|_| is_packed) {
+                let size = Vec3::new(CHUNK_WIDTH as f32, CHUNK_HEIGHT as f32, CHUNK_DEPTH as f32);
+                commands.entity(entity).insert((
+                    Mesh3d(mesh_handle),
+                    MeshMaterial3d(terrain_material.0.clone()),
+                    Aabb::from_min_max(Vec3::ZERO, size),
+                ));
+                continue;
+            }
+
+            // Debug material: Bright red and unlit (no lights needed)
+            let material_handle = materials.add(StandardMaterial {
+                base_color: Color::srgb(1.0, 0.0, 0.0),
+                unlit: true,
+                ..default()
+            });
+
+            commands.entity(entity).insert((Mesh3d(mesh_handle), MeshMaterial3d(material_handle)));
         }
     }
 }
@@ -1,13 +1,19 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-#[repr(u8)]
-pub enum Voxel {
-    Air = 0,
-    Solid = 1,
-}
+/// Block ID. What an ID means (name, solidity, textures) is up to the `VoxelRegistry`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Voxel(pub u8);
 
 impl Voxel {
+    /// ID 0 is always empty space
+    pub const AIR: Voxel = Voxel(0);
+    /// What the generator fills the ground with
+    pub const STONE: Voxel = Voxel(1);
+    /// What the generator fills the sea with. The last ID, so manifests can number
+    /// their own blocks from 2 up.
+    pub const WATER: Voxel = Voxel(255);
+
+    /// Block ID, as stored on disk
     #[inline]
-    pub fn is_solid(self) -> bool {
-        self != Voxel::Air
+    pub fn id(self) -> u8 {
+        self.0
     }
 }
@@ -0,0 +1,254 @@
+//! The terrain simulation under `MinimalPlugins`: no window, GPU or asset server.
+
+use std::time::Duration;
+
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+
+use engine::terrain::{
+    collision::raycast,
+    config::WorldConfig,
+    ecs::{
+        components::chunk::{ChunkCoords, ChunkData, ChunkDirty, ChunkLight, ChunkStage, MissingNeighbours},
+        resources::{chunk::ChunkMap, voxel::VoxelRegistry},
+    },
+    generator::{ChunkLoading, GeneratorKind, TerrainManager},
+    history::{EditHistory, EditRecorder},
+    stats::TerrainStats,
+    meshing::mesher::MesherKind,
+    plugins::{BlockSource, TerrainMaterialKind, VoxelTerrainPlugin},
+    structures::{VoxelAccess, WorldVoxels},
+    types::Voxel,
+};
+
+const MAX_FRAMES: usize = 2000;
+
+fn headless_app(generator: GeneratorKind) -> App {
+    let config = WorldConfig {
+        threads: Some(2),
+        generator,
+        // Dry land, the tests expect air right above the ground
+        sea_level: 0,
+        ..Default::default()
+    };
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins).add_plugins(
+        VoxelTerrainPlugin::new(config)
+            .with_blocks(BlockSource::BuiltIn)
+            .with_loading(ChunkLoading::Manual)
+            .without_rendering(),
+    );
+    app
+}
+
+fn all_lit(app: &mut App) -> bool {
+    let mut stages = app.world_mut().query::<&ChunkStage>();
+    stages.iter(app.world()).all(|stage| *stage == ChunkStage::Lit)
+}
+
+/// Steps the app until `done`, false if it takes more than `MAX_FRAMES`
+fn run_until(app: &mut App, mut done: impl FnMut(&mut App) -> bool) -> bool {
+    for _ in 0..MAX_FRAMES {
+        app.update();
+        if done(app) {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    false
+}
+
+/// Requests the chunks around `center` and waits until they are lit
+fn load_around(app: &mut App, center: IVec2, radius: i32) {
+    app.world_mut().resource_mut::<TerrainManager>().request_around(center, radius);
+    let expected = ((2 * radius + 1) * (2 * radius + 1)) as usize;
+    let loaded = run_until(app, |app| {
+        app.world().resource::<ChunkMap>().entities.len() == expected && all_lit(app)
+    });
+    assert!(loaded, "chunks around {center} were not lit in {MAX_FRAMES} frames");
+}
+
+#[test]
+fn generates_chunks_around_a_point() {
+    let mut app = headless_app(GeneratorKind::default());
+    load_around(&mut app, IVec2::new(3, -2), 1);
+
+    let mut coords = app.world_mut().query::<&ChunkCoords>();
+    let mut loaded: Vec<IVec2> = coords.iter(app.world()).map(|c| c.0).collect();
+    loaded.sort_by_key(|c| (c.y, c.x));
+    assert_eq!(loaded.first(), Some(&IVec2::new(2, -3)));
+    assert_eq!(loaded.last(), Some(&IVec2::new(4, -1)));
+
+    // Nothing outside the request is spawned with manual loading
+    for _ in 0..10 {
+        app.update();
+    }
+    assert_eq!(app.world().resource::<ChunkMap>().entities.len(), 9);
+
+    let stats = app.world().resource::<TerrainStats>();
+    assert_eq!(stats.generated, 9);
+    assert_eq!(stats.generation.len(), 9);
+}
+
+#[test]
+fn edits_relight_chunks() {
+    let mut app = headless_app(GeneratorKind::Flat { height: 20 });
+    load_around(&mut app, IVec2::ZERO, 1);
+
+    let placed = app
+        .world_mut()
+        .run_system_once(|mut voxels: WorldVoxels| {
+            assert_eq!(voxels.get_voxel(IVec3::new(-5, 19, 7)), Some(Voxel::STONE));
+            assert_eq!(voxels.get_voxel(IVec3::new(-5, 20, 7)), Some(Voxel::AIR));
+            voxels.set_voxel(IVec3::new(-5, 30, 7), Voxel::STONE)
+        })
+        .unwrap();
+    assert!(placed);
+
+    assert!(run_until(&mut app, all_lit));
+    let entity = app.world().resource::<ChunkMap>().get(IVec2::new(-1, 0)).unwrap();
+    let light = app.world().get::<ChunkLight>(entity).unwrap();
+    // Local x 11 of chunk (-1, 0) is world x -5
+    assert_eq!(light.get(11, 31, 7), 15);
+    assert!(light.get(11, 29, 7) < 15);
+}
+
+#[test]
+fn border_edits_dirty_the_neighbours() {
+    let mut app = headless_app(GeneratorKind::Flat { height: 20 });
+    load_around(&mut app, IVec2::ZERO, 1);
+
+    let dirty = |app: &mut App, pos: IVec3| {
+        app.world_mut()
+            .run_system_once(move |mut voxels: WorldVoxels| voxels.set_voxel(pos, Voxel::STONE))
+            .unwrap();
+        let mut dirty: Vec<IVec2> = app
+            .world_mut()
+            .query_filtered::<&ChunkCoords, With<ChunkDirty>>()
+            .iter(app.world())
+            .map(|c| c.0)
+            .collect();
+        dirty.sort_by_key(|c| (c.y, c.x));
+        assert!(run_until(app, all_lit));
+        dirty
+    };
+    assert_eq!(dirty(&mut app, IVec3::new(5, 30, 5)), vec![IVec2::ZERO]);
+    assert_eq!(dirty(&mut app, IVec3::new(0, 30, 5)), vec![IVec2::new(-1, 0), IVec2::ZERO]);
+    assert_eq!(
+        dirty(&mut app, IVec3::new(15, 30, -1)),
+        vec![IVec2::new(0, -1), IVec2::new(1, -1), IVec2::new(0, 0), IVec2::new(1, 0)]
+    );
+}
+
+#[test]
+fn raycasts_against_loaded_terrain() {
+    let mut app = headless_app(GeneratorKind::Flat { height: 20 });
+    load_around(&mut app, IVec2::ZERO, 1);
+
+    let hit = app
+        .world_mut()
+        .run_system_once(|voxels: WorldVoxels, registry: Res<VoxelRegistry>| {
+            raycast(&voxels, &registry, Vec3::new(8.5, 60.0, -8.5), Vec3::NEG_Y, 100.0)
+        })
+        .unwrap()
+        .expect("the ray hits the ground");
+    assert_eq!((hit.position, hit.normal), (IVec3::new(8, 19, -9), IVec3::Y));
+}
+
+#[test]
+fn undo_relights_like_an_edit() {
+    let mut app = headless_app(GeneratorKind::Flat { height: 20 });
+    app.init_resource::<EditHistory>();
+    load_around(&mut app, IVec2::ZERO, 1);
+
+    let roof = IVec3::new(4, 30, 4);
+    app.world_mut()
+        .run_system_once(move |mut voxels: WorldVoxels, mut history: ResMut<EditHistory>| {
+            let mut recorder = EditRecorder::new(&mut voxels);
+            recorder.set_voxel(roof, Voxel::STONE);
+            history.push(recorder.finish());
+        })
+        .unwrap();
+    assert!(run_until(&mut app, all_lit));
+    let entity = app.world().resource::<ChunkMap>().get(IVec2::ZERO).unwrap();
+    assert!(app.world().get::<ChunkLight>(entity).unwrap().get(4, 29, 4) < 15);
+
+    let undone = app
+        .world_mut()
+        .run_system_once(|mut voxels: WorldVoxels, mut history: ResMut<EditHistory>| history.undo(&mut voxels))
+        .unwrap();
+    assert_eq!(undone, Some(vec![IVec2::ZERO]));
+    assert!(app.world().get::<ChunkDirty>(entity).is_some());
+
+    assert!(run_until(&mut app, all_lit));
+    assert_eq!(app.world().get::<ChunkLight>(entity).unwrap().get(4, 29, 4), 15);
+}
+
+#[test]
+fn scatters_structures_from_the_asset_folder() {
+    let config = WorldConfig {
+        threads: Some(2),
+        structures: vec!["structures/marker.structure.ron".into()],
+        ..Default::default()
+    };
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin { file_path: "tests/assets".into(), ..default() },
+        VoxelTerrainPlugin::new(config)
+            .with_blocks(BlockSource::BuiltIn)
+            .with_loading(ChunkLoading::Manual)
+            .without_rendering(),
+    ));
+    assert_eq!(app.world().resource::<TerrainManager>().structures.len(), 1);
+    load_around(&mut app, IVec2::ZERO, 2);
+
+    // Pasted on the surface by the decoration stage
+    let mut chunks = app.world_mut().query::<&ChunkData>();
+    let pillars = chunks
+        .iter(app.world())
+        .map(|chunk| chunk.voxels.iter().filter(|voxel| **voxel == Voxel(9)).count())
+        .filter(|count| *count > 0)
+        .count();
+    assert!(pillars > 0, "no structure in 25 chunks");
+}
+
+#[test]
+fn smooth_meshes_reach_the_edge_of_the_loaded_area() {
+    let config = WorldConfig {
+        threads: Some(2),
+        generator: GeneratorKind::Flat { height: 20 },
+        ..Default::default()
+    };
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+        .init_asset::<Mesh>()
+        .init_asset::<StandardMaterial>()
+        .add_plugins(
+            VoxelTerrainPlugin::new(config)
+                .with_blocks(BlockSource::BuiltIn)
+                .with_loading(ChunkLoading::Manual)
+                .with_mesher(MesherKind::SurfaceNets)
+                .with_material(TerrainMaterialKind::Debug),
+        );
+    let uploaded = |app: &mut App, coords: IVec2| {
+        let entity = app.world().resource::<ChunkMap>().get(coords)?;
+        let stage = *app.world().get::<ChunkStage>(entity)?;
+        (stage == ChunkStage::Uploaded).then(|| app.world().get::<MissingNeighbours>(entity).copied())
+    };
+
+    app.world_mut().resource_mut::<TerrainManager>().request_around(IVec2::ZERO, 1);
+    let around: Vec<IVec2> = (-1..=1).flat_map(|z| (-1..=1).map(move |x| IVec2::new(x, z))).collect();
+    assert!(run_until(&mut app, |app| around.iter().all(|c| uploaded(app, *c).is_some())));
+    // Only the center had all of its neighbours, (1, 0) misses the three chunks at x = 2
+    assert_eq!(uploaded(&mut app, IVec2::ZERO), Some(None));
+    assert_eq!(uploaded(&mut app, IVec2::new(1, 0)), Some(Some(MissingNeighbours(0b1_0010_0100))));
+
+    // Once (2, 0) arrives, (1, 0) is meshed again against it
+    app.world_mut().resource_mut::<TerrainManager>().request(IVec2::new(2, 0));
+    assert!(run_until(&mut app, |app| {
+        uploaded(app, IVec2::new(2, 0)).is_some()
+            && uploaded(app, IVec2::new(1, 0)) == Some(Some(MissingNeighbours(0b1_0000_0100)))
+    }));
+}
+
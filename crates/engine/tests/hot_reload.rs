@@ -0,0 +1,101 @@
+//! Hot reload through the asset server's file watcher: edits on disk reach the running world.
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+
+use assets::blocks::{BlockManifest, CompiledBlock, MANIFEST_VERSION};
+use engine::terrain::{
+    config::WorldConfig,
+    ecs::resources::voxel::VoxelRegistry,
+    generator::{ChunkLoading, TerrainManager},
+    hot_reload::{BiomeTableHandle, BlockManifestHandle},
+    plugins::{VoxelTerrainPlugin, BLOCK_MANIFEST_PATH},
+    types::Voxel,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+fn write_manifest(root: &Path, stone: &str) {
+    let manifest = BlockManifest {
+        version: MANIFEST_VERSION,
+        source_hash: String::new(),
+        texture_array: String::new(),
+        tile_size: 16,
+        mip_levels: 1,
+        layers: 0,
+        blocks: vec![CompiledBlock { id: 1, name: stone.into(), solid: true, smooth: false, sound: None, faces: None }],
+    };
+    std::fs::write(root.join(BLOCK_MANIFEST_PATH), manifest.to_ron()).unwrap();
+}
+
+fn write_biomes(root: &Path, name: &str) {
+    let table = format!("(biomes: [(name: \"{name}\", surface: 1, subsurface: 1)])");
+    std::fs::write(root.join("world.biomes.ron"), table).unwrap();
+}
+
+/// Steps the app until `done`, false if it takes longer than `TIMEOUT`
+fn run_until(app: &mut App, mut done: impl FnMut(&App) -> bool) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < TIMEOUT {
+        app.update();
+        if done(app) {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    false
+}
+
+fn stone_name(app: &App) -> String {
+    app.world().resource::<VoxelRegistry>().get(&Voxel::STONE).name.clone()
+}
+
+fn biome_name(app: &App) -> Option<String> {
+    Some(app.world().resource::<TerrainManager>().biome(0, 0)?.name.clone())
+}
+
+#[test]
+fn edited_manifest_and_biomes_reach_the_world() {
+    let root = std::env::temp_dir().join(format!("hot-reload-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join(BLOCK_MANIFEST_PATH).parent().unwrap()).unwrap();
+    write_manifest(&root, "Granite");
+    write_biomes(&root, "Plains");
+
+    let config = WorldConfig { biomes: Some("world.biomes.ron".into()), ..Default::default() };
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin {
+            file_path: root.to_string_lossy().into_owned(),
+            watch_for_changes_override: Some(true),
+            ..default()
+        },
+        VoxelTerrainPlugin::new(config).with_loading(ChunkLoading::Manual).without_rendering(),
+    ));
+    // Done by `App::run`, the worldgen handles are taken here
+    app.finish();
+    app.cleanup();
+
+    // Read from the asset folder at startup
+    assert_eq!(stone_name(&app), "Granite");
+    assert_eq!(biome_name(&app).as_deref(), Some("Plains"));
+    // Edits are only reported for assets the server has loaded
+    assert!(run_until(&mut app, |app| {
+        let server = app.world().resource::<AssetServer>();
+        server.is_loaded(&app.world().resource::<BlockManifestHandle>().0)
+            && server.is_loaded(&app.world().resource::<BiomeTableHandle>().0)
+    }));
+
+    write_manifest(&root, "Basalt");
+    write_biomes(&root, "Tundra");
+    assert!(run_until(&mut app, |app| stone_name(app) == "Basalt"), "manifest edit was not reloaded");
+    assert!(
+        run_until(&mut app, |app| biome_name(app).as_deref() == Some("Tundra")),
+        "biome table edit was not reloaded"
+    );
+
+    std::fs::remove_dir_all(root).unwrap();
+}
@@ -0,0 +1,186 @@
+//! A server and two clients in one process, talking over TCP on localhost.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use engine::net::{
+    NetClient, NetClientPlugin, NetServer, NetServerPlugin, TcpClientTransport, TcpServerTransport,
+};
+use engine::terrain::{
+    config::WorldConfig,
+    ecs::{
+        components::chunk::{ChunkData, ChunkStage},
+        resources::chunk::ChunkMap,
+    },
+    generator::{ChunkLoading, GeneratorKind},
+    plugins::{BlockSource, VoxelTerrainPlugin},
+    types::Voxel,
+};
+
+const MAX_FRAMES: usize = 3000;
+
+fn terrain() -> VoxelTerrainPlugin {
+    let config = WorldConfig {
+        seed: 9,
+        threads: Some(2),
+        generator: GeneratorKind::Flat { height: 20 },
+        // Dry land, the edit checks expect air right above the ground
+        sea_level: 0,
+        ..Default::default()
+    };
+    VoxelTerrainPlugin::new(config)
+        .with_blocks(BlockSource::BuiltIn)
+        .with_loading(ChunkLoading::Manual)
+        .without_rendering()
+}
+
+fn server_app(transport: TcpServerTransport) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, terrain(), NetServerPlugin))
+        .insert_resource(NetServer::new(transport));
+    app
+}
+
+fn client_app(transport: TcpClientTransport) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, terrain(), NetClientPlugin))
+        .insert_resource(NetClient::new(transport, 1));
+    app
+}
+
+struct Session {
+    server: App,
+    clients: [App; 2],
+}
+
+impl Session {
+    fn start() -> Self {
+        let transport = TcpServerTransport::bind("127.0.0.1:0").unwrap();
+        let addr = transport.local_addr().unwrap();
+        Self {
+            server: server_app(transport),
+            clients: [
+                client_app(TcpClientTransport::connect(addr).unwrap()),
+                client_app(TcpClientTransport::connect(addr).unwrap()),
+            ],
+        }
+    }
+
+    /// Steps every app until `done`, panics after `MAX_FRAMES`
+    fn run_until(&mut self, what: &str, mut done: impl FnMut(&mut Session) -> bool) {
+        for _ in 0..MAX_FRAMES {
+            self.server.update();
+            for client in &mut self.clients {
+                client.update();
+            }
+            if done(self) {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        panic!("{what} did not happen in {MAX_FRAMES} frames");
+    }
+}
+
+/// Coordinates of the chunks a client holds, once they are all lit
+fn lit_chunks(app: &mut App) -> Option<Vec<IVec2>> {
+    let mut stages = app.world_mut().query::<&ChunkStage>();
+    if !stages.iter(app.world()).all(|stage| *stage == ChunkStage::Lit) {
+        return None;
+    }
+    let mut chunks: Vec<IVec2> = app.world().resource::<ChunkMap>().entities.keys().copied().collect();
+    chunks.sort_by_key(|c| (c.y, c.x));
+    Some(chunks)
+}
+
+fn voxel_at(app: &App, chunk: IVec2, local: IVec3) -> Option<Voxel> {
+    let entity = app.world().resource::<ChunkMap>().get(chunk)?;
+    let data = app.world().get::<ChunkData>(entity)?;
+    Some(data.get(local.x, local.y, local.z))
+}
+
+#[test]
+fn streams_chunks_and_replicates_edits() {
+    let mut session = Session::start();
+
+    // Both clients get the 3x3 chunks around the origin, lit locally
+    session.run_until("initial streaming", |s| {
+        s.clients.iter_mut().all(|c| lit_chunks(c).is_some_and(|chunks| chunks.len() == 9))
+    });
+    assert_eq!(session.server.world().resource::<NetServer>().client_count(), 2);
+    for client in &session.clients {
+        let net = client.world().resource::<NetClient>();
+        assert_eq!(net.seed, Some(9));
+        assert!(net.id.is_some());
+        assert_eq!(voxel_at(client, IVec2::ZERO, IVec3::new(3, 19, 3)), Some(Voxel::STONE));
+    }
+
+    // An edit by the first client shows up on the second one
+    session.clients[0].world_mut().resource_mut::<NetClient>().request_edit(IVec3::new(3, 20, 3), Voxel::STONE);
+    session.run_until("edit broadcast", |s| {
+        voxel_at(&s.clients[1], IVec2::ZERO, IVec3::new(3, 20, 3)) == Some(Voxel::STONE)
+    });
+    assert_eq!(voxel_at(&session.clients[0], IVec2::ZERO, IVec3::new(3, 20, 3)), Some(Voxel::STONE));
+    assert_eq!(session.server.world().resource::<NetServer>().revision(IVec2::ZERO), 1);
+    for client in &session.clients {
+        assert_eq!(client.world().resource::<NetClient>().revision(IVec2::ZERO), Some(1));
+    }
+
+    // A burst of edits over the threshold comes back as a full chunk
+    session.server.world_mut().resource_mut::<NetServer>().full_resend_changes = 4;
+    {
+        let mut net = session.clients[0].world_mut().resource_mut::<NetClient>();
+        for x in 0..12 {
+            net.request_edit(IVec3::new(x, 21, -8), Voxel::STONE);
+        }
+    }
+    session.run_until("burst of edits", |s| {
+        (0..12).all(|x| voxel_at(&s.clients[1], IVec2::new(0, -1), IVec3::new(x, 21, 8)) == Some(Voxel::STONE))
+    });
+    let revision = session.server.world().resource::<NetServer>().revision(IVec2::new(0, -1));
+    for client in &session.clients {
+        let net = client.world().resource::<NetClient>();
+        assert_eq!(net.revision(IVec2::new(0, -1)), Some(revision));
+        assert_eq!(net.resyncs, 0);
+    }
+
+    // Out of the world or out of view: refused, nothing changes
+    {
+        let mut net = session.clients[1].world_mut().resource_mut::<NetClient>();
+        net.request_edit(IVec3::new(3, 500, 3), Voxel::STONE);
+        net.request_edit(IVec3::new(400, 20, 400), Voxel::STONE);
+        net.request_edit(IVec3::new(4, 20, 4), Voxel(200));
+    }
+    session.run_until("edit rejections", |s| {
+        s.clients[1].world().resource::<NetClient>().rejected_edits.len() == 3
+    });
+    assert_eq!(voxel_at(&session.clients[0], IVec2::ZERO, IVec3::new(4, 20, 4)), Some(Voxel::AIR));
+
+    // Moving streams the new area in and drops what is left behind
+    session.clients[0].world_mut().resource_mut::<NetClient>().set_position(IVec2::new(5, 0));
+    session.run_until("streaming after a move", |s| {
+        lit_chunks(&mut s.clients[0]).is_some_and(|chunks| {
+            chunks.contains(&IVec2::new(6, 1)) && !chunks.contains(&IVec2::new(-1, 0)) && chunks.len() == 9
+        })
+    });
+    // The other client is unaffected
+    assert_eq!(lit_chunks(&mut session.clients[1]).map(|c| c.len()), Some(9));
+
+    // Once nobody is around, the server unloads the chunks but keeps the edited ones
+    session.clients[1].world_mut().resource_mut::<NetClient>().set_position(IVec2::new(5, 0));
+    session.run_until("server unloading", |s| {
+        let chunks = &s.server.world().resource::<ChunkMap>().entities;
+        !chunks.contains_key(&IVec2::new(-1, 1)) && !chunks.contains_key(&IVec2::new(1, 0))
+    });
+    let chunks = &session.server.world().resource::<ChunkMap>().entities;
+    assert!(chunks.contains_key(&IVec2::ZERO) && chunks.contains_key(&IVec2::new(0, -1)));
+    assert!(chunks.contains_key(&IVec2::new(6, 1)));
+
+    // Coming back streams the edit again, and the neighbours are generated anew
+    session.clients[1].world_mut().resource_mut::<NetClient>().set_position(IVec2::ZERO);
+    session.run_until("streaming back", |s| {
+        lit_chunks(&mut s.clients[1]).is_some_and(|chunks| chunks.contains(&IVec2::new(-1, 1)) && chunks.len() == 9)
+    });
+    assert_eq!(voxel_at(&session.clients[1], IVec2::ZERO, IVec3::new(3, 20, 3)), Some(Voxel::STONE));
+}
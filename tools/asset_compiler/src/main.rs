@@ -1,3 +1,30 @@
-fn main() {
-    println!("asset compiler (stub)");
+use std::path::Path;
+use std::process::ExitCode;
+
+use assets::compiler::compile;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [source, out] = args.as_slice() else {
+        eprintln!("usage: asset_compiler <block content dir> <output dir>");
+        return ExitCode::FAILURE;
+    };
+
+    match compile(Path::new(source), Path::new(out)) {
+        Ok(report) if report.up_to_date => {
+            println!("{out} is up to date ({} blocks, {} layers)", report.blocks, report.layers);
+            ExitCode::SUCCESS
+        }
+        Ok(report) => {
+            println!(
+                "compiled {} blocks into {} layers ({} textures built, {} from cache) to {out}",
+                report.blocks, report.layers, report.textures_built, report.textures_cached
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
 }
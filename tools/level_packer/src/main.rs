@@ -1,3 +1,194 @@
-fn main() {
-    println!("level packer (stub)");
+//! Bakes a saved world (region files plus entity/scene files) into one level archive.
+
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use bevy::math::IVec2;
+use engine::cli::Args;
+use engine::terrain::{
+    config::{WorldConfig, WORLD_CONFIG_FILE},
+    ecs::components::chunk::ChunkCoords,
+    generator::TerrainManager,
+    plugins::default_asset_root,
+    storage::{region_of, ArchiveEntry, ArchiveWriter, LevelArchive, Region, RegionStore},
+};
+
+const USAGE: &str = "usage:
+  level_packer pack --world <dir> --out <file.vxl> [--min <x,z> --max <x,z>] [--seed <i32>] [--assets <dir>]
+  level_packer verify --archive <file.vxl>
+  level_packer unpack --archive <file.vxl> --out <dir>
+
+Chunks identical to what the generator produces are dropped, the game regenerates them
+when loading the level (`sandbox --level <file.vxl>`). The generator settings come from the world's world.ron, --seed
+replaces its seed. A world without world.ron needs --seed and uses the default generator.
+Biome tables and structures are read from --assets, the game's asset folder by default.";
+
+fn is_region_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with("r.") && n.ends_with(".vxr"))
+}
+
+/// Every non-region file under `dir`, as paths relative to `root` with `/` separators
+fn level_files(root: &Path, dir: &Path, out: &mut Vec<String>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            level_files(root, &path, out)?;
+        } else if !is_region_file(&path) {
+            let relative = path.strip_prefix(root).expect("walked from root");
+            let parts: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+            out.push(parts.join("/"));
+        }
+    }
+    Ok(())
+}
+
+fn pack(args: &Args) -> Result<(), String> {
+    let world: String = args.required("world")?;
+    let out: String = args.required("out")?;
+    let bounds = match (args.get::<String>("min")?, args.get::<String>("max")?) {
+        (None, None) => None,
+        (Some(_), Some(_)) => {
+            let (a, b) = (args.coords("min")?, args.coords("max")?);
+            Some((a.min(b), a.max(b)))
+        }
+        _ => return Err("--min and --max go together".to_string()),
+    };
+    let config_path = Path::new(&world).join(WORLD_CONFIG_FILE);
+    let saved = config_path.exists().then(|| WorldConfig::load(&config_path)).transpose()?;
+    let config = match (saved, args.get::<i32>("seed")?) {
+        (Some(config), seed) => Some(WorldConfig { seed: seed.unwrap_or(config.seed), ..config }),
+        (None, Some(seed)) => Some(WorldConfig { seed, ..Default::default() }),
+        (None, None) => None,
+    };
+    let seed = config.as_ref().map(|c| c.seed);
+    let assets = args.get::<String>("assets")?.map_or_else(default_asset_root, PathBuf::from);
+    let generator = config.map(|c| TerrainManager::for_world(&WorldConfig { threads: Some(1), ..c }, &assets));
+
+    let store = RegionStore::new(&world);
+    let file = File::create(&out).map_err(|e| format!("cannot create {out}: {e}"))?;
+    let mut writer = ArchiveWriter::new(BufWriter::new(file), seed).map_err(|e| e.to_string())?;
+
+    let (mut packed, mut out_of_bounds, mut regenerable) = (0, 0, 0);
+    for region in store.regions().map_err(|e| format!("cannot read {world}: {e}"))? {
+        let contents = store.load_region(region).map_err(|e| format!("region {region}: {e}"))?;
+        for coords in contents.chunks(region) {
+            if bounds.is_some_and(|(min, max)| coords.cmplt(min).any() || coords.cmpgt(max).any()) {
+                out_of_bounds += 1;
+                continue;
+            }
+            let chunk = contents
+                .get(coords)
+                .map_err(|e| e.to_string())?
+                .expect("listed by Region::chunks");
+
+            if let Some(generator) = &generator {
+                let mut generated = generator.run(ChunkCoords(coords));
+                generator.decorate(ChunkCoords(coords), &mut generated);
+                if generated.voxels == chunk.voxels {
+                    regenerable += 1;
+                    continue;
+                }
+            }
+            writer.add_chunk(coords, &chunk).map_err(|e| e.to_string())?;
+            packed += 1;
+        }
+    }
+
+    let mut files = Vec::new();
+    level_files(Path::new(&world), Path::new(&world), &mut files).map_err(|e| e.to_string())?;
+    files.sort();
+    for name in &files {
+        let bytes = fs::read(Path::new(&world).join(name)).map_err(|e| format!("{name}: {e}"))?;
+        writer.add_file(name, &bytes).map_err(|e| e.to_string())?;
+    }
+
+    writer.finish().map_err(|e| format!("cannot write {out}: {e}"))?;
+    println!(
+        "packed {packed} chunks and {} files into {out} ({out_of_bounds} out of bounds, {regenerable} left to the generator)",
+        files.len()
+    );
+    Ok(())
+}
+
+fn open(args: &Args) -> Result<LevelArchive<BufReader<File>>, String> {
+    let path: String = args.required("archive")?;
+    let file = File::open(&path).map_err(|e| format!("cannot open {path}: {e}"))?;
+    LevelArchive::open(BufReader::new(file)).map_err(|e| format!("{path}: {e}"))
+}
+
+fn verify(args: &Args) -> Result<(), String> {
+    let mut archive = open(args)?;
+    archive.verify().map_err(|e| e.to_string())?;
+
+    let chunks = archive
+        .entries()
+        .iter()
+        .filter(|e| matches!(e.entry, ArchiveEntry::Chunk(_)))
+        .count();
+    let seed = archive.seed().map_or("none".to_string(), |s| s.to_string());
+    println!(
+        "ok: {chunks} chunks, {} files, seed {seed}",
+        archive.entries().len() - chunks
+    );
+    Ok(())
+}
+
+fn unpack(args: &Args) -> Result<(), String> {
+    let out = PathBuf::from(args.required::<String>("out")?);
+    let mut archive = open(args)?;
+    let store = RegionStore::new(&out);
+
+    let mut regions: std::collections::BTreeMap<(i32, i32), Region> = Default::default();
+    for index in 0..archive.entries().len() {
+        let bytes = archive.read(index).map_err(|e| e.to_string())?;
+        match archive.entries()[index].entry.clone() {
+            ArchiveEntry::Chunk(coords) => {
+                let region = region_of(coords);
+                regions.entry((region.x, region.y)).or_default().insert_encoded(coords, bytes);
+            }
+            ArchiveEntry::File(name) => {
+                // Archives come from elsewhere, don't write outside `out`
+                if name.split('/').any(|part| part == ".." || part.is_empty()) {
+                    return Err(format!("refusing to unpack suspicious path {name}"));
+                }
+                let path = out.join(&name);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                fs::write(&path, bytes).map_err(|e| format!("{}: {e}", path.display()))?;
+            }
+        }
+    }
+
+    for ((x, z), contents) in &regions {
+        store
+            .save_region(IVec2::new(*x, *z), contents)
+            .map_err(|e| format!("region {x},{z}: {e}"))?;
+    }
+    println!("unpacked {} entries into {}", archive.entries().len(), out.display());
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let mut raw = std::env::args().skip(1);
+    let command = raw.next();
+
+    let result = Args::parse(raw).and_then(|args| match command.as_deref() {
+        Some("pack") => pack(&args),
+        Some("verify") => verify(&args),
+        Some("unpack") => unpack(&args),
+        _ => Err(USAGE.to_string()),
+    });
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{message}");
+            ExitCode::FAILURE
+        }
+    }
 }
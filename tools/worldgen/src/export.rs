@@ -0,0 +1,60 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use engine::cli::Args;
+use engine::terrain::{
+    ecs::resources::voxel::VoxelRegistry,
+    export::{build_terrain_mesh, write_glb, write_mtl, write_obj},
+    meshing::mesher::MesherKind,
+};
+
+pub const USAGE: &str = "worldgen export --seed <i32> --from <x,z> --to <x,z> --out <file.obj|file.glb> [--mesher greedy|binary-greedy|surface-nets|per-block]";
+
+fn parse_mesher(name: &str) -> Result<MesherKind, String> {
+    match name {
+        "greedy" => Ok(MesherKind::Greedy),
+        "binary-greedy" => Ok(MesherKind::BinaryGreedy),
+        "surface-nets" => Ok(MesherKind::SurfaceNets),
+        "per-block" => Ok(MesherKind::PerBlock),
+        other => Err(format!("unknown mesher `{other}`")),
+    }
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let seed: i32 = args.required("seed")?;
+    let from = args.coords("from")?;
+    let to = args.coords("to")?;
+    let out: String = args.required("out")?;
+    let mesher = parse_mesher(&args.get_or("mesher", "binary-greedy".to_string())?)?;
+
+    let (min, max) = (from.min(to), from.max(to));
+    let registry = VoxelRegistry::default();
+    let mesh = build_terrain_mesh(seed, min, max, mesher, &registry);
+
+    let path = Path::new(&out);
+    let create = |path: &Path| {
+        File::create(path)
+            .map(BufWriter::new)
+            .map_err(|e| format!("cannot create {}: {e}", path.display()))
+    };
+    let io_err = |e: std::io::Error| format!("cannot write {}: {e}", path.display());
+
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("obj") => {
+            let mtl_path = path.with_extension("mtl");
+            let mtl_name = mtl_path.file_name().and_then(|n| n.to_str()).map(str::to_string);
+            write_obj(&mesh, &registry, mtl_name.as_deref(), &mut create(path)?).map_err(io_err)?;
+            write_mtl(&mesh, &registry, &mut create(&mtl_path)?).map_err(io_err)?;
+        }
+        Some("glb") => write_glb(&mesh, &registry, &mut create(path)?).map_err(io_err)?,
+        _ => return Err(format!("--out must end in .obj or .glb, got `{out}`")),
+    }
+
+    println!(
+        "exported chunks {min}..={max} ({} vertices, {} triangles) to {out}",
+        mesh.positions.len(),
+        mesh.indices.len() / 3
+    );
+    Ok(())
+}
@@ -0,0 +1,56 @@
+//! Offline world tools: run the terrain generator without a window or GPU.
+
+mod export;
+mod pregen;
+mod preview;
+
+use std::process::ExitCode;
+
+use engine::cli::Args;
+use engine::terrain::generator::GeneratorParams;
+
+const GENERATOR_OPTIONS: &str = "generator options: --frequency <f64> --octaves <n> --persistence <f64> --lacunarity <f64> --strength <f64> --height-scale <f32> --base-height <f32> --height-curve <f32>";
+
+fn usage() -> String {
+    format!(
+        "usage:\n  {}\n  {}\n  {}\n{GENERATOR_OPTIONS}",
+        export::USAGE,
+        pregen::USAGE,
+        preview::USAGE
+    )
+}
+
+/// Noise settings from the command line, defaults for the ones not given
+pub fn generator_params(args: &Args) -> Result<GeneratorParams, String> {
+    let defaults = GeneratorParams::default();
+    Ok(GeneratorParams {
+        frequency: args.get_or("frequency", defaults.frequency)?,
+        octaves: args.get_or("octaves", defaults.octaves)?,
+        persistence: args.get_or("persistence", defaults.persistence)?,
+        lacunarity: args.get_or("lacunarity", defaults.lacunarity)?,
+        strength: args.get_or("strength", defaults.strength)?,
+        height_scale: args.get_or("height-scale", defaults.height_scale)?,
+        base_height: args.get_or("base-height", defaults.base_height)?,
+        height_curve: args.get_or("height-curve", defaults.height_curve)?,
+    })
+}
+
+fn main() -> ExitCode {
+    let mut raw = std::env::args().skip(1);
+    let command = raw.next();
+
+    let result = Args::parse(raw).and_then(|args| match command.as_deref() {
+        Some("export") => export::run(&args),
+        Some("pregen") => pregen::run(&args),
+        Some("preview") => preview::run(&args),
+        _ => Err(usage()),
+    });
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{message}");
+            ExitCode::FAILURE
+        }
+    }
+}
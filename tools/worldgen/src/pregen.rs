@@ -0,0 +1,136 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bevy::math::IVec2;
+use engine::cli::Args;
+use engine::terrain::{
+    config::WorldConfig,
+    ecs::components::chunk::ChunkCoords,
+    generator::{GeneratorKind, TerrainManager},
+    storage::{region_of, Region, RegionStore, REGION_SIZE},
+};
+use crate::generator_params;
+
+pub const USAGE: &str = "worldgen pregen --seed <i32> --radius <chunks> --out <dir> [--threads <n>] [generator options]";
+
+#[derive(Default)]
+struct Timings {
+    generate: Vec<Duration>,
+    write: Duration,
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    sorted[((sorted.len() - 1) as f64 * p).round() as usize]
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let seed: i32 = args.required("seed")?;
+    let radius: i32 = args.required("radius")?;
+    let store = RegionStore::new(args.required::<String>("out")?);
+    let default_threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let threads: usize = args.get_or("threads", default_threads)?.max(1);
+
+    // The world settings are saved next to the regions, the game reopens it with them
+    let config = WorldConfig {
+        seed,
+        view_radius: radius,
+        threads: Some(threads),
+        generator: GeneratorKind::Noise(generator_params(args)?),
+        ..Default::default()
+    };
+    let saved = config.open_world(store.root())?;
+    if saved.seed != config.seed || saved.generator != config.generator {
+        return Err(format!(
+            "{} holds a world created with other settings (seed {}), pick another --out",
+            store.root().display(),
+            saved.seed
+        ));
+    }
+    let manager = TerrainManager::from_config(&config);
+
+    // Every worker owns whole regions, so each file is written exactly once
+    let min = region_of(IVec2::splat(-radius));
+    let max = region_of(IVec2::splat(radius));
+    let regions: Vec<IVec2> = (min.y..=max.y)
+        .flat_map(|z| (min.x..=max.x).map(move |x| IVec2::new(x, z)))
+        .collect();
+
+    let next = AtomicUsize::new(0);
+    let timings = Mutex::new(Timings::default());
+    let failure = Mutex::new(None);
+    let started = Instant::now();
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                let mut local = Timings::default();
+                while let Some(&region) = regions.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let mut contents = Region::new();
+                    let origin = region * REGION_SIZE;
+                    for dz in 0..REGION_SIZE {
+                        for dx in 0..REGION_SIZE {
+                            let chunk = origin + IVec2::new(dx, dz);
+                            if chunk.x.abs() > radius || chunk.y.abs() > radius {
+                                continue;
+                            }
+                            let t = Instant::now();
+                            let mut data = manager.run(ChunkCoords(chunk));
+                            manager.decorate(ChunkCoords(chunk), &mut data);
+                            local.generate.push(t.elapsed());
+                            contents.insert(chunk, &data);
+                        }
+                    }
+
+                    let t = Instant::now();
+                    if let Err(e) = store.save_region(region, &contents) {
+                        failure.lock().unwrap().get_or_insert(format!("region {region}: {e}"));
+                        return;
+                    }
+                    local.write += t.elapsed();
+                }
+                let mut timings = timings.lock().unwrap();
+                timings.generate.extend(local.generate);
+                timings.write += local.write;
+            });
+        }
+    });
+
+    if let Some(message) = failure.into_inner().unwrap() {
+        return Err(message);
+    }
+
+    let wall = started.elapsed();
+    let mut timings = timings.into_inner().unwrap();
+    timings.generate.sort();
+    let chunks = timings.generate.len();
+    let total: Duration = timings.generate.iter().sum();
+
+    println!(
+        "pregenerated {chunks} chunks in {} regions to {} with {threads} threads",
+        regions.len(),
+        store.root().display()
+    );
+    println!(
+        "  wall {:.2}s, {:.0} chunks/s",
+        wall.as_secs_f64(),
+        chunks as f64 / wall.as_secs_f64().max(f64::EPSILON)
+    );
+    println!(
+        "  generate per chunk: mean {:.2}ms, p50 {:.2}ms, p95 {:.2}ms, max {:.2}ms",
+        ms(total) / chunks.max(1) as f64,
+        ms(percentile(&timings.generate, 0.5)),
+        ms(percentile(&timings.generate, 0.95)),
+        ms(percentile(&timings.generate, 1.0)),
+    );
+    println!("  region writes: {:.2}ms total", ms(timings.write));
+    Ok(())
+}
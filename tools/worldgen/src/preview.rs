@@ -0,0 +1,72 @@
+use std::fs::File;
+use std::io::BufWriter;
+
+use engine::cli::Args;
+use engine::terrain::{
+    constants::{CHUNK_DEPTH, CHUNK_WIDTH},
+    generator::{generate_height, TerrainNoise},
+};
+use crate::generator_params;
+
+pub const USAGE: &str = "worldgen preview --seed <i32> --radius <chunks> --out <file.png> [--sea-level <y>] [generator options]";
+
+/// Height bands above the sea level, lowest first
+const BANDS: [(i32, [u8; 3]); 4] = [
+    (2, [218, 200, 140]),  // Beach
+    (10, [96, 158, 70]),   // Grass
+    (16, [60, 112, 52]),   // Forest
+    (i32::MAX, [130, 126, 120]), // Rock
+];
+
+fn color(height: i32, sea_level: i32) -> [u8; 3] {
+    if height < sea_level {
+        // Deeper is darker
+        let depth = (sea_level - height).min(16) as f32 / 16.0;
+        let shade = |c: f32| (c * (1.0 - 0.6 * depth)) as u8;
+        return [shade(60.0), shade(110.0), shade(200.0)];
+    }
+    let above = height - sea_level;
+    let base = BANDS.iter().find(|(top, _)| above < *top).map(|(_, c)| *c).unwrap_or([255; 3]);
+    // Slight brightness ramp inside a band so relief stays readable
+    let lift = 1.0 + (above % 8) as f32 * 0.02;
+    base.map(|c| (c as f32 * lift).min(255.0) as u8)
+}
+
+/// One pixel per column, north (-Z) up
+pub fn run(args: &Args) -> Result<(), String> {
+    let seed: i32 = args.required("seed")?;
+    let radius: i32 = args.required("radius")?;
+    let out: String = args.required("out")?;
+    let sea_level: i32 = args.get_or("sea-level", 56)?;
+    let noise = TerrainNoise::with_params(seed, &generator_params(args)?);
+
+    let min_x = -radius * CHUNK_WIDTH;
+    let min_z = -radius * CHUNK_DEPTH;
+    let width = ((2 * radius + 1) * CHUNK_WIDTH) as u32;
+    let height = ((2 * radius + 1) * CHUNK_DEPTH) as u32;
+
+    let mut pixels = Vec::with_capacity((width * height * 3) as usize);
+    let (mut lowest, mut highest) = (i32::MAX, i32::MIN);
+    for pz in 0..height as i32 {
+        for px in 0..width as i32 {
+            let h = generate_height(&noise, (min_x + px) as f32, (min_z + pz) as f32);
+            lowest = lowest.min(h);
+            highest = highest.max(h);
+            pixels.extend_from_slice(&color(h, sea_level));
+        }
+    }
+
+    let file = File::create(&out).map_err(|e| format!("cannot create {out}: {e}"))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let png_err = |e: png::EncodingError| format!("cannot write {out}: {e}");
+    encoder
+        .write_header()
+        .map_err(png_err)?
+        .write_image_data(&pixels)
+        .map_err(png_err)?;
+
+    println!("wrote {width}x{height} preview to {out}, heights {lowest}..={highest}, sea level {sea_level}");
+    Ok(())
+}